@@ -1,5 +1,10 @@
-use criterion::{Criterion, criterion_group, criterion_main};
-use vdb::{Metadata, Metric, VectorDB};
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::vectors;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+use vdb::{Id, Kernel, Metadata, Metric, Params, VectorDB};
 
 fn search_benchmark(c: &mut Criterion) {
     let path = "bench.vdb";
@@ -20,5 +25,67 @@ fn search_benchmark(c: &mut Criterion) {
     std::fs::remove_file(path).unwrap();
 }
 
-criterion_group!(benches, search_benchmark);
+/// Search latency at growing collection sizes. It depends on `k` and the
+/// beam width, so it should stay roughly flat as entries are added.
+fn scaling_benchmark(c: &mut Criterion) {
+    const DIM: usize = 16;
+    let params = Params {
+        ef_construction: 40,
+        ef_search: 40,
+    };
+    let queries = vectors(100, DIM, 2);
+    let mut group = c.benchmark_group("scaling");
+    for n in [10_000, 100_000, 1_000_000] {
+        let path = format!("bench_{n}.vdb");
+        let _ = std::fs::remove_file(&path);
+        let mut db = VectorDB::<12, 24>::open_with_params(&path, Metric::Euclidean, params).unwrap();
+        // batches write the file once each instead of once per entry
+        let data = vectors(n, DIM, 1);
+        for (c, chunk) in data.chunks(10_000).enumerate() {
+            let entries = chunk
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (Id::Int(c * 10_000 + i), v.clone(), Metadata::default()))
+                .collect();
+            db.add_batch(entries).unwrap();
+        }
+        let mut next = queries.iter().cycle();
+        group.throughput(Throughput::Elements(1));
+        group.bench_with_input(BenchmarkId::new("search", n), &n, |b, _| {
+            b.iter(|| db.search(next.next().unwrap(), 10).unwrap())
+        });
+        group.throughput(Throughput::Elements(queries.len() as u64));
+        group.bench_with_input(BenchmarkId::new("batch", n), &n, |b, _| {
+            b.iter(|| db.search_batch(&queries, 10).unwrap())
+        });
+        std::fs::remove_file(&path).unwrap();
+    }
+    group.finish();
+}
+
+type KernelFn = fn(Kernel, &[f32], &[f32]) -> f32;
+
+fn kernel_benchmark(c: &mut Criterion) {
+    let ops: [(&str, KernelFn); 3] = [
+        ("cosine", Kernel::dot),
+        ("euclidean", Kernel::squared_euclidean),
+        ("manhattan", Kernel::manhattan),
+    ];
+    for (name, op) in ops {
+        let mut group = c.benchmark_group(name);
+        for dim in [128, 384, 768, 1024, 1536, 3072] {
+            let a: Vec<f32> = (0..dim).map(|i| (i as f32).sin()).collect();
+            let b: Vec<f32> = (0..dim).map(|i| (i as f32).cos()).collect();
+            group.throughput(Throughput::Elements(dim as u64));
+            for kernel in Kernel::available() {
+                group.bench_with_input(BenchmarkId::new(kernel.name(), dim), &dim, |bench, _| {
+                    bench.iter(|| op(kernel, black_box(&a), black_box(&b)))
+                });
+            }
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, search_benchmark, scaling_benchmark, kernel_benchmark);
 criterion_main!(benches);
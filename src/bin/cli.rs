@@ -1,5 +1,14 @@
-use clap::{Parser, Subcommand};
-use vdb::{Metadata, Metric, VectorDB};
+use clap::{Args, Parser, Subcommand};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+use vdb::{
+    is_table, read_ground_truth, read_ids, stored_m, BinaryVector, CollectionOptions, Database, ElementType,
+    Evaluation, Id, IdKind, IndexKind, Line, Metadata, Metric, NpyWriter, Params, Quantization,
+    RecordReader, RecordWriter, TableReader, TableWriter, TextField, VectorDB, VectorFile,
+    AUTOTUNE_K, DEFAULT_COLLECTION, DEFAULT_EF_SWEEP, DEFAULT_OVERSAMPLING,
+};
 
 #[derive(Parser)]
 #[command(name = "vdb")]
@@ -12,57 +21,659 @@ struct Cli {
 enum Commands {
     Add {
         path: String,
-        id: usize,
+        id: String,
+        /// Comma-separated floats, or a string of 0 and 1 for binary
+        /// collections.
         vector: String,
         label: String,
+        #[arg(long, default_value = DEFAULT_COLLECTION)]
+        collection: String,
+        #[arg(long)]
+        namespace: Option<String>,
     },
     Search {
         path: String,
+        /// Comma-separated floats, or a string of 0 and 1 for binary
+        /// collections.
         vector: String,
         k: usize,
+        #[arg(long, default_value = DEFAULT_COLLECTION)]
+        collection: String,
+        /// Restrict the search to these namespaces.
+        #[arg(long)]
+        namespace: Vec<String>,
+        /// Candidates rescored per result in quantized collections.
+        #[arg(long, default_value_t = DEFAULT_OVERSAMPLING)]
+        oversampling: f32,
     },
     Remove {
         path: String,
-        id: usize,
+        id: String,
+        #[arg(long, default_value = DEFAULT_COLLECTION)]
+        collection: String,
+    },
+    /// Create a database file holding one collection.
+    Create {
+        path: String,
+        #[arg(long, default_value = DEFAULT_COLLECTION)]
+        collection: String,
+        #[command(flatten)]
+        settings: CollectionSettings,
+    },
+    /// Create a new collection in the database file.
+    CreateCollection {
+        path: String,
+        name: String,
+        #[command(flatten)]
+        settings: CollectionSettings,
+    },
+    /// Keyword search over the indexed metadata fields.
+    SearchText {
+        path: String,
+        query: String,
+        k: usize,
+        #[arg(long, default_value = DEFAULT_COLLECTION)]
+        collection: String,
+    },
+    /// Delete a collection and all of its entries.
+    DropCollection {
+        path: String,
+        name: String,
+    },
+    /// List the collections in the database file.
+    Collections {
+        path: String,
+    },
+    /// Measure recall@k and latency at a sweep of ef_search values against
+    /// the exact nearest neighbors of a query set.
+    Eval {
+        path: String,
+        /// Query vectors: an .fvecs, .bvecs or ann-benchmarks HDF5 file
+        /// (its test dataset), or a text file with one comma-separated query
+        /// per line.
+        queries: String,
+        /// Nearest neighbors of the queries, as an .ivecs file or the
+        /// neighbors dataset of an HDF5 file, instead of a full scan.
+        #[arg(long)]
+        ground_truth: Option<String>,
+        #[arg(long, default_value_t = 10)]
+        k: usize,
+        /// Comma-separated ef_search values to measure.
+        #[arg(long, value_delimiter = ',', default_values_t = DEFAULT_EF_SWEEP)]
+        ef: Vec<usize>,
+        #[arg(long, default_value = DEFAULT_COLLECTION)]
+        collection: String,
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Find and store the smallest ef_search that reaches a target recall
+    /// on a sample of queries.
+    Autotune {
+        path: String,
+        /// Query vectors, in any format `eval` reads.
+        queries: String,
+        /// Recall of the nearest AUTOTUNE_K entries to reach.
+        #[arg(long, default_value_t = 0.95)]
+        recall: f64,
+        #[arg(long, default_value = DEFAULT_COLLECTION)]
+        collection: String,
+    },
+    /// Bulk-load a .jsonl, .csv, Parquet or Arrow IPC file of records, or an
+    /// .fvecs, .bvecs, .ivecs, HDF5, .npy or .npz file whose vectors get
+    /// their row numbers as ids unless `--ids` gives them.
+    Import {
+        path: String,
+        file: String,
+        /// Dataset of an HDF5 file, or array of an .npz file, to load.
+        #[arg(long, default_value = "train")]
+        dataset: String,
+        /// Ids of the vectors of a NumPy file: an .npy file, or the name of
+        /// another array of the .npz file being loaded.
+        #[arg(long)]
+        ids: Option<String>,
+        #[arg(long, default_value = DEFAULT_COLLECTION)]
+        collection: String,
+        /// Metric of the collection, if it does not exist yet.
+        #[arg(long, default_value = "cosine")]
+        metric: Metric,
+        /// Id kind of the collection, if it does not exist yet.
+        #[arg(long, default_value = "int")]
+        id_kind: IdKind,
+        /// Vectors written to the file at once.
+        #[arg(long, default_value_t = 10_000)]
+        batch: usize,
+        /// File collecting the lines of a .jsonl or .csv file that could not
+        /// be imported, or the rows of a Parquet or Arrow file as a .jsonl
+        /// file. Defaults to the file name followed by `.rejects`, or
+        /// `.rejects.jsonl` for rows.
+        #[arg(long)]
+        rejects: Option<String>,
+    },
+    /// Write the entries of a collection to a .jsonl, .csv, Parquet or Arrow
+    /// IPC file, or their vectors to an .npy file and their ids and metadata
+    /// to a .jsonl file beside it.
+    Export {
+        path: String,
+        file: String,
+        #[arg(long, default_value = DEFAULT_COLLECTION)]
+        collection: String,
     },
 }
 
+/// Settings of a new collection.
+#[derive(Args)]
+struct CollectionSettings {
+    /// Distance metric: cosine, euclidean, manhattan, chebyshev or
+    /// minkowski:<p>.
+    #[arg(long, default_value = "cosine")]
+    metric: Metric,
+    /// Beam width of the searches that insert vectors into the graph.
+    #[arg(long, default_value_t = Params::default().ef_construction)]
+    ef_construction: usize,
+    /// Beam width of queries.
+    #[arg(long, default_value_t = Params::default().ef_search)]
+    ef_search: usize,
+    /// Links per node of the graph: 8, 12, 16, 24, 32, 48 or 64. All
+    /// collections of a file share it; defaults to the one of the file, or
+    /// 12 in a new file.
+    #[arg(long)]
+    m: Option<usize>,
+    /// Kind of entry ids: int, string or uuid.
+    #[arg(long, default_value = "int")]
+    id_kind: IdKind,
+    /// Metadata field to index for keyword search: label or description.
+    #[arg(long)]
+    text_field: Vec<TextField>,
+    /// Vector quantization in the index: none or binary.
+    #[arg(long, default_value = "none")]
+    quantization: Quantization,
+    /// Read cosine vectors back at unit length instead of as they were
+    /// added.
+    #[arg(long)]
+    normalize: bool,
+    /// Component type of the stored vectors: f32, f16 or bf16.
+    #[arg(long, default_value = "f32")]
+    element_type: ElementType,
+    /// Index over the vectors: hnsw or ivf:<nlist>:<nprobe>.
+    #[arg(long, default_value = "hnsw")]
+    index: IndexKind,
+}
+
+impl Commands {
+    /// The database file the command works on.
+    fn path(&self) -> &str {
+        match self {
+            Commands::Add { path, .. }
+            | Commands::Search { path, .. }
+            | Commands::Remove { path, .. }
+            | Commands::Create { path, .. }
+            | Commands::CreateCollection { path, .. }
+            | Commands::SearchText { path, .. }
+            | Commands::DropCollection { path, .. }
+            | Commands::Collections { path }
+            | Commands::Eval { path, .. }
+            | Commands::Autotune { path, .. }
+            | Commands::Import { path, .. }
+            | Commands::Export { path, .. } => path,
+        }
+    }
+}
+
 fn parse_vector(s: &str) -> Vec<f32> {
     s.split(',').filter_map(|x| x.parse().ok()).collect()
 }
 
+/// Parses a binary vector written as a string of `0` and `1`.
+fn parse_bits(s: &str) -> anyhow::Result<BinaryVector> {
+    let bits = s
+        .chars()
+        .map(|c| match c {
+            '0' => Ok(false),
+            '1' => Ok(true),
+            _ => Err(anyhow::anyhow!("invalid bit: {c}")),
+        })
+        .collect::<anyhow::Result<Vec<bool>>>()?;
+    Ok(BinaryVector::from_bits(&bits))
+}
+
+/// Reads the query vectors of a vector file, or one comma-separated vector
+/// per non-empty line of a text file.
+fn read_vectors(path: &str) -> anyhow::Result<Vec<Vec<f32>>> {
+    if VectorFile::supports(path) {
+        return VectorFile::open(path, "test")?.collect();
+    }
+    let text = std::fs::read_to_string(path)?;
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            line.split(',')
+                .map(|x| x.trim().parse::<f32>().map_err(|e| anyhow::anyhow!("{e}: {x}")))
+                .collect()
+        })
+        .collect()
+}
+
+fn print_evaluations(evaluations: &[Evaluation], k: usize, json: bool) {
+    let micros = |d: Duration| d.as_secs_f64() * 1e6;
+    if json {
+        let rows: Vec<serde_json::Value> = evaluations
+            .iter()
+            .map(|e| {
+                serde_json::json!({
+                    "ef_search": e.ef_search,
+                    "recall": e.recall,
+                    "qps": e.qps,
+                    "p50_us": micros(e.p50),
+                    "p99_us": micros(e.p99),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::Value::Array(rows));
+        return;
+    }
+    let recall = format!("recall@{k}");
+    println!("{:>9} {:>10} {:>10} {:>10} {:>10}", "ef_search", recall, "qps", "p50 us", "p99 us");
+    for e in evaluations {
+        println!(
+            "{:>9} {:>10.4} {:>10.0} {:>10.1} {:>10.1}",
+            e.ef_search,
+            e.recall,
+            e.qps,
+            micros(e.p50),
+            micros(e.p99)
+        );
+    }
+}
+
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
-    match cli.command {
+    // the graph is built for M at compile time, so the file picks the build
+    let m = match &cli.command {
+        Commands::Create { path, .. } if Path::new(path).exists() => {
+            anyhow::bail!("{path} already exists; add collections with create-collection");
+        }
+        Commands::Create { settings, .. } | Commands::CreateCollection { settings, .. }
+            if settings.m.is_some() =>
+        {
+            settings.m
+        }
+        command => stored_m(command.path())?,
+    };
+    match m.unwrap_or(vdb::M) {
+        8 => run::<8, 16>(cli.command),
+        12 => run::<12, 24>(cli.command),
+        16 => run::<16, 32>(cli.command),
+        24 => run::<24, 48>(cli.command),
+        32 => run::<32, 64>(cli.command),
+        48 => run::<48, 96>(cli.command),
+        64 => run::<64, 128>(cli.command),
+        m => anyhow::bail!("M must be 8, 12, 16, 24, 32, 48 or 64, not {m}"),
+    }
+}
+
+fn run<const M: usize, const M0: usize>(command: Commands) -> anyhow::Result<()> {
+    match command {
         Commands::Add {
             path,
             id,
             vector,
             label,
+            collection,
+            namespace,
         } => {
-            let mut db = VectorDB::<12, 24>::open(&path, Metric::Cosine)?;
-            let vec = parse_vector(&vector);
-            db.add(
-                id,
-                vec,
-                Metadata {
-                    label,
-                    description: None,
-                },
-            )?;
-        }
-        Commands::Search { path, vector, k } => {
-            let mut db = VectorDB::<12, 24>::open(&path, Metric::Cosine)?;
+            let mut db = Database::<M, M0>::open(&path)?;
+            let metadata = Metadata {
+                label,
+                description: None,
+            };
+            let c = db.collection_mut(&collection)?;
+            let id = Id::parse(&id, c.options().id_kind)?;
+            if c.metric().is_binary() {
+                if namespace.is_some() {
+                    return Err(anyhow::anyhow!("namespaces need an f32 collection"));
+                }
+                c.add_binary(id, parse_bits(&vector)?, metadata)?;
+            } else {
+                let vec = parse_vector(&vector);
+                match namespace {
+                    Some(ns) => c.add_in(&ns, id, vec, metadata)?,
+                    None => c.add(id, vec, metadata)?,
+                }
+            }
+        }
+        Commands::Search {
+            path,
+            vector,
+            k,
+            collection,
+            namespace,
+            oversampling,
+        } => {
+            let db = Database::<M, M0>::open(&path)?;
             let vec = parse_vector(&vector);
-            let results = db.search(&vec, k)?;
+            let c = db.collection(&collection)?;
+            let results = if c.metric().is_binary() {
+                c.search_binary(&parse_bits(&vector)?, k)?
+            } else if namespace.is_empty() {
+                c.search_with_oversampling(&vec, k, oversampling)?
+            } else {
+                let namespaces: Vec<&str> = namespace.iter().map(|s| s.as_str()).collect();
+                c.search_in(&namespaces, &vec, k)?
+            };
+            if !results.complete {
+                eprintln!("only {} of {k} results found", results.len());
+            }
             for r in results {
                 println!("{} {}", r.id, r.distance);
             }
         }
-        Commands::Remove { path, id } => {
-            let mut db = VectorDB::<12, 24>::open(&path, Metric::Cosine)?;
-            db.remove(id)?;
+        Commands::Remove {
+            path,
+            id,
+            collection,
+        } => {
+            let mut db = Database::<M, M0>::open(&path)?;
+            let c = db.collection_mut(&collection)?;
+            let id = Id::parse(&id, c.options().id_kind)?;
+            c.remove(id)?;
+        }
+        Commands::Create {
+            path,
+            collection: name,
+            settings,
+        }
+        | Commands::CreateCollection {
+            path,
+            name,
+            settings,
+        } => {
+            let mut db = Database::<M, M0>::open(&path)?;
+            let params = Params {
+                ef_construction: settings.ef_construction,
+                ef_search: settings.ef_search,
+            };
+            let options = CollectionOptions {
+                id_kind: settings.id_kind,
+                text_fields: settings.text_field,
+                quantization: settings.quantization,
+                keep_magnitude: !settings.normalize,
+                element_type: settings.element_type,
+                index: settings.index,
+                m: Some(M),
+            };
+            db.create_collection_with_options(&name, settings.metric, params, options)?;
+        }
+        Commands::SearchText {
+            path,
+            query,
+            k,
+            collection,
+        } => {
+            let db = Database::<M, M0>::open(&path)?;
+            for r in db.collection(&collection)?.search_text(&query, k)? {
+                println!("{} {}", r.id, r.score);
+            }
+        }
+        Commands::DropCollection { path, name } => {
+            let mut db = Database::<M, M0>::open(&path)?;
+            db.drop_collection(&name)?;
+        }
+        Commands::Collections { path } => {
+            let db = Database::<M, M0>::open(&path)?;
+            for name in db.collection_names() {
+                let c = db.collection(name)?;
+                println!("{} {} {} {}", name, c.metric(), c.dimension(), c.len());
+            }
+        }
+        Commands::Eval {
+            path,
+            queries,
+            ground_truth,
+            k,
+            ef,
+            collection,
+            json,
+        } => {
+            let db = Database::<M, M0>::open(&path)?;
+            let c = db.collection(&collection)?;
+            let queries = read_vectors(&queries)?;
+            let truth = match ground_truth {
+                Some(file) => read_ground_truth(file)?,
+                None => c.ground_truth(&queries, k)?,
+            };
+            print_evaluations(&c.evaluate(&queries, &truth, k, &ef)?, k, json);
+        }
+        Commands::Autotune {
+            path,
+            queries,
+            recall,
+            collection,
+        } => {
+            let mut db = Database::<M, M0>::open(&path)?;
+            let queries = read_vectors(&queries)?;
+            let tuning = db.collection_mut(&collection)?.autotune(recall, &queries)?;
+            print_evaluations(&[tuning.evaluation], AUTOTUNE_K, false);
+            if let Some(r) = tuning.rebuild {
+                let (m, ef) = (r.m, r.ef_construction);
+                println!("a rebuild with M = {m} and ef_construction = {ef} needs a smaller beam");
+            }
+        }
+        Commands::Import {
+            path,
+            file,
+            dataset,
+            ids,
+            collection,
+            metric,
+            id_kind,
+            batch,
+            rejects,
+        } => {
+            let mut db = Database::<M, M0>::open(&path)?;
+            if db.collection(&collection).is_err() {
+                let options = CollectionOptions {
+                    id_kind,
+                    m: Some(M),
+                    ..CollectionOptions::default()
+                };
+                db.create_collection_with_options(&collection, metric, Params::default(), options)?;
+            }
+            let c = db.collection_mut(&collection)?;
+            if VectorFile::supports(&file) {
+                let ids = match ids {
+                    Some(array) if file.ends_with(".npz") => {
+                        Some(read_ids(&file, &array, c.options().id_kind)?)
+                    }
+                    Some(ids) => Some(read_ids(&ids, "", c.options().id_kind)?),
+                    None => None,
+                };
+                import_vectors(c, &file, &dataset, ids, batch)?;
+            } else if is_table(&file) {
+                let rejects = rejects.unwrap_or_else(|| format!("{file}.rejects.jsonl"));
+                import_table(c, &file, &rejects, batch)?;
+            } else {
+                let rejects = rejects.unwrap_or_else(|| format!("{file}.rejects"));
+                import_records(c, &file, &rejects, batch)?;
+            }
+        }
+        Commands::Export {
+            path,
+            file,
+            collection,
+        } => {
+            let db = Database::<M, M0>::open(&path)?;
+            let c = db.collection(&collection)?;
+            if c.metric().is_binary() {
+                anyhow::bail!("binary collections cannot be exported");
+            }
+            if c.has_multi_vector_entries() {
+                anyhow::bail!("collections with multi-vector entries cannot be exported");
+            }
+            let total = c.len();
+            let mut output = if is_table(&file) {
+                let table = TableWriter::create(&file, c.options().id_kind, c.dimension())?;
+                Output::Table(Box::new(table))
+            } else if file.ends_with(".npy") {
+                let metadata = Path::new(&file).with_extension("jsonl");
+                let npy = NpyWriter::create(&file, total, c.dimension())?;
+                Output::Npy(npy, RecordWriter::create_metadata(metadata)?)
+            } else {
+                Output::Records(RecordWriter::create(&file)?)
+            };
+            if c.metric() == Metric::Cosine && !c.options().keep_magnitude {
+                eprintln!(
+                    "warning: {collection} does not keep magnitudes, so its vectors are \
+                     exported at unit length"
+                );
+            }
+            for (i, entry) in c.iter_entries().enumerate() {
+                let (id, vectors, metadata) = entry?;
+                output.write(id, &vectors[0], metadata)?;
+                if (i + 1) % 10_000 == 0 {
+                    eprintln!("exported {} of {total}", i + 1);
+                }
+            }
+            output.finish()?;
+            eprintln!("exported {total} of {total}");
+        }
+    }
+    Ok(())
+}
+
+/// Adds the vectors of a benchmark or NumPy file with the given ids, or
+/// their row numbers.
+fn import_vectors<const M: usize, const M0: usize>(
+    c: &mut VectorDB<M, M0>,
+    file: &str,
+    dataset: &str,
+    ids: Option<Vec<Id>>,
+    batch: usize,
+) -> anyhow::Result<()> {
+    let mut vectors = VectorFile::open(file, dataset)?;
+    let total = vectors.len();
+    if let Some(ids) = &ids {
+        if ids.len() != total {
+            anyhow::bail!("{} ids for {total} vectors", ids.len());
+        }
+    }
+    let mut done = 0;
+    loop {
+        let rows = vectors.by_ref().take(batch.max(1));
+        let rows: Vec<Vec<f32>> = rows.collect::<anyhow::Result<_>>()?;
+        if rows.is_empty() {
+            return Ok(());
         }
+        let entries = rows
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let id = ids.as_ref().map_or(Id::Int(done + i), |ids| ids[done + i].clone());
+                (id, v, Metadata::default())
+            })
+            .collect::<Vec<_>>();
+        done += entries.len();
+        c.add_batch(entries)?;
+        eprintln!("imported {done} of {total}");
     }
+}
+
+/// Adds the records of a .jsonl or .csv file. Lines that cannot be added are
+/// reported and copied to `rejects`, which is only created if there are any.
+fn import_records<const M: usize, const M0: usize>(
+    c: &mut VectorDB<M, M0>,
+    file: &str,
+    rejects: &str,
+    batch: usize,
+) -> anyhow::Result<()> {
+    let reader = RecordReader::open(file, c.options().id_kind)?;
+    let header = reader.header().map(String::from);
+    let mut out: Option<BufWriter<File>> = None;
+    let mut rejected = 0;
+    let reject = |line: &Line, e: &anyhow::Error| -> anyhow::Result<()> {
+        eprintln!("line {}: {e}", line.number);
+        let out = match &mut out {
+            Some(out) => out,
+            None => {
+                let mut file = BufWriter::new(File::create(rejects)?);
+                if let Some(header) = &header {
+                    writeln!(file, "{header}")?;
+                }
+                out.insert(file)
+            }
+        };
+        writeln!(out, "{}", line.text)?;
+        rejected += 1;
+        Ok(())
+    };
+    let added = c.import(reader, batch, reject, |read| eprintln!("read {read} lines"))?;
+    if let Some(mut out) = out {
+        out.flush()?;
+    }
+    eprintln!("imported {added} entries, {rejected} rejected");
     Ok(())
 }
+
+/// Adds the rows of a Parquet or Arrow IPC file. Rows that cannot be added
+/// are reported, and those that could be read are copied to `rejects` as
+/// JSON Lines, which is only created if there are any.
+fn import_table<const M: usize, const M0: usize>(
+    c: &mut VectorDB<M, M0>,
+    file: &str,
+    rejects: &str,
+    batch: usize,
+) -> anyhow::Result<()> {
+    let reader = TableReader::open(file, c.options().id_kind)?;
+    let mut out: Option<RecordWriter> = None;
+    let mut rejected = 0;
+    let reject = |row: &Line, e: &anyhow::Error| -> anyhow::Result<()> {
+        eprintln!("row {}: {e}", row.number);
+        rejected += 1;
+        if let Ok(r) = &row.record {
+            let out = match &mut out {
+                Some(out) => out,
+                None => out.insert(RecordWriter::create(rejects)?),
+            };
+            out.write(&r.id, &r.vector, &r.metadata)?;
+        }
+        Ok(())
+    };
+    let added = c.import(reader, batch, reject, |read| eprintln!("read {read} rows"))?;
+    if let Some(out) = out {
+        out.finish()?;
+    }
+    eprintln!("imported {added} entries, {rejected} rejected");
+    Ok(())
+}
+
+/// Where `vdb export` writes entries.
+enum Output {
+    Records(RecordWriter),
+    Table(Box<TableWriter>),
+    /// Vectors, and ids and metadata.
+    Npy(NpyWriter, RecordWriter),
+}
+
+impl Output {
+    fn write(&mut self, id: &Id, vector: &[f32], metadata: &Metadata) -> anyhow::Result<()> {
+        match self {
+            Output::Records(writer) => writer.write(id, vector, metadata),
+            Output::Table(writer) => writer.write(id, vector, metadata),
+            Output::Npy(npy, writer) => {
+                npy.write(vector)?;
+                writer.write(id, vector, metadata)
+            }
+        }
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        match self {
+            Output::Records(writer) => writer.finish(),
+            Output::Table(writer) => writer.finish(),
+            Output::Npy(npy, writer) => {
+                npy.finish()?;
+                writer.finish()
+            }
+        }
+    }
+}
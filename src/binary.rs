@@ -0,0 +1,96 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+/// Packed bit vector, such as a perceptual hash or a binary-quantized
+/// embedding. Bit `i` is bit `i % 64` of word `i / 64`; bits past the length
+/// are zero.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+pub struct BinaryVector {
+    len: u32,
+    words: Vec<u64>,
+}
+
+impl BinaryVector {
+    pub fn new(len: usize, words: Vec<u64>) -> Result<Self> {
+        if words.len() != len.div_ceil(64) {
+            return Err(anyhow!("word count does not match bit length"));
+        }
+        if !len.is_multiple_of(64) && words[len / 64] >> (len % 64) != 0 {
+            return Err(anyhow!("bits set past the bit length"));
+        }
+        Ok(Self::from_words(len, words))
+    }
+
+    pub(crate) fn from_words(len: usize, words: Vec<u64>) -> Self {
+        Self {
+            len: len as u32,
+            words,
+        }
+    }
+
+    pub fn from_bits(bits: &[bool]) -> Self {
+        let mut words = vec![0u64; bits.len().div_ceil(64)];
+        for (i, _) in bits.iter().enumerate().filter(|(_, &b)| b) {
+            words[i / 64] |= 1 << (i % 64);
+        }
+        Self::from_words(bits.len(), words)
+    }
+
+    /// Number of bits.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub(crate) fn into_words(self) -> Vec<u64> {
+        self.words
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        i < self.len() && self.words[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.words.iter().map(|w| w.count_ones()).sum()
+    }
+
+    /// Number of differing bits.
+    pub fn hamming(&self, other: &BinaryVector) -> u32 {
+        hamming(&self.words, &other.words)
+    }
+
+    /// Jaccard (Tanimoto) similarity: shared set bits over bits set in
+    /// either vector. Two vectors without set bits are identical.
+    pub fn jaccard(&self, other: &BinaryVector) -> f32 {
+        jaccard(&self.words, &other.words)
+    }
+}
+
+/// Sign bits of `vector`, set for positive components.
+pub(crate) fn sign_bits(vector: &[f32]) -> Vec<u64> {
+    let mut words = vec![0u64; vector.len().div_ceil(64)];
+    for (i, _) in vector.iter().enumerate().filter(|(_, &x)| x > 0.0) {
+        words[i / 64] |= 1 << (i % 64);
+    }
+    words
+}
+
+pub(crate) fn hamming(a: &[u64], b: &[u64]) -> u32 {
+    a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
+pub(crate) fn jaccard(a: &[u64], b: &[u64]) -> f32 {
+    let (mut shared, mut union) = (0, 0);
+    for (x, y) in a.iter().zip(b) {
+        shared += (x & y).count_ones();
+        union += (x | y).count_ones();
+    }
+    if union == 0 { 1.0 } else { shared as f32 / union as f32 }
+}
@@ -0,0 +1,105 @@
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::params::{CollectionOptions, Params};
+use crate::storage::{Header, Storage};
+use crate::types::Metric;
+use crate::vector_db::VectorDB;
+
+/// A database file holding any number of named collections. Each collection
+/// has its own metric, dimension, parameters and index.
+pub struct Database<const M: usize = 12, const M0: usize = 24> {
+    storage: Storage,
+    collections: BTreeMap<String, VectorDB<M, M0>>,
+    next_id: u32,
+}
+
+impl<const M: usize, const M0: usize> Database<M, M0> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let (storage, catalog) = Storage::open_or_create(path)?;
+        let mut collections = BTreeMap::new();
+        for stored in catalog.collections {
+            let name = stored.header.name.clone();
+            collections.insert(name, VectorDB::load(storage.clone(), stored)?);
+        }
+        Ok(Self {
+            storage,
+            collections,
+            next_id: catalog.next_id,
+        })
+    }
+
+    pub fn create_collection(
+        &mut self,
+        name: &str,
+        metric: Metric,
+        params: Params,
+    ) -> Result<&mut VectorDB<M, M0>> {
+        self.create_collection_with_options(name, metric, params, CollectionOptions::default())
+    }
+
+    pub fn create_collection_with_options(
+        &mut self,
+        name: &str,
+        metric: Metric,
+        params: Params,
+        options: CollectionOptions,
+    ) -> Result<&mut VectorDB<M, M0>> {
+        if self.collections.contains_key(name) {
+            return Err(anyhow!("collection already exists"));
+        }
+        let header = Header {
+            id: self.next_id,
+            name: name.to_string(),
+            metric,
+            dim: 0,
+            params,
+            options,
+        };
+        let db = VectorDB::create(self.storage.clone(), header)?;
+        self.next_id += 1;
+        Ok(self.collections.entry(name.to_string()).or_insert(db))
+    }
+
+    pub fn collection(&self, name: &str) -> Result<&VectorDB<M, M0>> {
+        self.collections
+            .get(name)
+            .ok_or(anyhow!("collection not found"))
+    }
+
+    pub fn collection_mut(&mut self, name: &str) -> Result<&mut VectorDB<M, M0>> {
+        self.collections
+            .get_mut(name)
+            .ok_or(anyhow!("collection not found"))
+    }
+
+    pub fn drop_collection(&mut self, name: &str) -> Result<()> {
+        let db = self
+            .collections
+            .remove(name)
+            .ok_or(anyhow!("collection not found"))?;
+        self.storage.drop_collection(db.collection_id())
+    }
+
+    /// Names of all collections, in lexicographic order.
+    pub fn collection_names(&self) -> impl Iterator<Item = &str> {
+        self.collections.keys().map(|k| k.as_str())
+    }
+}
+
+/// The `M` that the collections of the database file at `path` record, if
+/// any of them does, to learn which [`Database`] opens the file. Reads the
+/// whole file but never writes to it; a missing file records none.
+pub fn stored_m<P: AsRef<Path>>(path: P) -> Result<Option<usize>> {
+    if !path.as_ref().exists() {
+        return Ok(None);
+    }
+    let catalog = Storage::read(path)?;
+    let mut stored = catalog.collections.iter().filter_map(|c| c.header.options.m);
+    let m = stored.next();
+    if stored.any(|other| Some(other) != m) {
+        return Err(anyhow!("collections of the file need different M"));
+    }
+    Ok(m)
+}
@@ -0,0 +1,120 @@
+use crate::params::ElementType;
+use anyhow::{Result, anyhow};
+use half::slice::HalfFloatSliceExt;
+use half::{bf16, f16};
+
+/// Component type of vectors held in the index.
+pub trait Element: Copy + Default + Send + Sync + 'static {
+    fn from_f32(x: f32) -> Self;
+
+    /// Widens `src` into `dst`, which has the same length.
+    fn widen_into(src: &[Self], dst: &mut [f32]);
+
+    /// The components themselves, when they already are `f32`.
+    fn as_f32(slice: &[Self]) -> Option<&[f32]>;
+}
+
+impl Element for f32 {
+    fn from_f32(x: f32) -> Self {
+        x
+    }
+
+    fn widen_into(src: &[Self], dst: &mut [f32]) {
+        dst.copy_from_slice(src);
+    }
+
+    fn as_f32(slice: &[Self]) -> Option<&[f32]> {
+        Some(slice)
+    }
+}
+
+impl Element for f16 {
+    fn from_f32(x: f32) -> Self {
+        f16::from_f32(x)
+    }
+
+    fn widen_into(src: &[Self], dst: &mut [f32]) {
+        src.convert_to_f32_slice(dst);
+    }
+
+    fn as_f32(_: &[Self]) -> Option<&[f32]> {
+        None
+    }
+}
+
+impl Element for bf16 {
+    fn from_f32(x: f32) -> Self {
+        bf16::from_f32(x)
+    }
+
+    fn widen_into(src: &[Self], dst: &mut [f32]) {
+        src.convert_to_f32_slice(dst);
+    }
+
+    fn as_f32(_: &[Self]) -> Option<&[f32]> {
+        None
+    }
+}
+
+pub fn narrow<T: Element>(vector: &[f32]) -> Vec<T> {
+    vector.iter().map(|&x| T::from_f32(x)).collect()
+}
+
+pub fn widen<T: Element>(vector: &[T]) -> Vec<f32> {
+    let mut out = vec![0.0; vector.len()];
+    T::widen_into(vector, &mut out);
+    out
+}
+
+/// Checks that every component of `vector` is finite and stays finite at
+/// the precision of `element_type`, which holds smaller numbers than `f32`.
+pub fn check(vector: &[f32], element_type: ElementType) -> Result<()> {
+    let fits = |x: f32| match element_type {
+        ElementType::F32 => x.is_finite(),
+        ElementType::F16 => f16::from_f32(x).is_finite(),
+        ElementType::Bf16 => bf16::from_f32(x).is_finite(),
+    };
+    match vector.iter().find(|&&x| !fits(x)) {
+        Some(x) => Err(anyhow!("vector component {x} is out of range for the element type")),
+        None => Ok(()),
+    }
+}
+
+/// Rounds `vector` in place to the precision of `element_type`, after
+/// checking that it fits.
+pub fn round(vector: &mut [f32], element_type: ElementType) -> Result<()> {
+    check(vector, element_type)?;
+    match element_type {
+        ElementType::F32 => {}
+        ElementType::F16 => vector.iter_mut().for_each(|x| *x = f16::from_f32(*x).to_f32()),
+        ElementType::Bf16 => vector.iter_mut().for_each(|x| *x = bf16::from_f32(*x).to_f32()),
+    }
+    Ok(())
+}
+
+/// Components widened per block of a distance computation; the blocks live
+/// on the stack.
+const BLOCK: usize = 256;
+
+/// Applies `partial` to `a` and `b` widened to `f32` a block at a time and
+/// folds the block results with `combine`, starting from 0. `f32` vectors
+/// are passed through whole.
+pub fn fold_widened<T: Element>(
+    a: &[T],
+    b: &[T],
+    partial: impl Fn(&[f32], &[f32]) -> f32,
+    combine: impl Fn(f32, f32) -> f32,
+) -> f32 {
+    if let (Some(a), Some(b)) = (T::as_f32(a), T::as_f32(b)) {
+        return partial(a, b);
+    }
+    let (mut x, mut y) = ([0.0; BLOCK], [0.0; BLOCK]);
+    let mut acc = 0.0;
+    for (a, b) in a.chunks(BLOCK).zip(b.chunks(BLOCK)) {
+        let n = a.len().min(b.len());
+        T::widen_into(&a[..n], &mut x[..n]);
+        T::widen_into(&b[..n], &mut y[..n]);
+        acc = combine(acc, partial(&x[..n], &y[..n]));
+    }
+    acc
+}
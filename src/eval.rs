@@ -0,0 +1,119 @@
+//! Recall and latency measurement of approximate searches against exact
+//! ground truth.
+
+use crate::types::{Id, SearchResults};
+use anyhow::{Result, anyhow};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// Beam widths [`crate::VectorDB::evaluate`] is usually run at.
+pub const DEFAULT_EF_SWEEP: [usize; 7] = [10, 20, 40, 80, 160, 320, 640];
+
+/// Number of nearest entries whose recall [`crate::VectorDB::autotune`]
+/// targets.
+pub const AUTOTUNE_K: usize = 10;
+
+/// Search quality and speed at one `ef_search`.
+#[derive(Clone, Debug)]
+pub struct Evaluation {
+    pub ef_search: usize,
+    /// Share of the true `k` nearest entries found, over all queries.
+    pub recall: f64,
+    /// Queries per second on one thread.
+    pub qps: f64,
+    pub p50: Duration,
+    pub p99: Duration,
+}
+
+/// Runs `search` for every query number and compares its results with
+/// `ground_truth`. A query whose ground truth holds fewer than `k` ids only
+/// counts those.
+pub(crate) fn measure<F>(
+    ef_search: usize,
+    ground_truth: &[Vec<Id>],
+    k: usize,
+    search: F,
+) -> Result<Evaluation>
+where
+    F: Fn(usize) -> Result<SearchResults>,
+{
+    let mut latencies = Vec::with_capacity(ground_truth.len());
+    let mut found = 0;
+    let mut expected = 0;
+    for (i, truth) in ground_truth.iter().enumerate() {
+        let start = Instant::now();
+        let results = search(i)?;
+        latencies.push(start.elapsed());
+        let truth: HashSet<&Id> = truth.iter().take(k).collect();
+        found += results.iter().filter(|r| truth.contains(&r.id)).count();
+        expected += truth.len();
+    }
+    let total: Duration = latencies.iter().sum();
+    latencies.sort_unstable();
+    Ok(Evaluation {
+        ef_search,
+        recall: if expected == 0 { 1.0 } else { found as f64 / expected as f64 },
+        qps: latencies.len() as f64 / total.as_secs_f64().max(f64::MIN_POSITIVE),
+        p50: percentile(&latencies, 0.5),
+        p99: percentile(&latencies, 0.99),
+    })
+}
+
+/// Nearest-rank percentile of sorted, non-empty `values`.
+fn percentile(values: &[Duration], p: f64) -> Duration {
+    let rank = (p * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
+
+/// Result of [`crate::VectorDB::autotune`].
+#[derive(Clone, Debug)]
+pub struct Tuning {
+    /// Measurement at the chosen `ef_search`, which the collection now uses.
+    pub evaluation: Evaluation,
+    /// Graph settings to rebuild the collection with, when the target recall
+    /// took a beam wider than the one the graph was built with.
+    pub rebuild: Option<Rebuild>,
+}
+
+/// Suggested construction settings for a new collection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rebuild {
+    pub m: usize,
+    pub ef_construction: usize,
+}
+
+/// Smallest `ef_search` from `min_ef` up whose evaluation reaches
+/// `target_recall`, found by doubling and then bisecting. `max_ef` must
+/// reach the target, as a beam covering the whole index does.
+pub(crate) fn tune<F>(
+    target_recall: f64,
+    min_ef: usize,
+    max_ef: usize,
+    mut evaluate: F,
+) -> Result<Evaluation>
+where
+    F: FnMut(usize) -> Result<Evaluation>,
+{
+    let mut high = evaluate(min_ef)?;
+    if high.recall >= target_recall {
+        return Ok(high);
+    }
+    let mut low = min_ef;
+    while high.recall < target_recall && high.ef_search < max_ef {
+        low = high.ef_search;
+        high = evaluate((low * 2).min(max_ef))?;
+    }
+    if high.recall < target_recall {
+        return Err(anyhow!("recall {target_recall} not reached"));
+    }
+    // `low` misses the target and `high` reaches it
+    while low + 1 < high.ef_search {
+        let mid = evaluate(low + (high.ef_search - low) / 2)?;
+        if mid.recall >= target_recall {
+            high = mid;
+        } else {
+            low = mid.ef_search;
+        }
+    }
+    Ok(high)
+}
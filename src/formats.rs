@@ -0,0 +1,149 @@
+//! Readers for the vector files that ANN benchmarks are distributed as, and
+//! for the record files of `vdb import` and `vdb export`.
+
+mod columnar;
+mod hdf5;
+mod npy;
+mod records;
+mod vecs;
+
+use crate::types::{Id, IdKind};
+use anyhow::{Result, anyhow};
+use hdf5::{Hdf5, Rows};
+use std::path::Path;
+use vecs::{Component, VecsReader};
+
+pub use columnar::{is_table, TableReader, TableWriter};
+pub use npy::NpyWriter;
+pub use records::{Line, Record, RecordReader, RecordWriter};
+
+/// Vectors read one at a time from an `.fvecs`, `.bvecs`, `.ivecs`, HDF5
+/// (`.hdf5`, `.h5`) or NumPy (`.npy`, `.npz`) file, chosen by the extension
+/// of its path. Components are converted to `f32`.
+pub struct VectorFile {
+    reader: Reader,
+    dim: usize,
+    len: usize,
+}
+
+enum Reader {
+    Vecs(VecsReader),
+    Hdf5(Rows),
+    Npy(npy::Rows),
+}
+
+impl VectorFile {
+    /// Opens the file at `path`. `dataset` names the dataset read from an
+    /// HDF5 file, such as `train` or `test` in those of ann-benchmarks, or
+    /// the array read from an `.npz` archive, and is ignored for other files.
+    pub fn open(path: impl AsRef<Path>, dataset: &str) -> Result<Self> {
+        let path = path.as_ref();
+        let component = match extension(path).as_str() {
+            "fvecs" => Component::F32,
+            "ivecs" => Component::I32,
+            "bvecs" => Component::U8,
+            "hdf5" | "h5" => {
+                let mut file = Hdf5::open(path)?;
+                let dataset = file.dataset(dataset)?;
+                let (dim, len) = (dataset.cols, dataset.rows);
+                let reader = Reader::Hdf5(file.into_rows(dataset)?);
+                return Ok(Self { reader, dim, len });
+            }
+            ext @ ("npy" | "npz") => {
+                let rows = npy::open(path, ext == "npz", dataset)?;
+                let (dim, len) = (rows.cols(), rows.rows());
+                let reader = Reader::Npy(rows);
+                return Ok(Self { reader, dim, len });
+            }
+            ext => return Err(anyhow!("unknown vector file format: {ext}")),
+        };
+        let reader = VecsReader::open(path, component)?;
+        Ok(Self {
+            dim: reader.dim(),
+            len: reader.len(),
+            reader: Reader::Vecs(reader),
+        })
+    }
+
+    /// Whether `path` has the extension of a format [`VectorFile`] reads.
+    pub fn supports(path: impl AsRef<Path>) -> bool {
+        let formats = ["fvecs", "ivecs", "bvecs", "hdf5", "h5", "npy", "npz"];
+        formats.contains(&extension(path.as_ref()).as_str())
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Number of vectors in the file.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn next_vector(&mut self) -> Result<Option<Vec<f32>>> {
+        match &mut self.reader {
+            Reader::Vecs(r) => r.next_f32(),
+            Reader::Hdf5(r) => r.next_f32(),
+            Reader::Npy(r) => r.next_f32(),
+        }
+    }
+
+    /// The next row as integers, for files of neighbor lists.
+    fn next_ints(&mut self) -> Result<Option<Vec<i64>>> {
+        match &mut self.reader {
+            Reader::Vecs(r) => Ok(r.next_i32()?.map(|v| v.into_iter().map(i64::from).collect())),
+            Reader::Hdf5(r) => r.next_i64(),
+            Reader::Npy(r) => r.next_i64(),
+        }
+    }
+}
+
+impl Iterator for VectorFile {
+    type Item = Result<Vec<f32>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_vector().transpose()
+    }
+}
+
+/// Reads the true nearest neighbors of a query set, one row of row numbers
+/// per query, from an `.ivecs` file or the `neighbors` dataset of an
+/// ann-benchmarks HDF5 file. Row numbers become [`Id::Int`], the ids
+/// `vdb import` gives the vectors of a file.
+pub fn read_ground_truth(path: impl AsRef<Path>) -> Result<Vec<Vec<Id>>> {
+    let mut file = VectorFile::open(path, "neighbors")?;
+    let mut rows = Vec::with_capacity(file.len());
+    while let Some(row) = file.next_ints()? {
+        let ids = row
+            .into_iter()
+            .map(|i| usize::try_from(i).map(Id::Int).map_err(|_| anyhow!("negative neighbor: {i}")))
+            .collect::<Result<Vec<Id>>>()?;
+        rows.push(ids);
+    }
+    Ok(rows)
+}
+
+/// Reads the ids of the vectors of a NumPy file, in row order, from a
+/// one-dimensional `.npy` array of integers or strings, or from the array
+/// `array` of an `.npz` archive. Strings are parsed as `kind`.
+pub fn read_ids(path: impl AsRef<Path>, array: &str, kind: IdKind) -> Result<Vec<Id>> {
+    let path = path.as_ref();
+    let mut rows = match extension(path).as_str() {
+        ext @ ("npy" | "npz") => npy::open(path, ext == "npz", array)?,
+        ext => return Err(anyhow!("ids must be read from an npy or npz file, not {ext}")),
+    };
+    let mut ids = Vec::with_capacity(rows.rows());
+    while let Some(id) = rows.next_id(kind)? {
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+fn extension(path: &Path) -> String {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    ext.to_ascii_lowercase()
+}
@@ -0,0 +1,334 @@
+//! Entries as Parquet or Arrow IPC tables: an `id` column, a `vector`
+//! column of lists of floats and optionally `label` and `description`
+//! string columns. Exported tables store vectors as fixed-size lists of
+//! `f32` and integer ids as `u64`; other ids are strings.
+
+use super::records::{Line, Record};
+use crate::types::{Id, IdKind, Metadata};
+use anyhow::{Result, anyhow};
+use arrow::array::{
+    Array, ArrayRef, AsArray, FixedSizeListBuilder, Float32Array, Float32Builder, RecordBatch,
+    RecordBatchReader, StringArray, StringBuilder, UInt64Builder,
+};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, Float32Type, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::ipc::reader::{FileReader, StreamReader};
+use arrow::ipc::writer::FileWriter;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Rows buffered by [`TableWriter`] before they are written as a batch.
+const BATCH_ROWS: usize = 8192;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Parquet,
+    Ipc,
+}
+
+fn format(path: &Path) -> Option<Format> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match ext.to_ascii_lowercase().as_str() {
+        "parquet" => Some(Format::Parquet),
+        "arrow" | "arrows" | "feather" | "ipc" => Some(Format::Ipc),
+        _ => None,
+    }
+}
+
+/// Whether `path` has the extension of a Parquet (`.parquet`) or Arrow IPC
+/// (`.arrow`, `.arrows`, `.feather`, `.ipc`) file.
+pub fn is_table(path: impl AsRef<Path>) -> bool {
+    format(path.as_ref()).is_some()
+}
+
+type Batches = Box<dyn Iterator<Item = Result<RecordBatch, ArrowError>>>;
+
+/// Reads the rows of a Parquet or Arrow IPC file, a batch at a time. Ids
+/// are parsed as `id_kind`; rows are numbered from 1 and have no text.
+pub struct TableReader {
+    batches: Batches,
+    columns: Columns,
+    id_kind: IdKind,
+    rows: VecDeque<Line>,
+    number: usize,
+}
+
+/// Positions of the known columns.
+struct Columns {
+    id: usize,
+    vector: usize,
+    label: Option<usize>,
+    description: Option<usize>,
+}
+
+impl TableReader {
+    pub fn open(path: impl AsRef<Path>, id_kind: IdKind) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let (schema, batches): (SchemaRef, Batches) = match format(path) {
+            Some(Format::Parquet) => {
+                let reader = ParquetRecordBatchReaderBuilder::try_new(file)?.build()?;
+                (reader.schema(), Box::new(reader))
+            }
+            Some(Format::Ipc) => {
+                // the file format starts with a magic number, the stream
+                // format with a message
+                let mut magic = [0u8; 6];
+                let is_file = file.read_exact(&mut magic).is_ok() && &magic == b"ARROW1";
+                file.seek(SeekFrom::Start(0))?;
+                if is_file {
+                    let reader = FileReader::try_new(file, None)?;
+                    (reader.schema(), Box::new(reader))
+                } else {
+                    let reader = StreamReader::try_new(BufReader::new(file), None)?;
+                    (reader.schema(), Box::new(reader))
+                }
+            }
+            None => return Err(anyhow!("not a parquet or arrow file")),
+        };
+        let column = |name| schema.index_of(name).ok();
+        let columns = Columns {
+            id: column("id").ok_or(anyhow!("no id column"))?,
+            vector: column("vector").ok_or(anyhow!("no vector column"))?,
+            label: column("label"),
+            description: column("description"),
+        };
+        Ok(Self {
+            batches,
+            columns,
+            id_kind,
+            rows: VecDeque::new(),
+            number: 0,
+        })
+    }
+
+    fn next_line(&mut self) -> Result<Option<Line>> {
+        while self.rows.is_empty() {
+            match self.batches.next() {
+                None => return Ok(None),
+                Some(batch) => self.decode(&batch?)?,
+            }
+        }
+        Ok(self.rows.pop_front())
+    }
+
+    /// Turns the rows of `batch` into lines.
+    fn decode(&mut self, batch: &RecordBatch) -> Result<()> {
+        let strings = |i: usize| -> Result<StringArray> {
+            Ok(cast(batch.column(i), &DataType::Utf8)?.as_string::<i32>().clone())
+        };
+        let ids = strings(self.columns.id)?;
+        let (ranges, values) = vectors(batch.column(self.columns.vector))?;
+        let labels = self.columns.label.map(strings).transpose()?;
+        let descriptions = self.columns.description.map(strings).transpose()?;
+        let text = |array: &Option<StringArray>, i: usize| {
+            array.as_ref().filter(|a| a.is_valid(i)).map(|a| a.value(i).to_string())
+        };
+        for (i, range) in ranges.into_iter().enumerate() {
+            let record = || -> Result<Record> {
+                if ids.is_null(i) {
+                    return Err(anyhow!("missing id"));
+                }
+                let range = range.ok_or(anyhow!("missing vector"))?;
+                if values.null_count() > 0 && range.clone().any(|j| values.is_null(j)) {
+                    return Err(anyhow!("missing vector component"));
+                }
+                Ok(Record {
+                    id: Id::parse(ids.value(i), self.id_kind)?,
+                    vector: values.values()[range].to_vec(),
+                    metadata: Metadata {
+                        label: text(&labels, i).unwrap_or_default(),
+                        description: text(&descriptions, i),
+                    },
+                })
+            };
+            self.number += 1;
+            self.rows.push_back(Line {
+                number: self.number,
+                text: String::new(),
+                record: record(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for TableReader {
+    type Item = Result<Line>;
+
+    /// The next row; an error means the file cannot be read further.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_line().transpose()
+    }
+}
+
+/// The range of `f32` values of each row of a column of lists, `None` for
+/// null rows, and the values.
+fn vectors(column: &ArrayRef) -> Result<(Vec<Option<Range<usize>>>, Float32Array)> {
+    let (ranges, values): (Vec<Option<Range<usize>>>, &ArrayRef) = match column.data_type() {
+        DataType::FixedSizeList(..) => {
+            let list = column.as_fixed_size_list();
+            let ranges = (0..list.len()).map(|i| {
+                let start = list.value_offset(i) as usize;
+                list.is_valid(i).then(|| start..start + list.value_length() as usize)
+            });
+            (ranges.collect(), list.values())
+        }
+        DataType::List(_) => {
+            let list = column.as_list::<i32>();
+            let offsets = list.value_offsets();
+            let ranges = (0..list.len()).map(|i| {
+                list.is_valid(i).then(|| offsets[i] as usize..offsets[i + 1] as usize)
+            });
+            (ranges.collect(), list.values())
+        }
+        DataType::LargeList(_) => {
+            let list = column.as_list::<i64>();
+            let offsets = list.value_offsets();
+            let ranges = (0..list.len()).map(|i| {
+                list.is_valid(i).then(|| offsets[i] as usize..offsets[i + 1] as usize)
+            });
+            (ranges.collect(), list.values())
+        }
+        other => return Err(anyhow!("vector column must hold lists, not {other}")),
+    };
+    if !values.data_type().is_floating() && !values.data_type().is_integer() {
+        return Err(anyhow!("vectors must hold numbers, not {}", values.data_type()));
+    }
+    let values = cast(values, &DataType::Float32)?.as_primitive::<Float32Type>().clone();
+    Ok((ranges, values))
+}
+
+/// Writes entries to a Parquet or Arrow IPC file, chosen by the extension
+/// of its path, in the layout [`TableReader`] reads.
+pub struct TableWriter {
+    sink: Sink,
+    schema: SchemaRef,
+    ids: IdBuilder,
+    vectors: FixedSizeListBuilder<Float32Builder>,
+    labels: StringBuilder,
+    descriptions: StringBuilder,
+    id_kind: IdKind,
+    dim: usize,
+    rows: usize,
+}
+
+enum Sink {
+    Parquet(ArrowWriter<File>),
+    Ipc(FileWriter<File>),
+}
+
+enum IdBuilder {
+    Int(UInt64Builder),
+    Str(StringBuilder),
+}
+
+impl TableWriter {
+    /// Creates the file at `path` for entries of `dim`-dimensional vectors
+    /// with ids of `id_kind`.
+    pub fn create(path: impl AsRef<Path>, id_kind: IdKind, dim: usize) -> Result<Self> {
+        let path = path.as_ref();
+        let format = format(path).ok_or(anyhow!("not a parquet or arrow file"))?;
+        let (id_type, ids) = match id_kind {
+            IdKind::Int => (DataType::UInt64, IdBuilder::Int(UInt64Builder::new())),
+            _ => (DataType::Utf8, IdBuilder::Str(StringBuilder::new())),
+        };
+        let item = Arc::new(Field::new("item", DataType::Float32, false));
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", id_type, false),
+            Field::new("vector", DataType::FixedSizeList(item.clone(), dim as i32), false),
+            Field::new("label", DataType::Utf8, false),
+            Field::new("description", DataType::Utf8, true),
+        ]));
+        let file = File::create(path)?;
+        let sink = match format {
+            Format::Parquet => {
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                Sink::Parquet(ArrowWriter::try_new(file, schema.clone(), Some(properties))?)
+            }
+            Format::Ipc => Sink::Ipc(FileWriter::try_new(file, &schema)?),
+        };
+        let vectors = FixedSizeListBuilder::new(Float32Builder::new(), dim as i32).with_field(item);
+        Ok(Self {
+            sink,
+            schema,
+            ids,
+            vectors,
+            labels: StringBuilder::new(),
+            descriptions: StringBuilder::new(),
+            id_kind,
+            dim,
+            rows: 0,
+        })
+    }
+
+    pub fn write(&mut self, id: &Id, vector: &[f32], metadata: &Metadata) -> Result<()> {
+        if vector.len() != self.dim {
+            return Err(anyhow!("dimension mismatch"));
+        }
+        if id.kind() != self.id_kind {
+            return Err(anyhow!("id kind mismatch"));
+        }
+        match (&mut self.ids, id) {
+            (IdBuilder::Int(ids), Id::Int(i)) => ids.append_value(*i as u64),
+            (IdBuilder::Str(ids), _) => ids.append_value(id.to_string()),
+            (IdBuilder::Int(_), _) => unreachable!("ids of int collections are ints"),
+        }
+        self.vectors.values().append_slice(vector);
+        self.vectors.append(true);
+        self.labels.append_value(&metadata.label);
+        self.descriptions.append_option(metadata.description.as_deref());
+        self.rows += 1;
+        if self.rows == BATCH_ROWS {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered rows as a batch.
+    fn flush(&mut self) -> Result<()> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+        let ids: ArrayRef = match &mut self.ids {
+            IdBuilder::Int(ids) => Arc::new(ids.finish()),
+            IdBuilder::Str(ids) => Arc::new(ids.finish()),
+        };
+        let columns: Vec<ArrayRef> = vec![
+            ids,
+            Arc::new(self.vectors.finish()),
+            Arc::new(self.labels.finish()),
+            Arc::new(self.descriptions.finish()),
+        ];
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        match &mut self.sink {
+            Sink::Parquet(writer) => writer.write(&batch)?,
+            Sink::Ipc(writer) => writer.write(&batch)?,
+        }
+        self.rows = 0;
+        Ok(())
+    }
+
+    /// Writes the buffered rows and the footer of the file.
+    pub fn finish(mut self) -> Result<()> {
+        self.flush()?;
+        match self.sink {
+            Sink::Parquet(writer) => {
+                writer.close()?;
+            }
+            Sink::Ipc(mut writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,571 @@
+//! Reader for the HDF5 files of ann-benchmarks, as h5py writes them:
+//! numeric datasets with contiguous or compact storage, in groups indexed by
+//! a symbol table or by link messages. Chunked datasets, and so compressed
+//! ones, and groups with dense link storage are not supported.
+
+use anyhow::{Result, anyhow};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const SIGNATURE: [u8; 8] = *b"\x89HDF\r\n\x1a\n";
+
+// object header message types
+const DATASPACE: u16 = 0x0001;
+const DATATYPE: u16 = 0x0003;
+const LINK: u16 = 0x0006;
+const LAYOUT: u16 = 0x0008;
+const CONTINUATION: u16 = 0x0010;
+const SYMBOL_TABLE: u16 = 0x0011;
+
+pub struct Hdf5 {
+    file: BufReader<File>,
+    offset_size: usize,
+    length_size: usize,
+    /// Address all others are relative to.
+    base: u64,
+    root: u64,
+    /// Length of the file, which bounds every read.
+    size: u64,
+}
+
+/// A numeric dataset of one or two dimensions. One-dimensional datasets
+/// read as a single column.
+pub struct Dataset {
+    pub rows: usize,
+    pub cols: usize,
+    kind: Kind,
+    size: usize,
+    data: Data,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Float,
+    Signed,
+    Unsigned,
+}
+
+enum Data {
+    /// Absolute file position of the values.
+    Contiguous(u64),
+    Compact(Vec<u8>),
+}
+
+/// Little-endian fields of an object header message or other structure.
+struct Fields<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos + n;
+        let taken = self.bytes.get(self.pos..end).ok_or(anyhow!("truncated hdf5 structure"))?;
+        self.pos = end;
+        Ok(taken)
+    }
+
+    fn skip(&mut self, n: usize) -> Result<()> {
+        self.take(n).map(|_| ())
+    }
+
+    fn uint(&mut self, n: usize) -> Result<u64> {
+        let mut value = [0u8; 8];
+        value[..n].copy_from_slice(self.take(n)?);
+        Ok(u64::from_le_bytes(value))
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(self.uint(2)? as u16)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(self.uint(4)? as u32)
+    }
+}
+
+impl Hdf5 {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let size = file.get_ref().metadata()?.len();
+        // the superblock is at 0 or, after a user block, at a power of two
+        let mut at = 0;
+        loop {
+            if at + 8 > size {
+                return Err(anyhow!("not an hdf5 file"));
+            }
+            if read_at(&mut file, size, at, 8)? == SIGNATURE {
+                break;
+            }
+            at = if at == 0 { 512 } else { at * 2 };
+        }
+        let head = read_at(&mut file, size, at, 256.min(size - at) as usize)?;
+        let mut f = Fields::new(&head);
+        f.skip(8)?;
+        let version = f.u8()?;
+        let (offset_size, length_size, root) = match version {
+            0 | 1 => {
+                f.skip(4)?;
+                let offset_size = f.u8()? as usize;
+                let length_size = f.u8()? as usize;
+                // reserved, group K values, consistency flags and, from
+                // version 1, the indexed storage K
+                f.skip(if version == 0 { 9 } else { 13 })?;
+                // base, free space, end of file and driver addresses
+                let base = f.uint(offset_size)?;
+                f.skip(3 * offset_size)?;
+                // root group symbol table entry
+                f.skip(offset_size)?;
+                let root = f.uint(offset_size)?;
+                (offset_size, length_size, (base, root))
+            }
+            2 | 3 => {
+                let offset_size = f.u8()? as usize;
+                let length_size = f.u8()? as usize;
+                f.skip(1)?;
+                let base = f.uint(offset_size)?;
+                // superblock extension and end of file addresses
+                f.skip(2 * offset_size)?;
+                let root = f.uint(offset_size)?;
+                (offset_size, length_size, (base, root))
+            }
+            _ => return Err(anyhow!("unsupported hdf5 superblock version {version}")),
+        };
+        if !matches!(offset_size, 2 | 4 | 8) || !matches!(length_size, 2 | 4 | 8) {
+            return Err(anyhow!("invalid hdf5 superblock"));
+        }
+        Ok(Self {
+            file,
+            offset_size,
+            length_size,
+            base: root.0,
+            root: root.1,
+            size,
+        })
+    }
+
+    /// The dataset at `path`, such as `train` or `group/test`.
+    pub fn dataset(&mut self, path: &str) -> Result<Dataset> {
+        let mut header = self.root;
+        for name in path.split('/').filter(|s| !s.is_empty()) {
+            header = self.child(header, name)?.ok_or(anyhow!("no hdf5 dataset: {path}"))?;
+        }
+        let messages = self.messages(header)?;
+        let message = |kind| {
+            messages
+                .iter()
+                .find(|(t, _)| *t == kind)
+                .map(|(_, data)| data.as_slice())
+                .ok_or(anyhow!("{path} is not an hdf5 dataset"))
+        };
+        let (rows, cols) = self.dataspace(message(DATASPACE)?)?;
+        let (kind, size) = datatype(message(DATATYPE)?)?;
+        let data = self.layout(message(LAYOUT)?)?;
+        let dataset = Dataset {
+            rows,
+            cols,
+            kind,
+            size,
+            data,
+        };
+        let bytes = rows.checked_mul(cols).and_then(|n| n.checked_mul(size));
+        let available = match &dataset.data {
+            Data::Compact(data) => data.len() as u64,
+            Data::Contiguous(at) => self.size.saturating_sub(*at),
+        };
+        if bytes.is_none_or(|bytes| bytes as u64 > available) {
+            return Err(anyhow!("truncated hdf5 dataset"));
+        }
+        Ok(dataset)
+    }
+
+    /// Reader of the rows of `dataset`, which takes over the file.
+    pub fn into_rows(mut self, dataset: Dataset) -> Result<Rows> {
+        if let Data::Contiguous(at) = dataset.data {
+            self.file.seek(SeekFrom::Start(at))?;
+        }
+        Ok(Rows {
+            file: self.file,
+            row: vec![0; dataset.cols * dataset.size],
+            dataset,
+            next: 0,
+        })
+    }
+
+    /// Object header address of the link `name` in the group at `header`.
+    fn child(&mut self, header: u64, name: &str) -> Result<Option<u64>> {
+        let messages = self.messages(header)?;
+        for (kind, data) in &messages {
+            match *kind {
+                SYMBOL_TABLE => {
+                    let mut f = Fields::new(data);
+                    let tree = f.uint(self.offset_size)?;
+                    let heap = f.uint(self.offset_size)?;
+                    let names = self.local_heap(heap)?;
+                    return self.find_symbol(tree, &names, name);
+                }
+                LINK => {
+                    if let Some((link, address)) = self.link(data)? {
+                        if link == name {
+                            return Ok(Some(address));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// Name and object header address of a hard link message.
+    fn link(&self, data: &[u8]) -> Result<Option<(String, u64)>> {
+        let mut f = Fields::new(data);
+        f.skip(1)?;
+        let flags = f.u8()?;
+        let hard = flags & 0x08 == 0 || f.u8()? == 0;
+        if flags & 0x04 != 0 {
+            f.skip(8)?;
+        }
+        if flags & 0x10 != 0 {
+            f.skip(1)?;
+        }
+        let len = f.uint(1 << (flags & 0x03))? as usize;
+        let name = String::from_utf8_lossy(f.take(len)?).into_owned();
+        if !hard {
+            return Ok(None);
+        }
+        Ok(Some((name, f.uint(self.offset_size)?)))
+    }
+
+    /// Data segment of the local heap at `address`.
+    fn local_heap(&mut self, address: u64) -> Result<Vec<u8>> {
+        let head = self.read(address, 8 + 2 * self.length_size + self.offset_size)?;
+        if &head[..4] != b"HEAP" {
+            return Err(anyhow!("invalid hdf5 local heap"));
+        }
+        let mut f = Fields::new(&head[8..]);
+        let size = f.uint(self.length_size)? as usize;
+        f.skip(self.length_size)?;
+        let data = f.uint(self.offset_size)?;
+        self.read(data, size)
+    }
+
+    /// Looks `name` up in the group B-tree at `address`.
+    fn find_symbol(&mut self, address: u64, names: &[u8], name: &str) -> Result<Option<u64>> {
+        let (o, l) = (self.offset_size, self.length_size);
+        let head = self.read(address, 8 + 2 * o)?;
+        if &head[..4] != b"TREE" || head[4] != 0 {
+            return Err(anyhow!("invalid hdf5 group b-tree"));
+        }
+        let level = head[5];
+        let entries = u16::from_le_bytes([head[6], head[7]]) as usize;
+        // keys and children alternate, starting and ending with a key
+        let body = self.read(address + 8 + 2 * o as u64, entries * (l + o) + l)?;
+        let mut f = Fields::new(&body);
+        let mut children = Vec::with_capacity(entries);
+        for _ in 0..entries {
+            f.skip(l)?;
+            children.push(f.uint(o)?);
+        }
+        for child in children {
+            let found = if level > 0 {
+                self.find_symbol(child, names, name)?
+            } else {
+                self.find_in_node(child, names, name)?
+            };
+            if found.is_some() {
+                return Ok(found);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Looks `name` up in the symbol table node at `address`.
+    fn find_in_node(&mut self, address: u64, names: &[u8], name: &str) -> Result<Option<u64>> {
+        let o = self.offset_size;
+        let head = self.read(address, 8)?;
+        if &head[..4] != b"SNOD" {
+            return Err(anyhow!("invalid hdf5 symbol table node"));
+        }
+        let symbols = u16::from_le_bytes([head[6], head[7]]) as usize;
+        let entry_size = 2 * o + 24;
+        let body = self.read(address + 8, symbols * entry_size)?;
+        for entry in body.chunks_exact(entry_size) {
+            let mut f = Fields::new(entry);
+            let offset = f.uint(o)? as usize;
+            let header = f.uint(o)?;
+            let rest = names.get(offset..).ok_or(anyhow!("invalid hdf5 link name"))?;
+            let end = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+            if &rest[..end] == name.as_bytes() {
+                return Ok(Some(header));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Type and data of every message in the object header at `address`.
+    fn messages(&mut self, address: u64) -> Result<Vec<(u16, Vec<u8>)>> {
+        let mut messages = Vec::new();
+        let head = self.read(address, 16)?;
+        if &head[..4] == b"OHDR" {
+            let flags = head[5];
+            let mut at = address + 6;
+            if flags & 0x20 != 0 {
+                at += 16;
+            }
+            if flags & 0x10 != 0 {
+                at += 4;
+            }
+            let width = 1 << (flags & 0x03);
+            let size = Fields::new(&self.read(at, width)?).uint(width)?;
+            // with the checksum that follows, like that of continuation chunks
+            let mut blocks = vec![(at + width as u64, size + 4)];
+            while let Some((at, size)) = blocks.pop() {
+                let block = self.read(at, size as usize)?;
+                self.parse_v2(&block, flags, &mut messages, &mut blocks)?;
+            }
+        } else if head[0] == 1 {
+            let size = Fields::new(&head[8..12]).u32()? as u64;
+            let mut blocks = vec![(address + 16, size)];
+            while let Some((at, size)) = blocks.pop() {
+                let block = self.read(at, size as usize)?;
+                self.parse_v1(&block, &mut messages, &mut blocks)?;
+            }
+        } else {
+            return Err(anyhow!("unsupported hdf5 object header"));
+        }
+        Ok(messages)
+    }
+
+    fn parse_v1(
+        &self,
+        block: &[u8],
+        messages: &mut Vec<(u16, Vec<u8>)>,
+        blocks: &mut Vec<(u64, u64)>,
+    ) -> Result<()> {
+        let mut f = Fields::new(block);
+        while f.pos + 8 <= block.len() {
+            let kind = f.u16()?;
+            let size = f.u16()? as usize;
+            f.skip(4)?;
+            let data = f.take(size)?;
+            self.push_message(kind, data, messages, blocks)?;
+        }
+        Ok(())
+    }
+
+    /// Parses the messages of a version 2 header chunk; continuation chunks
+    /// start with a signature, and all chunks end with a checksum.
+    fn parse_v2(
+        &self,
+        block: &[u8],
+        flags: u8,
+        messages: &mut Vec<(u16, Vec<u8>)>,
+        blocks: &mut Vec<(u64, u64)>,
+    ) -> Result<()> {
+        let header_size = if flags & 0x04 != 0 { 6 } else { 4 };
+        let mut f = Fields::new(block);
+        if block.starts_with(b"OCHK") {
+            f.skip(4)?;
+        }
+        let end = block.len().saturating_sub(4);
+        while f.pos + header_size <= end {
+            let kind = f.u8()? as u16;
+            let size = f.u16()? as usize;
+            f.skip(header_size - 3)?;
+            let data = f.take(size)?;
+            self.push_message(kind, data, messages, blocks)?;
+        }
+        Ok(())
+    }
+
+    fn push_message(
+        &self,
+        kind: u16,
+        data: &[u8],
+        messages: &mut Vec<(u16, Vec<u8>)>,
+        blocks: &mut Vec<(u64, u64)>,
+    ) -> Result<()> {
+        if kind == CONTINUATION {
+            let mut f = Fields::new(data);
+            let at = f.uint(self.offset_size)?;
+            let size = f.uint(self.length_size)?;
+            blocks.push((at, size));
+        } else {
+            messages.push((kind, data.to_vec()));
+        }
+        Ok(())
+    }
+
+    fn dataspace(&self, data: &[u8]) -> Result<(usize, usize)> {
+        let mut f = Fields::new(data);
+        let version = f.u8()?;
+        let rank = f.u8()? as usize;
+        f.skip(if version == 1 { 6 } else { 2 })?;
+        let dims = (0..rank)
+            .map(|_| Ok(f.uint(self.length_size)? as usize))
+            .collect::<Result<Vec<usize>>>()?;
+        match dims[..] {
+            [rows] => Ok((rows, 1)),
+            [rows, cols] => Ok((rows, cols)),
+            _ => Err(anyhow!("hdf5 dataset of rank {rank}")),
+        }
+    }
+
+    fn layout(&self, data: &[u8]) -> Result<Data> {
+        let mut f = Fields::new(data);
+        let version = f.u8()?;
+        let class = if version < 3 {
+            let rank = f.u8()? as usize;
+            let class = f.u8()?;
+            f.skip(5)?;
+            if class == 1 {
+                return Ok(self.contiguous(f.uint(self.offset_size)?));
+            }
+            if class == 0 {
+                f.skip(4 * rank)?;
+                let size = f.u32()? as usize;
+                return Ok(Data::Compact(f.take(size)?.to_vec()));
+            }
+            class
+        } else {
+            let class = f.u8()?;
+            if class == 1 {
+                return Ok(self.contiguous(f.uint(self.offset_size)?));
+            }
+            if class == 0 {
+                let size = f.u16()? as usize;
+                return Ok(Data::Compact(f.take(size)?.to_vec()));
+            }
+            class
+        };
+        match class {
+            2 => Err(anyhow!("chunked hdf5 datasets are not supported")),
+            _ => Err(anyhow!("unsupported hdf5 storage layout {class}")),
+        }
+    }
+
+    /// Contiguous storage at `address`, which is undefined until a value
+    /// is written.
+    fn contiguous(&self, address: u64) -> Data {
+        if address == u64::MAX >> (64 - 8 * self.offset_size) {
+            Data::Compact(Vec::new())
+        } else {
+            Data::Contiguous(self.base + address)
+        }
+    }
+
+    fn read(&mut self, address: u64, len: usize) -> Result<Vec<u8>> {
+        let at = self.base.checked_add(address).ok_or(anyhow!("invalid hdf5 address"))?;
+        read_at(&mut self.file, self.size, at, len)
+    }
+}
+
+/// Rows of a dataset, read one at a time.
+pub struct Rows {
+    file: BufReader<File>,
+    dataset: Dataset,
+    row: Vec<u8>,
+    next: usize,
+}
+
+impl Rows {
+    /// The next row, or `None` after the last.
+    fn next_raw(&mut self) -> Result<Option<&[u8]>> {
+        if self.next == self.dataset.rows {
+            return Ok(None);
+        }
+        let size = self.row.len();
+        match &self.dataset.data {
+            Data::Contiguous(_) => self.file.read_exact(&mut self.row)?,
+            Data::Compact(bytes) => {
+                self.row.copy_from_slice(&bytes[self.next * size..(self.next + 1) * size])
+            }
+        }
+        self.next += 1;
+        Ok(Some(&self.row))
+    }
+
+    /// The next row converted to `f32`.
+    pub fn next_f32(&mut self) -> Result<Option<Vec<f32>>> {
+        let (kind, size) = (self.dataset.kind, self.dataset.size);
+        let raw = self.next_raw()?;
+        Ok(raw.map(|raw| raw.chunks_exact(size).map(|b| float(kind, b)).collect()))
+    }
+
+    /// The next row of an integer dataset, such as the neighbor lists of
+    /// ann-benchmarks.
+    pub fn next_i64(&mut self) -> Result<Option<Vec<i64>>> {
+        let (kind, size) = (self.dataset.kind, self.dataset.size);
+        if kind == Kind::Float {
+            return Err(anyhow!("not an integer dataset"));
+        }
+        let raw = self.next_raw()?;
+        Ok(raw.map(|raw| raw.chunks_exact(size).map(|b| int(kind, b)).collect()))
+    }
+}
+
+fn float(kind: Kind, b: &[u8]) -> f32 {
+    match (kind, b.len()) {
+        (Kind::Float, 4) => f32::from_bits(bits(b) as u32),
+        (Kind::Float, _) => f64::from_bits(bits(b)) as f32,
+        _ => int(kind, b) as f32,
+    }
+}
+
+fn int(kind: Kind, b: &[u8]) -> i64 {
+    match kind {
+        Kind::Signed => {
+            // sign-extend from the width of the value
+            let shift = 64 - 8 * b.len() as u32;
+            (bits(b) << shift) as i64 >> shift
+        }
+        _ => bits(b) as i64,
+    }
+}
+
+/// Little-endian value of up to 8 bytes.
+fn bits(b: &[u8]) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes[..b.len()].copy_from_slice(b);
+    u64::from_le_bytes(bytes)
+}
+
+/// Class and size of a little-endian numeric datatype message.
+fn datatype(data: &[u8]) -> Result<(Kind, usize)> {
+    let mut f = Fields::new(data);
+    let class = f.u8()? & 0x0f;
+    let bits = f.u8()?;
+    f.skip(2)?;
+    let size = f.u32()? as usize;
+    if bits & 0x01 != 0 {
+        return Err(anyhow!("big-endian hdf5 datasets are not supported"));
+    }
+    match (class, size) {
+        (0, 1 | 2 | 4 | 8) if bits & 0x08 != 0 => Ok((Kind::Signed, size)),
+        (0, 1 | 2 | 4 | 8) => Ok((Kind::Unsigned, size)),
+        (1, 4 | 8) => Ok((Kind::Float, size)),
+        _ => Err(anyhow!("hdf5 datasets must hold integers or floats")),
+    }
+}
+
+/// Reads `len` bytes at `at` of a file of `size` bytes. Lengths come from
+/// the file itself, so they are checked before anything is allocated.
+fn read_at(file: &mut BufReader<File>, size: u64, at: u64, len: usize) -> Result<Vec<u8>> {
+    if at.checked_add(len as u64).is_none_or(|end| end > size) {
+        return Err(anyhow!("truncated hdf5 file"));
+    }
+    file.seek(SeekFrom::Start(at))?;
+    let mut buf = vec![0u8; len];
+    file.read_exact(&mut buf)?;
+    Ok(buf)
+}
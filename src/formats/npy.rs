@@ -0,0 +1,419 @@
+//! NumPy `.npy` arrays, and `.npz` archives of them as `np.savez` writes
+//! them: little-endian numbers or strings in arrays of one or two
+//! dimensions, in C order. Archives from `np.savez_compressed` are not
+//! supported.
+
+use crate::types::{Id, IdKind};
+use anyhow::{Result, anyhow};
+use half::f16;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+// zip record signatures
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END: u32 = 0x0605_4b50;
+const ZIP64_END: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR: u32 = 0x0706_4b50;
+
+/// Element type of an array.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dtype {
+    Float(usize),
+    Signed(usize),
+    Unsigned(usize),
+    /// Strings of up to this many UTF-32 characters.
+    Unicode(usize),
+    /// Strings of up to this many bytes.
+    Bytes(usize),
+}
+
+impl Dtype {
+    fn parse(descr: &str) -> Result<Self> {
+        let unsupported = || anyhow!("unsupported npy dtype: {descr}");
+        let (order, rest) = descr.split_at_checked(1).ok_or_else(unsupported)?;
+        if order == ">" {
+            return Err(anyhow!("big-endian npy arrays are not supported"));
+        }
+        if !matches!(order, "<" | "|" | "=") || rest.is_empty() {
+            return Err(unsupported());
+        }
+        let (kind, size) = rest.split_at(1);
+        let size: usize = size.parse().map_err(|_| unsupported())?;
+        match (kind, size) {
+            ("f", 2 | 4 | 8) => Ok(Dtype::Float(size)),
+            ("i", 1 | 2 | 4 | 8) => Ok(Dtype::Signed(size)),
+            ("u", 1 | 2 | 4 | 8) => Ok(Dtype::Unsigned(size)),
+            ("U", 1..) if size <= usize::MAX / 4 => Ok(Dtype::Unicode(size)),
+            ("S", 1..) => Ok(Dtype::Bytes(size)),
+            _ => Err(unsupported()),
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Dtype::Unicode(chars) => 4 * chars,
+            Dtype::Float(size) | Dtype::Signed(size) | Dtype::Unsigned(size) => size,
+            Dtype::Bytes(size) => size,
+        }
+    }
+}
+
+/// An array of one or two dimensions. One-dimensional arrays read as a
+/// single column.
+pub struct Array {
+    pub rows: usize,
+    pub cols: usize,
+    dtype: Dtype,
+    /// File position of the values.
+    start: u64,
+}
+
+/// Opens the `.npy` file at `path`, or the array `name` of the `.npz`
+/// archive at `path`.
+pub fn open(path: &Path, npz: bool, name: &str) -> Result<Rows> {
+    let mut file = BufReader::new(File::open(path)?);
+    let (start, end) = if npz {
+        member(&mut file, name)?
+    } else {
+        (0, file.get_ref().metadata()?.len())
+    };
+    let array = header(&mut file, start, end)?;
+    Rows::new(file, array)
+}
+
+/// Reads the header of the `.npy` data between `start` and `end`. The
+/// header and the values it describes must fit in between.
+fn header(file: &mut BufReader<File>, start: u64, end: u64) -> Result<Array> {
+    let head = read_at(file, end, start, 10)?;
+    if head[..6] != MAGIC[..] {
+        return Err(anyhow!("not an npy file"));
+    }
+    let (len, prefix) = match head[6] {
+        1 => (u16::from_le_bytes([head[8], head[9]]) as usize, 10),
+        2 | 3 => {
+            let rest = read_at(file, end, start + 10, 2)?;
+            (u32::from_le_bytes([head[8], head[9], rest[0], rest[1]]) as usize, 12)
+        }
+        v => return Err(anyhow!("unsupported npy version: {v}")),
+    };
+    let text = read_at(file, end, start + prefix, len)?;
+    let text = String::from_utf8_lossy(&text);
+    let descr = value(&text, "descr")?
+        .strip_prefix('\'')
+        .and_then(|s| s.split('\'').next())
+        .ok_or(anyhow!("structured npy arrays are not supported"))?;
+    let dtype = Dtype::parse(descr)?;
+    if value(&text, "fortran_order")?.starts_with("True") {
+        return Err(anyhow!("fortran-ordered npy arrays are not supported"));
+    }
+    let shape = value(&text, "shape")?;
+    let shape = shape.strip_prefix('(').and_then(|s| s.split(')').next());
+    let shape = shape
+        .ok_or(anyhow!("invalid npy shape"))?
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<usize>().map_err(|_| anyhow!("invalid npy shape")))
+        .collect::<Result<Vec<usize>>>()?;
+    let (rows, cols) = match shape[..] {
+        [rows] => (rows, 1),
+        [rows, cols] => (rows, cols),
+        _ => return Err(anyhow!("npy arrays must have one or two dimensions")),
+    };
+    let start = start + prefix + len as u64;
+    let size = rows.checked_mul(cols).and_then(|n| n.checked_mul(dtype.size()));
+    if size.is_none_or(|size| size as u64 > end - start) {
+        return Err(anyhow!("truncated npy data"));
+    }
+    Ok(Array {
+        rows,
+        cols,
+        dtype,
+        start,
+    })
+}
+
+/// The text following `'key':` in the dictionary of an npy header.
+fn value<'a>(header: &'a str, key: &str) -> Result<&'a str> {
+    let at = header
+        .find(&format!("'{key}':"))
+        .ok_or(anyhow!("npy header has no {key}"))?;
+    Ok(header[at + key.len() + 3..].trim_start())
+}
+
+/// Finds the array `name` of a `.npz` archive in its central directory and
+/// returns the file positions where its `.npy` data starts and ends.
+fn member(file: &mut BufReader<File>, name: &str) -> Result<(u64, u64)> {
+    let size = file.get_ref().metadata()?.len();
+    // the end record, followed by a comment of up to 64 KiB
+    let tail_len = size.min(22 + 0xffff);
+    let tail = read_at(file, size, size - tail_len, tail_len as usize)?;
+    let end = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&i| u32_at(&tail, i) == END)
+        .ok_or(anyhow!("not an npz file"))?;
+    let mut count = u16::from_le_bytes([tail[end + 10], tail[end + 11]]) as u64;
+    let mut directory = u32_at(&tail, end + 16) as u64;
+    if end >= 20 && u32_at(&tail, end - 20) == ZIP64_LOCATOR {
+        let at = u64::from_le_bytes(tail[end - 12..end - 4].try_into().unwrap());
+        let record = read_at(file, size, at, 56)?;
+        if u32_at(&record, 0) != ZIP64_END {
+            return Err(anyhow!("invalid zip64 end record"));
+        }
+        count = u64::from_le_bytes(record[32..40].try_into().unwrap());
+        directory = u64::from_le_bytes(record[48..56].try_into().unwrap());
+    }
+    let wanted = format!("{name}.npy");
+    let mut names = Vec::new();
+    let mut at = directory;
+    for _ in 0..count {
+        let head = read_at(file, size, at, 46)?;
+        if u32_at(&head, 0) != CENTRAL_HEADER {
+            return Err(anyhow!("invalid npz directory"));
+        }
+        let method = u16::from_le_bytes([head[10], head[11]]);
+        let name_len = u16::from_le_bytes([head[28], head[29]]) as usize;
+        let extra_len = u16::from_le_bytes([head[30], head[31]]) as usize;
+        let comment_len = u16::from_le_bytes([head[32], head[33]]) as usize;
+        let rest = read_at(file, size, at + 46, name_len + extra_len)?;
+        let entry = String::from_utf8_lossy(&rest[..name_len]).into_owned();
+        if entry == wanted {
+            if method != 0 {
+                return Err(anyhow!("compressed npz archives are not supported"));
+            }
+            let (mut length, mut offset) = (u32_at(&head, 20) as u64, u32_at(&head, 42) as u64);
+            if length == u32::MAX as u64 || offset == u32::MAX as u64 {
+                (length, offset) = zip64_fields(&head, &rest[name_len..])?;
+            }
+            let local = read_at(file, size, offset, 30)?;
+            if u32_at(&local, 0) != LOCAL_HEADER {
+                return Err(anyhow!("invalid npz entry"));
+            }
+            let name_len = u16::from_le_bytes([local[26], local[27]]) as u64;
+            let extra_len = u16::from_le_bytes([local[28], local[29]]) as u64;
+            let start = offset + 30 + name_len + extra_len;
+            let end = start.checked_add(length).filter(|&end| end <= size);
+            return Ok((start, end.ok_or(anyhow!("truncated npz entry"))?));
+        }
+        names.push(entry.trim_end_matches(".npy").to_string());
+        at += 46 + (name_len + extra_len + comment_len) as u64;
+    }
+    Err(anyhow!("no array {name} in npz file, which has {}", names.join(", ")))
+}
+
+/// Stored size and local header offset of a central directory header,
+/// taking those that overflowed from its zip64 extra field. The field holds
+/// the overflowed values only, in the order size, stored size, offset.
+fn zip64_fields(head: &[u8], mut extra: &[u8]) -> Result<(u64, u64)> {
+    while extra.len() >= 4 {
+        let id = u16::from_le_bytes([extra[0], extra[1]]);
+        let len = u16::from_le_bytes([extra[2], extra[3]]) as usize;
+        let data = extra.get(4..4 + len).ok_or(anyhow!("invalid npz directory"))?;
+        if id == 0x0001 {
+            let mut values = data.chunks_exact(8);
+            let mut field = |at: usize| -> Result<u64> {
+                let value = u32_at(head, at) as u64;
+                if value != u32::MAX as u64 {
+                    return Ok(value);
+                }
+                let bytes = values.next().ok_or(anyhow!("invalid npz directory"))?;
+                Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+            };
+            field(24)?;
+            return Ok((field(20)?, field(42)?));
+        }
+        extra = &extra[4 + len..];
+    }
+    Err(anyhow!("invalid npz directory"))
+}
+
+/// Rows of an array, read one at a time.
+pub struct Rows {
+    file: BufReader<File>,
+    array: Array,
+    row: Vec<u8>,
+    next: usize,
+}
+
+impl Rows {
+    fn new(mut file: BufReader<File>, array: Array) -> Result<Self> {
+        file.seek(SeekFrom::Start(array.start))?;
+        // an empty array may claim any number of columns
+        let row = vec![0; if array.rows == 0 { 0 } else { array.cols * array.dtype.size() }];
+        Ok(Self {
+            file,
+            array,
+            row,
+            next: 0,
+        })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.array.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.array.cols
+    }
+
+    /// The next row, or `None` after the last.
+    fn next_raw(&mut self) -> Result<Option<&[u8]>> {
+        if self.next == self.array.rows {
+            return Ok(None);
+        }
+        self.file.read_exact(&mut self.row)?;
+        self.next += 1;
+        Ok(Some(&self.row))
+    }
+
+    /// The next row converted to `f32`.
+    pub fn next_f32(&mut self) -> Result<Option<Vec<f32>>> {
+        let dtype = self.array.dtype;
+        let size = dtype.size();
+        let raw = self.next_raw()?;
+        let Some(raw) = raw else { return Ok(None) };
+        let row = raw.chunks_exact(size);
+        Ok(Some(match dtype {
+            Dtype::Float(2) => row.map(|b| f16::from_le_bytes([b[0], b[1]]).to_f32()).collect(),
+            Dtype::Float(4) => row.map(|b| f32::from_bits(bits(b) as u32)).collect(),
+            Dtype::Float(_) => row.map(|b| f64::from_bits(bits(b)) as f32).collect(),
+            Dtype::Signed(_) => row.map(|b| signed(b) as f32).collect(),
+            Dtype::Unsigned(_) => row.map(|b| bits(b) as f32).collect(),
+            Dtype::Unicode(_) | Dtype::Bytes(_) => {
+                return Err(anyhow!("npy array holds strings, not numbers"));
+            }
+        }))
+    }
+
+    /// The next row of an integer array.
+    pub fn next_i64(&mut self) -> Result<Option<Vec<i64>>> {
+        let dtype = self.array.dtype;
+        let size = dtype.size();
+        let raw = self.next_raw()?;
+        let Some(raw) = raw else { return Ok(None) };
+        let row = raw.chunks_exact(size);
+        match dtype {
+            Dtype::Signed(_) => Ok(Some(row.map(signed).collect())),
+            Dtype::Unsigned(_) => Ok(Some(row.map(|b| bits(b) as i64).collect())),
+            _ => Err(anyhow!("not an integer npy array")),
+        }
+    }
+
+    /// The next element of a one-dimensional array of ids: integers, or
+    /// strings parsed as `kind`.
+    pub fn next_id(&mut self, kind: IdKind) -> Result<Option<Id>> {
+        if self.array.cols != 1 {
+            return Err(anyhow!("ids must be a one-dimensional array"));
+        }
+        let dtype = self.array.dtype;
+        let Some(raw) = self.next_raw()? else {
+            return Ok(None);
+        };
+        let text = match dtype {
+            Dtype::Unicode(_) => raw
+                .chunks_exact(4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+                .take_while(|&c| c != 0)
+                .map(|c| char::from_u32(c).ok_or(anyhow!("invalid character in id")))
+                .collect::<Result<String>>()?,
+            Dtype::Bytes(_) => {
+                let end = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+                String::from_utf8(raw[..end].to_vec())?
+            }
+            Dtype::Signed(_) if signed(raw) < 0 => {
+                return Err(anyhow!("negative id: {}", signed(raw)));
+            }
+            Dtype::Signed(_) | Dtype::Unsigned(_) => bits(raw).to_string(),
+            Dtype::Float(_) => return Err(anyhow!("ids must be integers or strings")),
+        };
+        Id::parse(&text, kind).map(Some)
+    }
+}
+
+/// Writes a two-dimensional `f32` array to a `.npy` file, one row at a time.
+pub struct NpyWriter {
+    file: BufWriter<File>,
+    rows: usize,
+    cols: usize,
+    written: usize,
+}
+
+impl NpyWriter {
+    /// Creates the file at `path` for `rows` rows of `cols` values.
+    pub fn create(path: impl AsRef<Path>, rows: usize, cols: usize) -> Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let mut text =
+            format!("{{'descr': '<f4', 'fortran_order': False, 'shape': ({rows}, {cols}), }}");
+        // the data starts on a multiple of 64 bytes, after a line break
+        while !(MAGIC.len() + 4 + text.len() + 1).is_multiple_of(64) {
+            text.push(' ');
+        }
+        text.push('\n');
+        file.write_all(MAGIC)?;
+        file.write_all(&[1, 0])?;
+        file.write_all(&(text.len() as u16).to_le_bytes())?;
+        file.write_all(text.as_bytes())?;
+        Ok(Self {
+            file,
+            rows,
+            cols,
+            written: 0,
+        })
+    }
+
+    pub fn write(&mut self, row: &[f32]) -> Result<()> {
+        if row.len() != self.cols {
+            return Err(anyhow!("dimension mismatch"));
+        }
+        if self.written == self.rows {
+            return Err(anyhow!("npy file already holds {} rows", self.rows));
+        }
+        for x in row {
+            self.file.write_all(&x.to_le_bytes())?;
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Flushes the file, which must have received all its rows.
+    pub fn finish(mut self) -> Result<()> {
+        if self.written != self.rows {
+            return Err(anyhow!("{} of {} npy rows written", self.written, self.rows));
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+fn signed(b: &[u8]) -> i64 {
+    // sign-extend from the width of the value
+    let shift = 64 - 8 * b.len() as u32;
+    (bits(b) << shift) as i64 >> shift
+}
+
+/// Little-endian value of up to 8 bytes.
+fn bits(b: &[u8]) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes[..b.len()].copy_from_slice(b);
+    u64::from_le_bytes(bytes)
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+/// Reads `len` bytes at `at`, which must end by `end`. Lengths come from
+/// the file itself, so they are checked before anything is allocated.
+fn read_at(file: &mut BufReader<File>, end: u64, at: u64, len: usize) -> Result<Vec<u8>> {
+    if at.checked_add(len as u64).is_none_or(|stop| stop > end) {
+        return Err(anyhow!("truncated npy file"));
+    }
+    file.seek(SeekFrom::Start(at))?;
+    let mut buf = vec![0u8; len];
+    file.read_exact(&mut buf)?;
+    Ok(buf)
+}
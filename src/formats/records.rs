@@ -0,0 +1,310 @@
+//! Entries as JSON Lines or CSV, for moving collections in and out of
+//! `.vdb` files.
+//!
+//! A JSON line is an object with an `id`, a `vector` array and optionally
+//! `metadata` with a `label` and a `description`. A CSV file starts with a
+//! header naming its `id` and `vector` columns and optionally `label` and
+//! `description`; a vector is written as comma-separated numbers, in quotes,
+//! with or without surrounding brackets.
+
+use crate::types::{Id, IdKind, Metadata};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// One entry of an import or export file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub id: Id,
+    pub vector: Vec<f32>,
+    pub metadata: Metadata,
+}
+
+/// A line read from an import file, with the record it holds or why it
+/// holds none.
+pub struct Line {
+    /// Line number in the file, from 1.
+    pub number: usize,
+    /// The line as read, without its line break. CSV records spanning
+    /// several lines are joined into one.
+    pub text: String,
+    pub record: Result<Record>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Jsonl,
+    Csv,
+}
+
+fn format(path: &Path) -> Result<Format> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match ext.to_ascii_lowercase().as_str() {
+        "jsonl" | "ndjson" => Ok(Format::Jsonl),
+        "csv" => Ok(Format::Csv),
+        _ => Err(anyhow!("unknown record file format: {ext}")),
+    }
+}
+
+/// Reads the records of a `.jsonl` (or `.ndjson`) or `.csv` file one line at
+/// a time. Ids are parsed as `id_kind`.
+pub struct RecordReader {
+    source: Source,
+    id_kind: IdKind,
+}
+
+enum Source {
+    Jsonl {
+        reader: BufReader<File>,
+        number: usize,
+    },
+    Csv {
+        reader: csv::Reader<BufReader<File>>,
+        header: String,
+        columns: Columns,
+    },
+}
+
+/// Positions of the known CSV columns.
+struct Columns {
+    id: usize,
+    vector: usize,
+    label: Option<usize>,
+    description: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct JsonRecord {
+    id: serde_json::Value,
+    vector: Vec<f32>,
+    #[serde(default)]
+    metadata: Metadata,
+}
+
+#[derive(Serialize)]
+struct JsonRecordRef<'a> {
+    id: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vector: Option<&'a [f32]>,
+    metadata: &'a Metadata,
+}
+
+impl RecordReader {
+    pub fn open(path: impl AsRef<Path>, id_kind: IdKind) -> Result<Self> {
+        let path = path.as_ref();
+        let file = BufReader::new(File::open(path)?);
+        let source = match format(path)? {
+            Format::Jsonl => Source::Jsonl {
+                reader: file,
+                number: 0,
+            },
+            Format::Csv => {
+                let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(file);
+                let names = reader.byte_headers()?.clone();
+                let column = |name: &str| names.iter().position(|c| c == name.as_bytes());
+                let columns = Columns {
+                    id: column("id").ok_or(anyhow!("no id column"))?,
+                    vector: column("vector").ok_or(anyhow!("no vector column"))?,
+                    label: column("label"),
+                    description: column("description"),
+                };
+                Source::Csv {
+                    header: csv_line(&names)?,
+                    reader,
+                    columns,
+                }
+            }
+        };
+        Ok(Self { source, id_kind })
+    }
+
+    /// Header line of a CSV file, which a file of rejected lines needs to be
+    /// read again.
+    pub fn header(&self) -> Option<&str> {
+        match &self.source {
+            Source::Jsonl { .. } => None,
+            Source::Csv { header, .. } => Some(header),
+        }
+    }
+
+    fn next_line(&mut self) -> Result<Option<Line>> {
+        let id_kind = self.id_kind;
+        match &mut self.source {
+            Source::Jsonl { reader, number } => {
+                let mut bytes = Vec::new();
+                loop {
+                    bytes.clear();
+                    if reader.read_until(b'\n', &mut bytes)? == 0 {
+                        return Ok(None);
+                    }
+                    *number += 1;
+                    let text = String::from_utf8_lossy(&bytes);
+                    let text = text.trim_end_matches(['\n', '\r']).to_string();
+                    if text.trim().is_empty() {
+                        continue;
+                    }
+                    let record = std::str::from_utf8(&bytes)
+                        .map_err(|e| anyhow!("{e}"))
+                        .and_then(|line| parse_json(line, id_kind));
+                    return Ok(Some(Line {
+                        number: *number,
+                        text,
+                        record,
+                    }));
+                }
+            }
+            Source::Csv { reader, columns, .. } => {
+                let mut fields = csv::ByteRecord::new();
+                if !reader.read_byte_record(&mut fields)? {
+                    return Ok(None);
+                }
+                let number = fields.position().map_or(0, |p| p.line() as usize);
+                Ok(Some(Line {
+                    number,
+                    text: csv_line(&fields)?,
+                    record: parse_csv(&fields, columns, id_kind),
+                }))
+            }
+        }
+    }
+}
+
+impl Iterator for RecordReader {
+    type Item = Result<Line>;
+
+    /// The next line; an error means the file cannot be read further.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_line().transpose()
+    }
+}
+
+fn parse_json(line: &str, id_kind: IdKind) -> Result<Record> {
+    let record: JsonRecord = serde_json::from_str(line)?;
+    let id = match record.id {
+        serde_json::Value::String(s) => Id::parse(&s, id_kind)?,
+        serde_json::Value::Number(n) => Id::parse(&n.to_string(), id_kind)?,
+        other => return Err(anyhow!("invalid id: {other}")),
+    };
+    Ok(Record {
+        id,
+        vector: record.vector,
+        metadata: record.metadata,
+    })
+}
+
+fn parse_csv(fields: &csv::ByteRecord, columns: &Columns, id_kind: IdKind) -> Result<Record> {
+    let field = |i: usize| -> Result<&str> {
+        let bytes = fields.get(i).ok_or(anyhow!("missing column {}", i + 1))?;
+        Ok(std::str::from_utf8(bytes)?)
+    };
+    let optional = |i: Option<usize>| -> Result<Option<&str>> {
+        i.filter(|&i| i < fields.len()).map(field).transpose()
+    };
+    let vector = field(columns.vector)?;
+    let vector = vector.trim().trim_start_matches('[').trim_end_matches(']');
+    let vector = vector
+        .split(',')
+        .map(|x| x.trim().parse::<f32>().map_err(|e| anyhow!("{e}: {x:?}")))
+        .collect::<Result<Vec<f32>>>()?;
+    Ok(Record {
+        id: Id::parse(field(columns.id)?, id_kind)?,
+        vector,
+        metadata: Metadata {
+            label: optional(columns.label)?.unwrap_or("").to_string(),
+            description: optional(columns.description)?.filter(|d| !d.is_empty()).map(String::from),
+        },
+    })
+}
+
+/// CSV text of `fields`, without a line break.
+fn csv_line(fields: &csv::ByteRecord) -> Result<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+    writer.write_byte_record(fields)?;
+    let mut bytes = writer.into_inner().map_err(|e| anyhow!("{e}"))?;
+    bytes.pop();
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Writes records to a `.jsonl` (or `.ndjson`) or `.csv` file in the layout
+/// [`RecordReader`] reads. Integer ids are written as JSON numbers.
+pub struct RecordWriter {
+    sink: Sink,
+    vectors: bool,
+}
+
+enum Sink {
+    Jsonl(BufWriter<File>),
+    Csv(Box<csv::Writer<BufWriter<File>>>),
+}
+
+impl RecordWriter {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format = format(path)?;
+        let file = BufWriter::new(File::create(path)?);
+        let sink = match format {
+            Format::Jsonl => Sink::Jsonl(file),
+            Format::Csv => {
+                let mut writer = csv::Writer::from_writer(file);
+                writer.write_record(["id", "vector", "label", "description"])?;
+                Sink::Csv(Box::new(writer))
+            }
+        };
+        Ok(Self {
+            sink,
+            vectors: true,
+        })
+    }
+
+    /// Creates a JSON Lines file of ids and metadata only, such as goes with
+    /// the vectors of an exported `.npy` file. [`RecordWriter::write`]
+    /// ignores the vectors it is given.
+    pub fn create_metadata(path: impl AsRef<Path>) -> Result<Self> {
+        let sink = Sink::Jsonl(BufWriter::new(File::create(path)?));
+        Ok(Self {
+            sink,
+            vectors: false,
+        })
+    }
+
+    pub fn write(&mut self, id: &Id, vector: &[f32], metadata: &Metadata) -> Result<()> {
+        match &mut self.sink {
+            Sink::Jsonl(writer) => {
+                let id = match id {
+                    Id::Int(i) => serde_json::Value::from(*i),
+                    id => serde_json::Value::from(id.to_string()),
+                };
+                let record = JsonRecordRef {
+                    id,
+                    vector: self.vectors.then_some(vector),
+                    metadata,
+                };
+                serde_json::to_writer(&mut *writer, &record)?;
+                writer.write_all(b"\n")?;
+            }
+            Sink::Csv(writer) => {
+                let vector: Vec<String> = vector.iter().map(|x| x.to_string()).collect();
+                writer.write_record([
+                    &id.to_string(),
+                    &vector.join(","),
+                    &metadata.label,
+                    metadata.description.as_deref().unwrap_or(""),
+                ])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes the file.
+    pub fn finish(self) -> Result<()> {
+        match self.sink {
+            Sink::Jsonl(mut writer) => writer.flush()?,
+            Sink::Csv(mut writer) => writer.flush()?,
+        }
+        Ok(())
+    }
+}
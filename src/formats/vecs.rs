@@ -0,0 +1,116 @@
+//! The `.fvecs`, `.ivecs` and `.bvecs` files of the SIFT and GIST corpora:
+//! each vector is a little-endian `i32` dimension followed by that many
+//! components, `f32`, `i32` or `u8` respectively.
+
+use anyhow::{Result, anyhow};
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read, Seek, SeekFrom};
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Component {
+    F32,
+    I32,
+    U8,
+}
+
+impl Component {
+    fn size(self) -> usize {
+        match self {
+            Component::F32 | Component::I32 => 4,
+            Component::U8 => 1,
+        }
+    }
+}
+
+/// Vectors of a `.*vecs` file, read one at a time.
+pub struct VecsReader {
+    reader: BufReader<File>,
+    component: Component,
+    dim: usize,
+    len: usize,
+    buf: Vec<u8>,
+}
+
+impl VecsReader {
+    pub fn open(path: &Path, component: Component) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let size = reader.get_ref().metadata()?.len();
+        let dim = read_dim(&mut reader)?.unwrap_or(0);
+        reader.seek(SeekFrom::Start(0))?;
+        let row = 4 + dim * component.size();
+        if size % row as u64 != 0 {
+            return Err(anyhow!("file size is not a multiple of a {dim}-dimensional vector"));
+        }
+        Ok(Self {
+            reader,
+            component,
+            dim,
+            len: (size / row as u64) as usize,
+            buf: vec![0; dim * component.size()],
+        })
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Number of vectors in the file.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The next vector as little-endian components, or `None` at the end of
+    /// the file.
+    fn next_raw(&mut self) -> Result<Option<&[u8]>> {
+        match read_dim(&mut self.reader)? {
+            None => Ok(None),
+            Some(dim) if dim != self.dim => {
+                Err(anyhow!("vector of dimension {dim} in a {}-dimensional file", self.dim))
+            }
+            Some(_) => {
+                self.reader.read_exact(&mut self.buf)?;
+                Ok(Some(&self.buf))
+            }
+        }
+    }
+
+    pub fn next_f32(&mut self) -> Result<Option<Vec<f32>>> {
+        let component = self.component;
+        Ok(self.next_raw()?.map(|raw| match component {
+            Component::F32 => raw.chunks_exact(4).map(|b| f32::from_le_bytes(word(b))).collect(),
+            Component::I32 => {
+                raw.chunks_exact(4).map(|b| i32::from_le_bytes(word(b)) as f32).collect()
+            }
+            Component::U8 => raw.iter().map(|&b| b as f32).collect(),
+        }))
+    }
+
+    /// The next vector of an `.ivecs` file.
+    pub fn next_i32(&mut self) -> Result<Option<Vec<i32>>> {
+        if self.component != Component::I32 {
+            return Err(anyhow!("not an ivecs file"));
+        }
+        let raw = self.next_raw()?;
+        Ok(raw.map(|raw| raw.chunks_exact(4).map(|b| i32::from_le_bytes(word(b))).collect()))
+    }
+}
+
+/// Reads the dimension that starts a vector; `None` at the end of the file.
+fn read_dim(reader: &mut impl Read) -> Result<Option<usize>> {
+    let mut head = [0u8; 4];
+    match reader.read_exact(&mut head) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let dim = i32::from_le_bytes(head);
+    if dim < 0 {
+        return Err(anyhow!("negative dimension: {dim}"));
+    }
+    Ok(Some(dim as usize))
+}
+
+fn word(bytes: &[u8]) -> [u8; 4] {
+    bytes.try_into().unwrap()
+}
@@ -0,0 +1,348 @@
+//! Hierarchical navigable small world graph (Malkov & Yashunin) that, unlike
+//! the `hnsw` crate it replaces, can remove nodes again.
+
+use rand_core::{RngCore, SeedableRng};
+use rand_pcg::Pcg64;
+use space::{Metric, Neighbor};
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::hash::{BuildHasherDefault, Hasher};
+
+/// Highest layer a node can be drawn into.
+const MAX_LEVEL: usize = 16;
+
+/// HNSW graph with at most `M` neighbors per node on upper layers and `M0`
+/// on layer 0. Nodes are numbered in insertion order; a removed node keeps
+/// its number, which is never handed out again.
+pub struct Graph<Met, T, const M: usize, const M0: usize> {
+    metric: Met,
+    nodes: Vec<Option<Node<T>>>,
+    /// Node on the top layer that searches start from.
+    entry: Option<u32>,
+    len: usize,
+    ef_construction: usize,
+    rng: Pcg64,
+}
+
+struct Node<T> {
+    feature: T,
+    /// Neighbors on each layer the node is part of, starting with layer 0.
+    links: Vec<Vec<u32>>,
+}
+
+/// Scratch space of a search, reused across searches. Its buffers grow
+/// with the beam width and the nodes visited, never with the graph.
+#[derive(Default)]
+pub struct Searcher {
+    visited: HashSet<u32, BuildHasherDefault<NodeHasher>>,
+    /// Nodes still to expand, nearest first.
+    candidates: BinaryHeap<Reverse<(u32, u32)>>,
+    /// Best nodes found so far, farthest first.
+    nearest: BinaryHeap<(u32, u32)>,
+    /// `nearest` in order, once the search is done.
+    found: Vec<(u32, u32)>,
+}
+
+/// Hashes node numbers for the visited set. Spreading them by a multiply is
+/// enough and much cheaper than SipHash, which showed in search profiles.
+#[derive(Default)]
+struct NodeHasher(u64);
+
+impl Hasher for NodeHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0.rotate_left(8) ^ b as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        }
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.0 = (n as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
+}
+
+thread_local! {
+    /// Searchers left by the queries that ran on this thread.
+    static POOL: RefCell<Vec<Searcher>> = const { RefCell::new(Vec::new()) };
+}
+
+impl Searcher {
+    /// Runs `f` with a searcher taken from the current thread's pool, so
+    /// that queries reuse the buffers of earlier ones.
+    pub fn pooled<R>(f: impl FnOnce(&mut Searcher) -> R) -> R {
+        let mut searcher = POOL.with_borrow_mut(Vec::pop).unwrap_or_default();
+        let result = f(&mut searcher);
+        POOL.with_borrow_mut(|pool| pool.push(searcher));
+        result
+    }
+
+    fn clear(&mut self) {
+        self.visited.clear();
+        self.candidates.clear();
+        self.nearest.clear();
+    }
+
+    /// The nodes found, as `(distance, node)` pairs nearest first.
+    fn found(&mut self) -> &[(u32, u32)] {
+        self.found.clear();
+        self.found.extend(self.nearest.drain());
+        self.found.sort_unstable();
+        &self.found
+    }
+}
+
+impl<Met, T, const M: usize, const M0: usize> Graph<Met, T, M, M0>
+where
+    Met: Metric<T, Unit = u32>,
+{
+    pub fn new(metric: Met, ef_construction: usize) -> Self {
+        Self {
+            metric,
+            nodes: Vec::new(),
+            entry: None,
+            len: 0,
+            ef_construction,
+            rng: Pcg64::seed_from_u64(0),
+        }
+    }
+
+    /// Number of nodes that have not been removed.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn feature(&self, node: usize) -> &T {
+        &self.node(node as u32).feature
+    }
+
+    fn node(&self, node: u32) -> &Node<T> {
+        self.nodes[node as usize].as_ref().expect("removed node")
+    }
+
+    fn links(&self, node: u32, layer: usize) -> &[u32] {
+        &self.node(node).links[layer]
+    }
+
+    fn top_layer(&self, node: u32) -> usize {
+        self.node(node).links.len() - 1
+    }
+
+    fn distance(&self, a: u32, b: u32) -> u32 {
+        self.metric.distance(&self.node(a).feature, &self.node(b).feature)
+    }
+
+    fn capacity(layer: usize) -> usize {
+        if layer == 0 { M0 } else { M }
+    }
+
+    fn random_level(&mut self) -> usize {
+        let uniform = (self.rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        let level = -(1.0 - uniform).ln() / (M as f64).ln();
+        (level as usize).min(MAX_LEVEL)
+    }
+
+    /// Adds `feature` and returns its node number.
+    pub fn insert(&mut self, feature: T, searcher: &mut Searcher) -> usize {
+        let level = self.random_level();
+        let node = self.nodes.len() as u32;
+        self.nodes.push(Some(Node {
+            feature,
+            links: vec![Vec::new(); level + 1],
+        }));
+        self.len += 1;
+        let Some(entry) = self.entry else {
+            self.entry = Some(node);
+            return node as usize;
+        };
+
+        let top = self.top_layer(entry);
+        let mut nearest = entry;
+        for layer in (level + 1..=top).rev() {
+            nearest = self.greedy(&self.node(node).feature, nearest, layer);
+        }
+        for layer in (0..=level.min(top)).rev() {
+            self.search_layer(&self.node(node).feature, nearest, self.ef_construction, layer, searcher);
+            let candidates = searcher.found();
+            nearest = candidates[0].1;
+            let selected = self.select(candidates, Self::capacity(layer));
+            for &neighbor in &selected {
+                self.connect(neighbor, node, layer);
+            }
+            self.nodes[node as usize].as_mut().unwrap().links[layer] = selected;
+        }
+        if level > top {
+            self.entry = Some(node);
+        }
+        node as usize
+    }
+
+    /// Adds a link from `from` to `to`, pruning the links of `from` if they
+    /// exceed the layer's capacity.
+    fn connect(&mut self, from: u32, to: u32, layer: usize) {
+        let mut links = self.links(from, layer).to_vec();
+        links.push(to);
+        if links.len() > Self::capacity(layer) {
+            let mut scored: Vec<(u32, u32)> =
+                links.iter().map(|&n| (self.distance(from, n), n)).collect();
+            scored.sort_unstable();
+            links = self.select(&scored, Self::capacity(layer));
+        }
+        self.nodes[from as usize].as_mut().unwrap().links[layer] = links;
+    }
+
+    /// Picks up to `capacity` neighbors among `candidates`, given nearest
+    /// first with their distance to the node being linked. A candidate is
+    /// preferred when it is closer to that node than to any neighbor picked
+    /// before it, which spreads links across directions; the remaining slots
+    /// are filled with the nearest of the others.
+    fn select(&self, candidates: &[(u32, u32)], capacity: usize) -> Vec<u32> {
+        let mut selected: Vec<u32> = Vec::with_capacity(capacity);
+        let mut pruned = Vec::new();
+        for &(d, c) in candidates {
+            if selected.len() == capacity {
+                break;
+            }
+            if selected.iter().all(|&s| self.distance(c, s) > d) {
+                selected.push(c);
+            } else {
+                pruned.push(c);
+            }
+        }
+        let room = capacity - selected.len();
+        selected.extend(pruned.into_iter().take(room));
+        selected
+    }
+
+    /// Removes `node` from the graph. Nodes that linked to it are relinked
+    /// among their remaining neighbors and those of the removed node, so the
+    /// graph stays navigable around the gap.
+    pub fn remove(&mut self, node: usize) {
+        let node = node as u32;
+        let removed = self.nodes[node as usize].take().expect("removed node");
+        self.len -= 1;
+        for (layer, out) in removed.links.iter().enumerate() {
+            // Links are mostly mutual, but pruning drops some back links; the
+            // nodes still pointing at the removed one are close to it, so they
+            // are looked for two hops out.
+            let mut suspects: HashSet<u32> = out.iter().copied().collect();
+            for &n in out {
+                if let Some(neighbor) = &self.nodes[n as usize] {
+                    suspects.extend(&neighbor.links[layer]);
+                }
+            }
+            for s in suspects {
+                let Some(suspect) = &self.nodes[s as usize] else {
+                    continue;
+                };
+                if !suspect.links[layer].contains(&node) {
+                    continue;
+                }
+                let mut candidates: Vec<u32> = suspect.links[layer]
+                    .iter()
+                    .chain(out)
+                    .copied()
+                    .filter(|&c| c != s && self.nodes[c as usize].is_some())
+                    .collect();
+                candidates.sort_unstable();
+                candidates.dedup();
+                let mut scored: Vec<(u32, u32)> =
+                    candidates.into_iter().map(|c| (self.distance(s, c), c)).collect();
+                scored.sort_unstable();
+                let links = self.select(&scored, Self::capacity(layer));
+                self.nodes[s as usize].as_mut().unwrap().links[layer] = links;
+            }
+        }
+        if self.entry == Some(node) {
+            self.entry = (0..self.nodes.len() as u32)
+                .filter(|&n| self.nodes[n as usize].is_some())
+                .max_by_key(|&n| (self.top_layer(n), Reverse(n)));
+        }
+    }
+
+    /// Fills `dest` with the nearest nodes to `query` found with a beam of
+    /// `ef`, nearest first, and returns the filled part. It is shorter than
+    /// `dest` when the graph has fewer nodes.
+    pub fn nearest<'a>(
+        &self,
+        query: &T,
+        ef: usize,
+        searcher: &mut Searcher,
+        dest: &'a mut [Neighbor<u32>],
+    ) -> &'a mut [Neighbor<u32>] {
+        let Some(entry) = self.entry else {
+            return &mut dest[..0];
+        };
+        let mut nearest = entry;
+        for layer in (1..=self.top_layer(entry)).rev() {
+            nearest = self.greedy(query, nearest, layer);
+        }
+        self.search_layer(query, nearest, ef.max(dest.len()), 0, searcher);
+        let found = searcher.found();
+        let n = found.len().min(dest.len());
+        for (d, &(distance, index)) in dest.iter_mut().zip(found) {
+            *d = Neighbor {
+                index: index as usize,
+                distance,
+            };
+        }
+        &mut dest[..n]
+    }
+
+    /// Walks `layer` from `start` to the node nearest to `query` that has no
+    /// nearer neighbor.
+    fn greedy(&self, query: &T, start: u32, layer: usize) -> u32 {
+        let mut current = start;
+        let mut best = self.metric.distance(query, &self.node(current).feature);
+        loop {
+            let mut moved = false;
+            for &n in self.links(current, layer) {
+                let Some(neighbor) = &self.nodes[n as usize] else {
+                    continue;
+                };
+                let d = self.metric.distance(query, &neighbor.feature);
+                if d < best {
+                    (best, current, moved) = (d, n, true);
+                }
+            }
+            if !moved {
+                return current;
+            }
+        }
+    }
+
+    /// Beam search of width `ef` on `layer` from `start`. The result is left
+    /// in `searcher.nearest`.
+    fn search_layer(&self, query: &T, start: u32, ef: usize, layer: usize, searcher: &mut Searcher) {
+        searcher.clear();
+        let d = self.metric.distance(query, &self.node(start).feature);
+        searcher.visited.insert(start);
+        searcher.candidates.push(Reverse((d, start)));
+        searcher.nearest.push((d, start));
+        while let Some(Reverse((d, current))) = searcher.candidates.pop() {
+            if searcher.nearest.len() >= ef && d > searcher.nearest.peek().unwrap().0 {
+                break;
+            }
+            for &n in self.links(current, layer) {
+                if !searcher.visited.insert(n) {
+                    continue;
+                }
+                // links to removed nodes that were not repaired are skipped
+                let Some(neighbor) = &self.nodes[n as usize] else {
+                    continue;
+                };
+                let d = self.metric.distance(query, &neighbor.feature);
+                if searcher.nearest.len() < ef || d < searcher.nearest.peek().unwrap().0 {
+                    searcher.candidates.push(Reverse((d, n)));
+                    searcher.nearest.push((d, n));
+                    if searcher.nearest.len() > ef {
+                        searcher.nearest.pop();
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Inverted file index: vectors are grouped by the nearest centroid of a
+//! k-means coarse quantizer, and a search scans only the lists of the
+//! centroids nearest to the query.
+
+use crate::element::{self, Element};
+use crate::metrics;
+use crate::types::Metric;
+use ordered_float::NotNan;
+use rand_core::{RngCore, SeedableRng};
+use rand_pcg::Pcg64;
+use rayon::prelude::*;
+use space::Neighbor;
+use std::collections::BinaryHeap;
+
+/// Vectors per list a collection holds when its quantizer is trained.
+pub const TRAINING_POINTS_PER_LIST: usize = 32;
+
+/// Vectors per list that k-means is run on at most; larger collections are
+/// sampled.
+const SAMPLE_POINTS_PER_LIST: usize = 256;
+
+const KMEANS_ITERATIONS: usize = 10;
+
+/// Inverted file over vectors with components of type `T`. Nodes are
+/// numbered in insertion order like those of a graph, and the number of a
+/// removed node is never reused.
+pub struct Ivf<T> {
+    metric: Metric,
+    nlist: usize,
+    nprobe: usize,
+    /// One centroid per list; empty until trained, when every vector is in
+    /// the single list.
+    centroids: Vec<Vec<f32>>,
+    lists: Vec<Vec<u32>>,
+    nodes: Vec<Option<Node<T>>>,
+    len: usize,
+}
+
+struct Node<T> {
+    feature: Vec<T>,
+    list: u32,
+}
+
+/// The parts of an [`Ivf`] that do not depend on its element type.
+pub trait InvertedFile {
+    fn is_trained(&self) -> bool;
+
+    /// Whether the collection has grown enough to train the quantizer.
+    fn needs_training(&self) -> bool;
+
+    /// Runs k-means over the vectors held and moves each into the list of
+    /// its nearest centroid.
+    fn train(&mut self);
+
+    fn centroids(&self) -> &[Vec<f32>];
+
+    /// Takes trained centroids, before any vector is inserted.
+    fn set_centroids(&mut self, centroids: Vec<Vec<f32>>);
+
+    /// List of the centroid nearest to `vector`.
+    fn nearest_list(&self, vector: &[f32]) -> u32;
+
+    /// List holding node `node`.
+    fn list(&self, node: usize) -> u32;
+
+    /// Inserts `vector`, rounded to the element type, into `list`. Returns
+    /// its node number.
+    fn insert_into(&mut self, vector: Vec<f32>, list: u32) -> usize;
+}
+
+impl<T: Element> Ivf<T> {
+    pub fn new(metric: Metric, nlist: usize, nprobe: usize) -> Self {
+        Self {
+            metric,
+            nlist,
+            nprobe,
+            centroids: Vec::new(),
+            lists: vec![Vec::new()],
+            nodes: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Number of nodes that have not been removed.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn feature(&self, node: usize) -> &Vec<T> {
+        &self.nodes[node].as_ref().expect("removed node").feature
+    }
+
+    /// Adds `feature` to the list of its nearest centroid and returns its
+    /// node number.
+    pub fn insert(&mut self, feature: Vec<T>) -> usize {
+        let list = self.nearest_list(&element::widen(&feature));
+        self.push(feature, list)
+    }
+
+    fn push(&mut self, feature: Vec<T>, list: u32) -> usize {
+        let node = self.nodes.len();
+        self.lists[list as usize].push(node as u32);
+        self.nodes.push(Some(Node { feature, list }));
+        self.len += 1;
+        node
+    }
+
+    pub fn remove(&mut self, node: usize) {
+        let removed = self.nodes[node].take().expect("removed node");
+        let list = &mut self.lists[removed.list as usize];
+        let at = list.iter().position(|&n| n == node as u32).unwrap();
+        list.swap_remove(at);
+        self.len -= 1;
+    }
+
+    /// Fills `dest` with the nearest nodes to `query`, nearest first, and
+    /// returns the filled part. The `nprobe` lists nearest to the query are
+    /// scanned, and further ones while fewer vectors than `dest` holds have
+    /// been seen.
+    pub fn nearest<'a>(
+        &self,
+        query: &[T],
+        dest: &'a mut [Neighbor<u32>],
+    ) -> &'a mut [Neighbor<u32>] {
+        let mut order: Vec<(NotNan<f32>, usize)> = if self.is_trained() {
+            let q = element::widen(query);
+            self.centroids
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (NotNan::new(metrics::distance(self.metric, &q, c)).unwrap(), i))
+                .collect()
+        } else {
+            vec![(NotNan::default(), 0)]
+        };
+        order.sort_unstable();
+        // farthest of the best found so far on top
+        let mut nearest: BinaryHeap<(NotNan<f32>, u32)> = BinaryHeap::with_capacity(dest.len() + 1);
+        let mut scanned = 0;
+        for (probed, &(_, list)) in order.iter().enumerate() {
+            if probed >= self.nprobe && scanned >= dest.len() {
+                break;
+            }
+            for &node in &self.lists[list] {
+                let feature = &self.nodes[node as usize].as_ref().unwrap().feature;
+                let d = NotNan::new(metrics::distance(self.metric, query, feature)).unwrap();
+                if nearest.len() < dest.len() {
+                    nearest.push((d, node));
+                } else if nearest.peek().is_some_and(|&(worst, _)| d < worst) {
+                    nearest.pop();
+                    nearest.push((d, node));
+                }
+            }
+            scanned += self.lists[list].len();
+        }
+        let found = nearest.into_sorted_vec();
+        let n = found.len();
+        for (d, (distance, node)) in dest.iter_mut().zip(found) {
+            *d = Neighbor {
+                index: node as usize,
+                distance: distance.into_inner().to_bits(),
+            };
+        }
+        &mut dest[..n]
+    }
+}
+
+impl<T: Element> InvertedFile for Ivf<T> {
+    fn is_trained(&self) -> bool {
+        !self.centroids.is_empty()
+    }
+
+    fn needs_training(&self) -> bool {
+        !self.is_trained() && self.len >= self.nlist * TRAINING_POINTS_PER_LIST
+    }
+
+    fn train(&mut self) {
+        let live: Vec<usize> = (0..self.nodes.len()).filter(|&n| self.nodes[n].is_some()).collect();
+        let mut rng = Pcg64::seed_from_u64(0);
+        let sample_size = live.len().min(self.nlist * SAMPLE_POINTS_PER_LIST);
+        let points: Vec<Vec<f32>> = sample(live.len(), sample_size, &mut rng)
+            .into_iter()
+            .map(|i| element::widen(self.feature(live[i])))
+            .collect();
+        let k = self.nlist.min(points.len());
+        if k == 0 {
+            return;
+        }
+        self.centroids = kmeans(&points, k, self.metric, &mut rng);
+        let lists: Vec<u32> = live
+            .par_iter()
+            .map(|&n| self.nearest_list(&element::widen(self.feature(n))))
+            .collect();
+        self.lists = vec![Vec::new(); k];
+        for (&n, list) in live.iter().zip(lists) {
+            self.lists[list as usize].push(n as u32);
+            self.nodes[n].as_mut().unwrap().list = list;
+        }
+    }
+
+    fn centroids(&self) -> &[Vec<f32>] {
+        &self.centroids
+    }
+
+    fn set_centroids(&mut self, centroids: Vec<Vec<f32>>) {
+        self.lists = vec![Vec::new(); centroids.len().max(1)];
+        self.centroids = centroids;
+    }
+
+    fn nearest_list(&self, vector: &[f32]) -> u32 {
+        nearest_centroid(&self.centroids, vector, self.metric) as u32
+    }
+
+    fn list(&self, node: usize) -> u32 {
+        self.nodes[node].as_ref().expect("removed node").list
+    }
+
+    fn insert_into(&mut self, vector: Vec<f32>, list: u32) -> usize {
+        self.push(element::narrow(&vector), list)
+    }
+}
+
+/// Position of the centroid nearest to `vector`; 0 when there are none.
+fn nearest_centroid(centroids: &[Vec<f32>], vector: &[f32], metric: Metric) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, c)| NotNan::new(metrics::distance(metric, vector, c)).unwrap())
+        .map_or(0, |(i, _)| i)
+}
+
+/// `k` distinct numbers below `n`, picked at random.
+fn sample(n: usize, k: usize, rng: &mut Pcg64) -> Vec<usize> {
+    let mut picked: Vec<usize> = (0..n).collect();
+    for i in 0..k {
+        let j = i + (rng.next_u64() % (n - i) as u64) as usize;
+        picked.swap(i, j);
+    }
+    picked.truncate(k);
+    picked
+}
+
+/// Lloyd's k-means starting from `k` distinct points. Points are assigned by
+/// `metric`, and each centroid moves to the mean of its points, scaled to
+/// unit length for cosine. A centroid left without points restarts from a
+/// random one.
+fn kmeans(points: &[Vec<f32>], k: usize, metric: Metric, rng: &mut Pcg64) -> Vec<Vec<f32>> {
+    let dim = points[0].len();
+    let mut centroids: Vec<Vec<f32>> =
+        sample(points.len(), k, rng).into_iter().map(|i| points[i].clone()).collect();
+    for _ in 0..KMEANS_ITERATIONS {
+        let assigned: Vec<usize> = points
+            .par_iter()
+            .map(|p| nearest_centroid(&centroids, p, metric))
+            .collect();
+        let mut sums = vec![vec![0.0f64; dim]; k];
+        let mut counts = vec![0usize; k];
+        for (p, &c) in points.iter().zip(&assigned) {
+            counts[c] += 1;
+            for (s, &x) in sums[c].iter_mut().zip(p) {
+                *s += x as f64;
+            }
+        }
+        for (c, centroid) in centroids.iter_mut().enumerate() {
+            if counts[c] == 0 {
+                *centroid = points[(rng.next_u64() % points.len() as u64) as usize].clone();
+                continue;
+            }
+            for (x, s) in centroid.iter_mut().zip(&sums[c]) {
+                *x = (s / counts[c] as f64) as f32;
+            }
+            if metric == Metric::Cosine {
+                metrics::normalize(centroid);
+            }
+        }
+    }
+    centroids
+}
@@ -0,0 +1,315 @@
+//! Distance kernels over `f32` slices. The fastest instruction set the CPU
+//! supports is picked at runtime; other CPUs use a portable fallback.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Isa {
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Sse,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    #[cfg(target_arch = "x86_64")]
+    Avx512,
+}
+
+/// A set of distance kernels for one instruction set. Only kernels the
+/// running CPU supports can be obtained.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Kernel(Isa);
+
+impl Kernel {
+    /// The fastest kernel for the running CPU, detected once.
+    pub fn detect() -> Self {
+        static DETECTED: OnceLock<Kernel> = OnceLock::new();
+        *DETECTED.get_or_init(|| *Self::available().last().unwrap())
+    }
+
+    /// Every kernel the running CPU supports, slowest first.
+    pub fn available() -> Vec<Self> {
+        #[allow(unused_mut)]
+        let mut kernels = vec![Kernel(Isa::Scalar)];
+        #[cfg(target_arch = "x86_64")]
+        {
+            // SSE2 is part of x86_64
+            kernels.push(Kernel(Isa::Sse));
+            if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+                kernels.push(Kernel(Isa::Avx2));
+            }
+            if is_x86_feature_detected!("avx512f") {
+                kernels.push(Kernel(Isa::Avx512));
+            }
+        }
+        kernels
+    }
+
+    pub fn name(self) -> &'static str {
+        match self.0 {
+            Isa::Scalar => "scalar",
+            #[cfg(target_arch = "x86_64")]
+            Isa::Sse => "sse",
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx2 => "avx2",
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx512 => "avx512",
+        }
+    }
+
+    pub fn dot(self, a: &[f32], b: &[f32]) -> f32 {
+        // Safety: a `Kernel` only exists for instruction sets the CPU has.
+        match self.0 {
+            Isa::Scalar => scalar::dot(a, b),
+            #[cfg(target_arch = "x86_64")]
+            Isa::Sse => unsafe { sse::dot(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx2 => unsafe { avx2::dot(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx512 => unsafe { avx512::dot(a, b) },
+        }
+    }
+
+    pub fn squared_euclidean(self, a: &[f32], b: &[f32]) -> f32 {
+        match self.0 {
+            Isa::Scalar => scalar::squared_euclidean(a, b),
+            #[cfg(target_arch = "x86_64")]
+            Isa::Sse => unsafe { sse::squared_euclidean(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx2 => unsafe { avx2::squared_euclidean(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx512 => unsafe { avx512::squared_euclidean(a, b) },
+        }
+    }
+
+    pub fn manhattan(self, a: &[f32], b: &[f32]) -> f32 {
+        match self.0 {
+            Isa::Scalar => scalar::manhattan(a, b),
+            #[cfg(target_arch = "x86_64")]
+            Isa::Sse => unsafe { sse::manhattan(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx2 => unsafe { avx2::manhattan(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx512 => unsafe { avx512::manhattan(a, b) },
+        }
+    }
+}
+
+impl fmt::Display for Kernel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+mod scalar {
+    pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    pub fn squared_euclidean(a: &[f32], b: &[f32]) -> f32 {
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| {
+                let d = x - y;
+                d * d
+            })
+            .sum()
+    }
+
+    pub fn manhattan(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum()
+    }
+}
+
+/// Generates the kernels of one instruction set from its lane primitives
+/// `zero`, `load`, `add`, `sub`, `mul_add`, `abs` and `sum`, which must be in
+/// scope. Elements past the last full register go through the scalar kernels.
+#[cfg(target_arch = "x86_64")]
+macro_rules! kernels {
+    ($feature:literal, $lanes:expr) => {
+        #[target_feature(enable = $feature)]
+        pub unsafe fn dot(a: &[f32], b: &[f32]) -> f32 {
+            let n = a.len().min(b.len());
+            let split = n - n % $lanes;
+            let mut acc = zero();
+            for i in (0..split).step_by($lanes) {
+                acc = mul_add(load(a, i), load(b, i), acc);
+            }
+            sum(acc) + super::scalar::dot(&a[split..n], &b[split..n])
+        }
+
+        #[target_feature(enable = $feature)]
+        pub unsafe fn squared_euclidean(a: &[f32], b: &[f32]) -> f32 {
+            let n = a.len().min(b.len());
+            let split = n - n % $lanes;
+            let mut acc = zero();
+            for i in (0..split).step_by($lanes) {
+                let d = sub(load(a, i), load(b, i));
+                acc = mul_add(d, d, acc);
+            }
+            sum(acc) + super::scalar::squared_euclidean(&a[split..n], &b[split..n])
+        }
+
+        #[target_feature(enable = $feature)]
+        pub unsafe fn manhattan(a: &[f32], b: &[f32]) -> f32 {
+            let n = a.len().min(b.len());
+            let split = n - n % $lanes;
+            let mut acc = zero();
+            for i in (0..split).step_by($lanes) {
+                acc = add(abs(sub(load(a, i), load(b, i))), acc);
+            }
+            sum(acc) + super::scalar::manhattan(&a[split..n], &b[split..n])
+        }
+    };
+}
+
+#[cfg(target_arch = "x86_64")]
+mod sse {
+    use std::arch::x86_64::*;
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn zero() -> __m128 {
+        _mm_setzero_ps()
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn load(v: &[f32], i: usize) -> __m128 {
+        _mm_loadu_ps(v.as_ptr().add(i))
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn add(a: __m128, b: __m128) -> __m128 {
+        _mm_add_ps(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn sub(a: __m128, b: __m128) -> __m128 {
+        _mm_sub_ps(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn mul_add(a: __m128, b: __m128, acc: __m128) -> __m128 {
+        _mm_add_ps(_mm_mul_ps(a, b), acc)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn abs(v: __m128) -> __m128 {
+        _mm_andnot_ps(_mm_set1_ps(-0.0), v)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn sum(v: __m128) -> f32 {
+        let mut lanes = [0.0f32; 4];
+        _mm_storeu_ps(lanes.as_mut_ptr(), v);
+        lanes.iter().sum()
+    }
+
+    kernels!("sse2", 4);
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    #[inline]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn zero() -> __m256 {
+        _mm256_setzero_ps()
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn load(v: &[f32], i: usize) -> __m256 {
+        _mm256_loadu_ps(v.as_ptr().add(i))
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn add(a: __m256, b: __m256) -> __m256 {
+        _mm256_add_ps(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn sub(a: __m256, b: __m256) -> __m256 {
+        _mm256_sub_ps(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn mul_add(a: __m256, b: __m256, acc: __m256) -> __m256 {
+        _mm256_fmadd_ps(a, b, acc)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn abs(v: __m256) -> __m256 {
+        _mm256_andnot_ps(_mm256_set1_ps(-0.0), v)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn sum(v: __m256) -> f32 {
+        let mut lanes = [0.0f32; 8];
+        _mm256_storeu_ps(lanes.as_mut_ptr(), v);
+        lanes.iter().sum()
+    }
+
+    kernels!("avx2,fma", 8);
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx512 {
+    use std::arch::x86_64::*;
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn zero() -> __m512 {
+        _mm512_setzero_ps()
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn load(v: &[f32], i: usize) -> __m512 {
+        _mm512_loadu_ps(v.as_ptr().add(i))
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn add(a: __m512, b: __m512) -> __m512 {
+        _mm512_add_ps(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn sub(a: __m512, b: __m512) -> __m512 {
+        _mm512_sub_ps(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn mul_add(a: __m512, b: __m512, acc: __m512) -> __m512 {
+        _mm512_fmadd_ps(a, b, acc)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn abs(v: __m512) -> __m512 {
+        _mm512_abs_ps(v)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn sum(v: __m512) -> f32 {
+        _mm512_reduce_add_ps(v)
+    }
+
+    kernels!("avx512f", 16);
+}
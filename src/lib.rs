@@ -1,12 +1,41 @@
+mod binary;
+mod database;
+mod element;
+mod eval;
+#[cfg(feature = "formats")]
+mod formats;
+mod graph;
+mod ivf;
+mod kernels;
 mod metrics;
 mod params;
+mod sparse;
 mod storage;
+mod text;
 mod types;
 mod vector_db;
 
-pub use params::Params;
-pub use types::{Metadata, Metric, SearchResult};
-pub use vector_db::VectorDB;
+pub use binary::BinaryVector;
+pub use database::{stored_m, Database};
+pub use eval::{Evaluation, Rebuild, Tuning, AUTOTUNE_K, DEFAULT_EF_SWEEP};
+#[cfg(feature = "formats")]
+pub use formats::{
+    is_table, read_ground_truth, read_ids, Line, NpyWriter, Record, RecordReader, RecordWriter,
+    TableReader, TableWriter, VectorFile,
+};
+pub use kernels::Kernel;
+pub use ivf::TRAINING_POINTS_PER_LIST;
+pub use ordered_float::OrderedFloat;
+pub use params::{CollectionOptions, ElementType, IndexKind, Params, Quantization};
+pub use sparse::SparseVector;
+pub use text::TextField;
+pub use types::{
+    Fusion, Id, IdKind, Metadata, Metric, Recommend, ScoredResult, SearchResult, SearchResults,
+};
+pub use vector_db::{VectorDB, DEFAULT_OVERSAMPLING};
 
 pub const M: usize = 12;
 pub const M0: usize = 24;
+
+/// Name of the collection used by [`VectorDB::open`].
+pub const DEFAULT_COLLECTION: &str = "default";
@@ -1,84 +1,365 @@
+use crate::binary;
+use crate::element::{self, fold_widened, Element};
+use crate::graph::{Graph, Searcher};
+use crate::ivf::{InvertedFile, Ivf};
+use crate::kernels::Kernel;
+use crate::params::{ElementType, IndexKind};
 use crate::types::Metric;
-use hnsw::Hnsw;
-use rand_pcg::Pcg64;
+use half::{bf16, f16};
 use space::{Metric as SpaceMetric, Neighbor};
+use std::borrow::Cow;
 
 #[derive(Clone, Copy)]
 pub struct CosineMetric;
 
-impl SpaceMetric<Vec<f32>> for CosineMetric {
+impl<T: Element> SpaceMetric<Vec<T>> for CosineMetric {
     type Unit = u32;
-    fn distance(&self, a: &Vec<f32>, b: &Vec<f32>) -> Self::Unit {
-        let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-        let na: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-        let nb: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
-        const EPSILON: f32 = 1e-6;
-        let cos = if na < EPSILON || nb < EPSILON {
-            0.0
-        } else {
-            dot / (na * nb)
-        };
-        let dist = 1.0 - cos;
-        dist.to_bits()
+    fn distance(&self, a: &Vec<T>, b: &Vec<T>) -> Self::Unit {
+        cosine(a, b).to_bits()
+    }
+}
+
+/// Cosine distance between vectors already scaled to unit length, which is
+/// how cosine collections store them and query with them. A zero vector has
+/// no direction and is at distance 1 from every vector, itself included.
+///
+/// Rounding can push the dot product of close vectors past 1; the distance
+/// is clamped at 0, as the bits of a negative one would sort after every
+/// other distance in the index.
+fn cosine<T: Element>(a: &[T], b: &[T]) -> f32 {
+    (1.0 - fold_widened(a, b, |x, y| Kernel::detect().dot(x, y), |s, t| s + t)).max(0.0)
+}
+
+/// Scales `vector` to unit length and returns its original norm. A zero
+/// vector is left as it is.
+pub fn normalize(vector: &mut [f32]) -> f32 {
+    let norm = Kernel::detect().dot(vector, vector).sqrt();
+    if norm > 0.0 {
+        for x in vector.iter_mut() {
+            *x /= norm;
+        }
     }
+    norm
 }
 
 #[derive(Clone, Copy)]
 pub struct EuclideanMetric;
 
-impl SpaceMetric<Vec<f32>> for EuclideanMetric {
+impl<T: Element> SpaceMetric<Vec<T>> for EuclideanMetric {
+    type Unit = u32;
+    fn distance(&self, a: &Vec<T>, b: &Vec<T>) -> Self::Unit {
+        euclidean(a, b).to_bits()
+    }
+}
+
+fn euclidean<T: Element>(a: &[T], b: &[T]) -> f32 {
+    let kernel = Kernel::detect();
+    fold_widened(a, b, |x, y| kernel.squared_euclidean(x, y), |s, t| s + t).sqrt()
+}
+
+#[derive(Clone, Copy)]
+pub struct ManhattanMetric;
+
+impl<T: Element> SpaceMetric<Vec<T>> for ManhattanMetric {
     type Unit = u32;
-    fn distance(&self, a: &Vec<f32>, b: &Vec<f32>) -> Self::Unit {
-        let sum: f32 = a.iter().zip(b.iter()).map(|(x, y)| (x - y).powi(2)).sum();
-        sum.sqrt().to_bits()
+    fn distance(&self, a: &Vec<T>, b: &Vec<T>) -> Self::Unit {
+        manhattan(a, b).to_bits()
     }
 }
 
+fn manhattan<T: Element>(a: &[T], b: &[T]) -> f32 {
+    fold_widened(a, b, |x, y| Kernel::detect().manhattan(x, y), |s, t| s + t)
+}
+
+#[derive(Clone, Copy)]
+pub struct ChebyshevMetric;
+
+impl<T: Element> SpaceMetric<Vec<T>> for ChebyshevMetric {
+    type Unit = u32;
+    fn distance(&self, a: &Vec<T>, b: &Vec<T>) -> Self::Unit {
+        chebyshev(a, b).to_bits()
+    }
+}
+
+fn chebyshev<T: Element>(a: &[T], b: &[T]) -> f32 {
+    let max_diff = |x: &[f32], y: &[f32]| {
+        x.iter().zip(y.iter()).map(|(x, y)| (x - y).abs()).fold(0.0, f32::max)
+    };
+    fold_widened(a, b, max_diff, f32::max)
+}
+
+/// Minkowski distance of order `p`.
+#[derive(Clone, Copy)]
+pub struct MinkowskiMetric(pub f32);
+
+impl<T: Element> SpaceMetric<Vec<T>> for MinkowskiMetric {
+    type Unit = u32;
+    fn distance(&self, a: &Vec<T>, b: &Vec<T>) -> Self::Unit {
+        minkowski(a, b, self.0).to_bits()
+    }
+}
+
+fn minkowski<T: Element>(a: &[T], b: &[T], p: f32) -> f32 {
+    let power_sum = |x: &[f32], y: &[f32]| -> f32 {
+        x.iter().zip(y.iter()).map(|(x, y)| (x - y).abs().powf(p)).sum()
+    };
+    fold_widened(a, b, power_sum, |s, t| s + t).powf(1.0 / p)
+}
+
+/// Number of differing bits between packed bit vectors.
+#[derive(Clone, Copy)]
+pub struct HammingMetric;
+
+impl SpaceMetric<Vec<u64>> for HammingMetric {
+    type Unit = u32;
+    fn distance(&self, a: &Vec<u64>, b: &Vec<u64>) -> Self::Unit {
+        (binary::hamming(a, b) as f32).to_bits()
+    }
+}
+
+/// Jaccard (Tanimoto) distance between packed bit vectors.
+#[derive(Clone, Copy)]
+pub struct JaccardMetric;
+
+impl SpaceMetric<Vec<u64>> for JaccardMetric {
+    type Unit = u32;
+    fn distance(&self, a: &Vec<u64>, b: &Vec<u64>) -> Self::Unit {
+        (1.0 - binary::jaccard(a, b)).to_bits()
+    }
+}
+
+/// Distance between two vectors under `metric`. Cosine expects unit vectors.
+pub fn distance<T: Element>(metric: Metric, a: &[T], b: &[T]) -> f32 {
+    match metric {
+        Metric::Cosine => cosine(a, b),
+        Metric::Euclidean => euclidean(a, b),
+        Metric::Manhattan => manhattan(a, b),
+        Metric::Chebyshev => chebyshev(a, b),
+        Metric::Minkowski(p) => minkowski(a, b, p.0),
+        Metric::Hamming | Metric::Jaccard => unreachable!("f32 vectors under a binary metric"),
+    }
+}
+
+/// HNSW graph, or inverted file, over vectors with components of type `T`.
+pub enum Dense<T: Element, const M: usize, const M0: usize> {
+    Cosine(Graph<CosineMetric, Vec<T>, M, M0>),
+    Euclidean(Graph<EuclideanMetric, Vec<T>, M, M0>),
+    Manhattan(Graph<ManhattanMetric, Vec<T>, M, M0>),
+    Chebyshev(Graph<ChebyshevMetric, Vec<T>, M, M0>),
+    Minkowski(Graph<MinkowskiMetric, Vec<T>, M, M0>),
+    Ivf(Ivf<T>),
+}
+
+impl<T: Element, const M: usize, const M0: usize> Dense<T, M, M0> {
+    fn new(metric: Metric, kind: IndexKind, ef_construction: usize) -> Self {
+        if let IndexKind::Ivf { nlist, nprobe } = kind {
+            return Dense::Ivf(Ivf::new(metric, nlist, nprobe));
+        }
+        match metric {
+            Metric::Cosine => Dense::Cosine(Graph::new(CosineMetric, ef_construction)),
+            Metric::Euclidean => Dense::Euclidean(Graph::new(EuclideanMetric, ef_construction)),
+            Metric::Manhattan => Dense::Manhattan(Graph::new(ManhattanMetric, ef_construction)),
+            Metric::Chebyshev => Dense::Chebyshev(Graph::new(ChebyshevMetric, ef_construction)),
+            Metric::Minkowski(p) => {
+                Dense::Minkowski(Graph::new(MinkowskiMetric(p.0), ef_construction))
+            }
+            Metric::Hamming | Metric::Jaccard => unreachable!("binary metric in f32 index"),
+        }
+    }
+
+    fn insert(&mut self, vector: Vec<T>, searcher: &mut Searcher) {
+        match self {
+            Dense::Cosine(h) => h.insert(vector, searcher),
+            Dense::Euclidean(h) => h.insert(vector, searcher),
+            Dense::Manhattan(h) => h.insert(vector, searcher),
+            Dense::Chebyshev(h) => h.insert(vector, searcher),
+            Dense::Minkowski(h) => h.insert(vector, searcher),
+            Dense::Ivf(f) => f.insert(vector),
+        };
+    }
+
+    fn remove(&mut self, i: usize) {
+        match self {
+            Dense::Cosine(h) => h.remove(i),
+            Dense::Euclidean(h) => h.remove(i),
+            Dense::Manhattan(h) => h.remove(i),
+            Dense::Chebyshev(h) => h.remove(i),
+            Dense::Minkowski(h) => h.remove(i),
+            Dense::Ivf(f) => f.remove(i),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Dense::Cosine(h) => h.len(),
+            Dense::Euclidean(h) => h.len(),
+            Dense::Manhattan(h) => h.len(),
+            Dense::Chebyshev(h) => h.len(),
+            Dense::Minkowski(h) => h.len(),
+            Dense::Ivf(f) => f.len(),
+        }
+    }
+
+    fn feature(&self, i: usize) -> &Vec<T> {
+        match self {
+            Dense::Cosine(h) => h.feature(i),
+            Dense::Euclidean(h) => h.feature(i),
+            Dense::Manhattan(h) => h.feature(i),
+            Dense::Chebyshev(h) => h.feature(i),
+            Dense::Minkowski(h) => h.feature(i),
+            Dense::Ivf(f) => f.feature(i),
+        }
+    }
+
+    fn nearest<'a>(
+        &self,
+        query: &Vec<T>,
+        ef: usize,
+        searcher: &mut Searcher,
+        neighbors: &'a mut [Neighbor<u32>],
+    ) -> &'a mut [Neighbor<u32>] {
+        match self {
+            Dense::Cosine(h) => h.nearest(query, ef, searcher, neighbors),
+            Dense::Euclidean(h) => h.nearest(query, ef, searcher, neighbors),
+            Dense::Manhattan(h) => h.nearest(query, ef, searcher, neighbors),
+            Dense::Chebyshev(h) => h.nearest(query, ef, searcher, neighbors),
+            Dense::Minkowski(h) => h.nearest(query, ef, searcher, neighbors),
+            Dense::Ivf(f) => f.nearest(query, neighbors),
+        }
+    }
+}
+
+/// Index of a collection. Binary metrics index packed `u64` words in a graph
+/// and are only reached through the `*_binary` methods; the other methods
+/// are for `f32` vectors, which the index may hold at a narrower element
+/// type.
 pub enum Index<const M: usize, const M0: usize> {
-    Cosine(Hnsw<CosineMetric, Vec<f32>, Pcg64, M, M0>),
-    Euclidean(Hnsw<EuclideanMetric, Vec<f32>, Pcg64, M, M0>),
+    F32(Dense<f32, M, M0>),
+    F16(Dense<f16, M, M0>),
+    Bf16(Dense<bf16, M, M0>),
+    Hamming(Graph<HammingMetric, Vec<u64>, M, M0>),
+    Jaccard(Graph<JaccardMetric, Vec<u64>, M, M0>),
 }
 
 impl<const M: usize, const M0: usize> Index<M, M0> {
-    pub fn new(metric: Metric) -> Self {
-        match metric {
-            Metric::Cosine => Index::Cosine(Hnsw::new(CosineMetric)),
-            Metric::Euclidean => Index::Euclidean(Hnsw::new(EuclideanMetric)),
+    pub fn new_params(
+        metric: Metric,
+        element_type: ElementType,
+        kind: IndexKind,
+        ef_construction: usize,
+    ) -> Self {
+        match (metric, element_type) {
+            (Metric::Hamming, _) => Index::Hamming(Graph::new(HammingMetric, ef_construction)),
+            (Metric::Jaccard, _) => Index::Jaccard(Graph::new(JaccardMetric, ef_construction)),
+            (_, ElementType::F32) => Index::F32(Dense::new(metric, kind, ef_construction)),
+            (_, ElementType::F16) => Index::F16(Dense::new(metric, kind, ef_construction)),
+            (_, ElementType::Bf16) => Index::Bf16(Dense::new(metric, kind, ef_construction)),
         }
     }
 
-    pub fn new_params(metric: Metric, ef_construction: usize) -> Self {
-        let params = hnsw::Params::new().ef_construction(ef_construction);
-        match metric {
-            Metric::Cosine => Index::Cosine(Hnsw::new_params(CosineMetric, params)),
-            Metric::Euclidean => Index::Euclidean(Hnsw::new_params(EuclideanMetric, params)),
+    /// The inverted file of an IVF collection.
+    pub fn ivf(&self) -> Option<&dyn InvertedFile> {
+        match self {
+            Index::F32(Dense::Ivf(f)) => Some(f),
+            Index::F16(Dense::Ivf(f)) => Some(f),
+            Index::Bf16(Dense::Ivf(f)) => Some(f),
+            _ => None,
         }
     }
 
-    pub fn insert(&mut self, vector: Vec<f32>, searcher: &mut hnsw::Searcher<u32>) {
+    pub fn ivf_mut(&mut self) -> Option<&mut dyn InvertedFile> {
         match self {
-            Index::Cosine(h) => h.insert(vector, searcher),
-            Index::Euclidean(h) => h.insert(vector, searcher),
-        };
+            Index::F32(Dense::Ivf(f)) => Some(f),
+            Index::F16(Dense::Ivf(f)) => Some(f),
+            Index::Bf16(Dense::Ivf(f)) => Some(f),
+            _ => None,
+        }
     }
 
-    pub fn feature(&self, i: usize) -> &Vec<f32> {
+    /// Inserts `vector`, rounded to the index's element type.
+    pub fn insert(&mut self, vector: Vec<f32>, searcher: &mut Searcher) {
         match self {
-            Index::Cosine(h) => h.feature(i),
-            Index::Euclidean(h) => h.feature(i),
+            Index::F32(d) => d.insert(vector, searcher),
+            Index::F16(d) => d.insert(element::narrow(&vector), searcher),
+            Index::Bf16(d) => d.insert(element::narrow(&vector), searcher),
+            Index::Hamming(_) | Index::Jaccard(_) => unreachable!("f32 vector in binary index"),
+        }
+    }
+
+    /// Removes node `i` from the graph. Its number is not reused.
+    pub fn remove(&mut self, i: usize) {
+        match self {
+            Index::F32(d) => d.remove(i),
+            Index::F16(d) => d.remove(i),
+            Index::Bf16(d) => d.remove(i),
+            Index::Hamming(h) => h.remove(i),
+            Index::Jaccard(h) => h.remove(i),
+        }
+    }
+
+    /// Number of nodes that have not been removed.
+    pub fn len(&self) -> usize {
+        match self {
+            Index::F32(d) => d.len(),
+            Index::F16(d) => d.len(),
+            Index::Bf16(d) => d.len(),
+            Index::Hamming(h) => h.len(),
+            Index::Jaccard(h) => h.len(),
+        }
+    }
+
+    /// The vector of node `i`, widened to `f32` if needed.
+    pub fn feature(&self, i: usize) -> Cow<'_, [f32]> {
+        match self {
+            Index::F32(d) => Cow::Borrowed(d.feature(i)),
+            Index::F16(d) => Cow::Owned(element::widen(d.feature(i))),
+            Index::Bf16(d) => Cow::Owned(element::widen(d.feature(i))),
+            Index::Hamming(_) | Index::Jaccard(_) => unreachable!("f32 vector in binary index"),
         }
     }
 
     pub fn nearest<'a>(
         &self,
-        query: &Vec<f32>,
+        query: &[f32],
+        ef: usize,
+        searcher: &mut Searcher,
+        neighbors: &'a mut [Neighbor<u32>],
+    ) -> &'a mut [Neighbor<u32>] {
+        match self {
+            Index::F32(d) => d.nearest(&query.to_vec(), ef, searcher, neighbors),
+            Index::F16(d) => d.nearest(&element::narrow(query), ef, searcher, neighbors),
+            Index::Bf16(d) => d.nearest(&element::narrow(query), ef, searcher, neighbors),
+            Index::Hamming(_) | Index::Jaccard(_) => unreachable!("f32 vector in binary index"),
+        }
+    }
+
+    pub fn insert_binary(&mut self, words: Vec<u64>, searcher: &mut Searcher) {
+        match self {
+            Index::Hamming(h) => h.insert(words, searcher),
+            Index::Jaccard(h) => h.insert(words, searcher),
+            _ => unreachable!("binary vector in f32 index"),
+        };
+    }
+
+    pub fn binary_feature(&self, i: usize) -> &Vec<u64> {
+        match self {
+            Index::Hamming(h) => h.feature(i),
+            Index::Jaccard(h) => h.feature(i),
+            _ => unreachable!("binary vector in f32 index"),
+        }
+    }
+
+    pub fn nearest_binary<'a>(
+        &self,
+        query: &Vec<u64>,
         ef: usize,
-        searcher: &mut hnsw::Searcher<u32>,
+        searcher: &mut Searcher,
         neighbors: &'a mut [Neighbor<u32>],
     ) -> &'a mut [Neighbor<u32>] {
         match self {
-            Index::Cosine(h) => h.nearest(query, ef, searcher, neighbors),
-            Index::Euclidean(h) => h.nearest(query, ef, searcher, neighbors),
+            Index::Hamming(h) => h.nearest(query, ef, searcher, neighbors),
+            Index::Jaccard(h) => h.nearest(query, ef, searcher, neighbors),
+            _ => unreachable!("binary vector in f32 index"),
         }
     }
 }
@@ -1,6 +1,8 @@
+use crate::text::TextField;
+use crate::types::{IdKind, Metric};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct Params {
     pub ef_construction: usize,
     pub ef_search: usize,
@@ -14,3 +16,184 @@ impl Default for Params {
         }
     }
 }
+
+/// Properties fixed when a collection is created.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(from = "Vec<Setting>", into = "Vec<Setting>")]
+pub struct CollectionOptions {
+    pub id_kind: IdKind,
+    /// Metadata fields covered by the keyword index. Empty disables it.
+    pub text_fields: Vec<TextField>,
+    pub quantization: Quantization,
+    /// Cosine collections index their vectors scaled to unit length. By
+    /// default the original norms are kept as well and reads return the
+    /// vectors as they were added; when cleared, reads return unit vectors
+    /// and no norms are held. Other metrics ignore it.
+    pub keep_magnitude: bool,
+    pub element_type: ElementType,
+    pub index: IndexKind,
+    /// Links per node of the graph, the `M` of [`crate::VectorDB`], the
+    /// collection has to be opened with. Loading it with another `M` fails.
+    /// `None` leaves the choice to whoever opens the file.
+    pub m: Option<usize>,
+}
+
+impl Default for CollectionOptions {
+    fn default() -> Self {
+        Self {
+            id_kind: IdKind::default(),
+            text_fields: Vec::new(),
+            quantization: Quantization::default(),
+            keep_magnitude: true,
+            element_type: ElementType::default(),
+            index: IndexKind::default(),
+            m: None,
+        }
+    }
+}
+
+impl CollectionOptions {
+    /// Resets the settings `metric` ignores to their defaults, so that
+    /// options compare equal whatever those were set to.
+    pub(crate) fn fit(&mut self, metric: Metric) {
+        if metric != Metric::Cosine {
+            self.keep_magnitude = true;
+        }
+    }
+}
+
+/// Structure of the index over the vectors of an `f32` collection.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub enum IndexKind {
+    /// Hierarchical navigable small world graph. High recall at low latency,
+    /// but every vector carries its links and inserts search the graph.
+    #[default]
+    Hnsw,
+    /// Inverted file: vectors are grouped into `nlist` lists around the
+    /// centroids of a k-means quantizer, and searches scan the `nprobe` lists
+    /// nearest to the query. Takes no memory beyond the vectors and inserts
+    /// quickly, at some cost in recall. The quantizer is trained once the
+    /// collection holds [`crate::TRAINING_POINTS_PER_LIST`] vectors per list;
+    /// until then searches scan every vector.
+    Ivf { nlist: usize, nprobe: usize },
+}
+
+impl std::str::FromStr for IndexKind {
+    type Err = anyhow::Error;
+
+    /// Parses `hnsw` or `ivf:<nlist>:<nprobe>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_ascii_lowercase();
+        if lower == "hnsw" {
+            return Ok(IndexKind::Hnsw);
+        }
+        let sizes = lower.strip_prefix("ivf:").and_then(|rest| rest.split_once(':'));
+        match sizes {
+            Some((nlist, nprobe)) => Ok(IndexKind::Ivf {
+                nlist: nlist.parse()?,
+                nprobe: nprobe.parse()?,
+            }),
+            None => Err(anyhow::anyhow!("unknown index: {s}")),
+        }
+    }
+}
+
+/// Component type of the vectors an `f32` collection stores, both in the file
+/// and in the index. Vectors are converted when added and widened back to
+/// `f32` to compute distances.
+#[repr(u8)]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub enum ElementType {
+    #[default]
+    F32 = 0,
+    /// IEEE half precision: 11 significant bits, values up to 65504.
+    F16 = 1,
+    /// bfloat16: the range of `f32` with 8 significant bits.
+    Bf16 = 2,
+}
+
+impl std::str::FromStr for ElementType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "f32" => Ok(ElementType::F32),
+            "f16" => Ok(ElementType::F16),
+            "bf16" => Ok(ElementType::Bf16),
+            _ => Err(anyhow::anyhow!("unknown element type: {s}")),
+        }
+    }
+}
+
+/// How the index stores vectors of an `f32` collection.
+#[repr(u8)]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub enum Quantization {
+    /// The index holds the vectors themselves.
+    #[default]
+    None = 0,
+    /// The index holds one sign bit per dimension and searches by Hamming
+    /// distance. Candidates are rescored with the full vectors read back from
+    /// the file, so memory use drops 32-fold at some cost in latency.
+    Binary = 1,
+}
+
+impl std::str::FromStr for Quantization {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Quantization::None),
+            "binary" => Ok(Quantization::Binary),
+            _ => Err(anyhow::anyhow!("unknown quantization: {s}")),
+        }
+    }
+}
+
+/// Persisted form of a single collection option. Options are stored as a
+/// list so that new ones can be added as variants without changing the
+/// header layout; options missing from a file keep their defaults.
+#[derive(Serialize, Deserialize)]
+enum Setting {
+    IdKind(IdKind),
+    TextFields(Vec<TextField>),
+    Quantization(Quantization),
+    KeepMagnitude(bool),
+    ElementType(ElementType),
+    Index(IndexKind),
+    M(usize),
+}
+
+impl From<Vec<Setting>> for CollectionOptions {
+    fn from(settings: Vec<Setting>) -> Self {
+        // settings missing from the list predate it
+        let mut options = Self::default();
+        for setting in settings {
+            match setting {
+                Setting::IdKind(kind) => options.id_kind = kind,
+                Setting::TextFields(fields) => options.text_fields = fields,
+                Setting::Quantization(q) => options.quantization = q,
+                Setting::KeepMagnitude(keep) => options.keep_magnitude = keep,
+                Setting::ElementType(t) => options.element_type = t,
+                Setting::Index(kind) => options.index = kind,
+                Setting::M(m) => options.m = Some(m),
+            }
+        }
+        options
+    }
+}
+
+impl From<CollectionOptions> for Vec<Setting> {
+    fn from(options: CollectionOptions) -> Self {
+        let mut settings = vec![
+            Setting::IdKind(options.id_kind),
+            Setting::TextFields(options.text_fields),
+            Setting::Quantization(options.quantization),
+            Setting::KeepMagnitude(options.keep_magnitude),
+            Setting::ElementType(options.element_type),
+            Setting::Index(options.index),
+        ];
+        settings.extend(options.m.map(Setting::M));
+        settings
+    }
+}
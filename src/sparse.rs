@@ -0,0 +1,125 @@
+use anyhow::{Result, anyhow};
+use ordered_float::NotNan;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Sparse vector given as dimension indices and their values, such as
+/// SPLADE term weights. Indices are kept sorted and unique.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct SparseVector {
+    indices: Vec<u32>,
+    values: Vec<f32>,
+}
+
+impl SparseVector {
+    pub fn new(indices: Vec<u32>, values: Vec<f32>) -> Result<Self> {
+        if indices.len() != values.len() {
+            return Err(anyhow!("indices and values differ in length"));
+        }
+        if values.iter().any(|v| !v.is_finite()) {
+            return Err(anyhow!("sparse values must be finite"));
+        }
+        let mut pairs: Vec<(u32, f32)> = indices.into_iter().zip(values).collect();
+        pairs.sort_by_key(|p| p.0);
+        if pairs.windows(2).any(|w| w[0].0 == w[1].0) {
+            return Err(anyhow!("duplicate sparse index"));
+        }
+        Ok(Self::from_sorted(pairs))
+    }
+
+    fn from_sorted(pairs: Vec<(u32, f32)>) -> Self {
+        let (indices, values) = pairs.into_iter().unzip();
+        Self { indices, values }
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, f32)> + '_ {
+        self.indices.iter().copied().zip(self.values.iter().copied())
+    }
+
+    pub fn dot(&self, other: &SparseVector) -> f32 {
+        let (mut i, mut j, mut sum) = (0, 0, 0.0);
+        while i < self.indices.len() && j < other.indices.len() {
+            match self.indices[i].cmp(&other.indices[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    sum += self.values[i] * other.values[j];
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        sum
+    }
+}
+
+/// Inverted index from sparse dimensions to the entries that use them.
+/// Entries are addressed by their position.
+#[derive(Default)]
+pub struct SparseIndex {
+    postings: HashMap<u32, Vec<(usize, f32)>>,
+    vectors: Vec<Option<SparseVector>>,
+}
+
+impl SparseIndex {
+    pub fn insert(&mut self, pos: usize, vector: SparseVector) {
+        for (index, value) in vector.iter() {
+            self.postings.entry(index).or_default().push((pos, value));
+        }
+        if self.vectors.len() <= pos {
+            self.vectors.resize(pos + 1, None);
+        }
+        self.vectors[pos] = Some(vector);
+    }
+
+    /// Forgets the vector at `pos`. Its postings are skipped from then on.
+    pub fn remove(&mut self, pos: usize) {
+        if let Some(v) = self.vectors.get_mut(pos) {
+            *v = None;
+        }
+    }
+
+    pub fn get(&self, pos: usize) -> Option<&SparseVector> {
+        self.vectors.get(pos).and_then(Option::as_ref)
+    }
+
+    /// Returns up to `k` entries accepted by `filter` with the largest dot
+    /// product with `query`. Entries sharing no dimension with it are not
+    /// returned.
+    pub fn search<F>(&self, query: &SparseVector, k: usize, filter: F) -> Vec<(usize, f32)>
+    where
+        F: Fn(usize) -> bool,
+    {
+        let mut scores: HashMap<usize, f32> = HashMap::new();
+        for (index, weight) in query.iter() {
+            let Some(postings) = self.postings.get(&index) else {
+                continue;
+            };
+            for &(pos, value) in postings {
+                if self.vectors[pos].is_some() && filter(pos) {
+                    *scores.entry(pos).or_insert(0.0) += weight * value;
+                }
+            }
+        }
+        let mut results: Vec<(usize, f32)> = scores.into_iter().collect();
+        results.sort_by_key(|&(pos, score)| (std::cmp::Reverse(NotNan::new(score).unwrap()), pos));
+        results.truncate(k);
+        results
+    }
+}
@@ -1,41 +1,197 @@
-use crate::types::{Metadata, Metric};
+mod legacy;
+
+use crate::binary::BinaryVector;
+use crate::element::{self, Element};
+use crate::params::{CollectionOptions, ElementType, Params};
+use crate::sparse::SparseVector;
+use crate::types::{Id, Metadata, Metric};
+use legacy::{EntryV1, HeaderV1};
 use anyhow::{Result, anyhow};
+use half::{bf16, f16};
 use serde::{Deserialize, Serialize};
-use std::fs::{File, OpenOptions};
-use std::io::{BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
 pub const MAGIC: [u8; 4] = *b"VDB0";
-pub const VERSION: u8 = 1;
+pub const VERSION: u8 = 2;
 
 #[derive(Serialize, Deserialize)]
-pub struct Header {
+pub struct FileHeader {
     pub magic: [u8; 4],
     pub version: u8,
+}
+
+/// Descriptor of a single collection. A newer record with the same `id`
+/// replaces the previous one.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Header {
+    pub id: u32,
+    pub name: String,
     pub metric: Metric,
     pub dim: u32,
+    pub params: Params,
+    pub options: CollectionOptions,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct StoredEntry {
-    pub id: usize,
-    pub vector: Vec<f32>,
+    pub id: Id,
+    /// One vector for plain entries, several for multi-vector entries and
+    /// none for tombstones and binary entries.
+    pub vectors: Vectors,
     pub metadata: Metadata,
+    pub namespace: Option<String>,
+    pub sparse: Option<SparseVector>,
+    /// The vector of an entry in a binary collection.
+    pub binary: Option<BinaryVector>,
     pub deleted: bool,
+    /// List of each vector in a trained IVF collection, as assigned when the
+    /// entry was added. A later training moves the vectors, and its
+    /// [`StoredIvf`] record takes precedence. Empty otherwise.
+    pub lists: Vec<u32>,
+}
+
+impl StoredEntry {
+    pub fn new(id: Id, vector: Vec<f32>, metadata: Metadata) -> Self {
+        Self::multi(id, vec![vector], metadata)
+    }
+
+    pub fn multi(id: Id, vectors: Vec<Vec<f32>>, metadata: Metadata) -> Self {
+        Self {
+            id,
+            vectors: Vectors::F32(vectors),
+            metadata,
+            namespace: None,
+            sparse: None,
+            binary: None,
+            deleted: false,
+            lists: Vec::new(),
+        }
+    }
+
+    pub fn binary(id: Id, vector: BinaryVector, metadata: Metadata) -> Self {
+        Self {
+            binary: Some(vector),
+            ..Self::multi(id, Vec::new(), metadata)
+        }
+    }
+
+    pub fn tombstone(id: Id) -> Self {
+        Self {
+            deleted: true,
+            ..Self::multi(id, Vec::new(), Metadata::default())
+        }
+    }
+}
+
+/// Vectors of an entry, in the element type of its collection.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Vectors {
+    F32(Vec<Vec<f32>>),
+    F16(Vec<Vec<f16>>),
+    Bf16(Vec<Vec<bf16>>),
 }
 
+impl Vectors {
+    /// Number of vectors.
+    pub fn len(&self) -> usize {
+        match self {
+            Vectors::F32(v) => v.len(),
+            Vectors::F16(v) => v.len(),
+            Vectors::Bf16(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Dimension shared by all vectors.
+    pub fn dim(&self) -> Result<usize> {
+        fn shared<T>(vectors: &[Vec<T>]) -> Result<usize> {
+            let dim = vectors.first().ok_or(anyhow!("no vectors"))?.len();
+            if vectors.iter().any(|v| v.len() != dim) {
+                return Err(anyhow!("dimension mismatch"));
+            }
+            Ok(dim)
+        }
+        match self {
+            Vectors::F32(v) => shared(v),
+            Vectors::F16(v) => shared(v),
+            Vectors::Bf16(v) => shared(v),
+        }
+    }
+
+    /// Converts the vectors to `element_type`, rounding if it is narrower.
+    pub fn convert(self, element_type: ElementType) -> Self {
+        fn to<T: Element>(vectors: Vec<Vec<f32>>) -> Vec<Vec<T>> {
+            vectors.iter().map(|v| element::narrow(v)).collect()
+        }
+        match element_type {
+            ElementType::F32 => Vectors::F32(self.into_f32()),
+            ElementType::F16 => Vectors::F16(to(self.into_f32())),
+            ElementType::Bf16 => Vectors::Bf16(to(self.into_f32())),
+        }
+    }
+
+    pub fn into_f32(self) -> Vec<Vec<f32>> {
+        fn widen<T: Element>(vectors: Vec<Vec<T>>) -> Vec<Vec<f32>> {
+            vectors.iter().map(|v| element::widen(v)).collect()
+        }
+        match self {
+            Vectors::F32(v) => v,
+            Vectors::F16(v) => widen(v),
+            Vectors::Bf16(v) => widen(v),
+        }
+    }
+}
+
+/// Trained quantizer of an IVF collection, with the list of every vector
+/// the collection held at the time.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredIvf {
+    pub centroids: Vec<Vec<f32>>,
+    /// Lists of the vectors of each live entry.
+    pub assignments: Vec<(Id, Vec<u32>)>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum Record<H = Header, E = StoredEntry, I = StoredIvf> {
+    Collection(H),
+    Entry { collection: u32, entry: E },
+    Drop { collection: u32 },
+    Ivf { collection: u32, ivf: I },
+}
+
+pub struct StoredCollection {
+    pub header: Header,
+    pub entries: Vec<StoredEntry>,
+    /// File offset of each entry's record, for [`Storage::read_entries`].
+    pub offsets: Vec<u64>,
+    /// Latest quantizer of an IVF collection, and the number of entries
+    /// written before it.
+    pub ivf: Option<(usize, StoredIvf)>,
+}
+
+/// Everything read back from a file: live collections in creation order and
+/// the next unused collection id.
+pub struct Catalog {
+    pub collections: Vec<StoredCollection>,
+    pub next_id: u32,
+}
+
+#[derive(Clone)]
 pub struct Storage {
     path: PathBuf,
 }
 
 impl Storage {
-    pub fn create<P: AsRef<Path>>(path: P, metric: Metric) -> Result<Self> {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref().to_path_buf();
-        let header = Header {
+        let header = FileHeader {
             magic: MAGIC,
             version: VERSION,
-            metric,
-            dim: 0,
         };
         let file = OpenOptions::new()
             .write(true)
@@ -48,62 +204,287 @@ impl Storage {
         Ok(Self { path })
     }
 
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<(Self, Header, Vec<StoredEntry>)> {
-        let path = path.as_ref().to_path_buf();
-        let file = File::open(&path)?;
-        let mut reader = BufReader::new(file);
-        let header: Header = bincode::deserialize_from(&mut reader)?;
+    pub fn open_or_create<P: AsRef<Path>>(path: P) -> Result<(Self, Catalog)> {
+        if path.as_ref().exists() {
+            Self::open(path)
+        } else {
+            let storage = Self::create(path)?;
+            let catalog = Catalog {
+                collections: Vec::new(),
+                next_id: 0,
+            };
+            Ok((storage, catalog))
+        }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<(Self, Catalog)> {
+        let storage = Self {
+            path: path.as_ref().to_path_buf(),
+        };
+        let (version, catalog) = Self::read_catalog(&storage.path)?;
+        if version == VERSION {
+            return Ok((storage, catalog));
+        }
+        // Older layouts are converted and written back, then read again so
+        // that entry offsets refer to the new file.
+        storage.rewrite(&catalog.collections)?;
+        Self::open(&storage.path)
+    }
+
+    /// Reads the catalog of the file at `path` without writing to it. Files
+    /// of an older layout are converted in memory only, so the offsets of
+    /// their entries do not point into the file.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Catalog> {
+        Ok(Self::read_catalog(path.as_ref())?.1)
+    }
+
+    /// Reads the file at `path`, returning its layout version and catalog.
+    fn read_catalog(path: &Path) -> Result<(u8, Catalog)> {
+        let file = File::open(path)?;
+        let mut reader = Counting::new(BufReader::new(file));
+        let header: FileHeader = bincode::deserialize_from(&mut reader)?;
         if header.magic != MAGIC {
             return Err(anyhow!("invalid magic"));
         }
-        if header.version != VERSION {
-            return Err(anyhow!("unsupported version"));
+        let mut catalog = match header.version {
+            1 => Self::read_v1(&mut reader)?,
+            VERSION => Self::read_records(&mut reader)?,
+            _ => return Err(anyhow!("unsupported version")),
+        };
+        for c in &mut catalog.collections {
+            c.header.options.fit(c.header.metric);
         }
-        let mut entries = Vec::new();
+        Ok((header.version, catalog))
+    }
+
+    fn read_records<R: Read>(reader: &mut Counting<R>) -> Result<Catalog> {
+        let mut collections: Vec<StoredCollection> = Vec::new();
+        let mut next_id = 0;
         loop {
-            match bincode::deserialize_from::<_, StoredEntry>(&mut reader) {
-                Ok(e) => entries.push(e),
-                Err(e) => {
-                    if let bincode::ErrorKind::Io(ref io_err) = *e {
-                        if io_err.kind() == std::io::ErrorKind::UnexpectedEof {
-                            break;
-                        }
+            let offset = reader.pos;
+            let Some(record) = read_next::<_, Record>(&mut *reader)? else {
+                break;
+            };
+            match record {
+                Record::Collection(header) => {
+                    next_id = next_id.max(header.id + 1);
+                    match collections.iter_mut().find(|c| c.header.id == header.id) {
+                        Some(c) => c.header = header,
+                        None => collections.push(StoredCollection {
+                            header,
+                            entries: Vec::new(),
+                            offsets: Vec::new(),
+                            ivf: None,
+                        }),
+                    }
+                }
+                Record::Entry { collection, entry } => {
+                    if let Some(c) = collections.iter_mut().find(|c| c.header.id == collection) {
+                        c.entries.push(entry);
+                        c.offsets.push(offset);
+                    }
+                }
+                Record::Drop { collection } => {
+                    collections.retain(|c| c.header.id != collection);
+                }
+                Record::Ivf { collection, ivf } => {
+                    if let Some(c) = collections.iter_mut().find(|c| c.header.id == collection) {
+                        c.ivf = Some((c.entries.len(), ivf));
                     }
-                    return Err(e.into());
                 }
             }
         }
-        Ok((Self { path }, header, entries))
+        Ok(Catalog {
+            collections,
+            next_id,
+        })
+    }
+
+    fn read_v1<R: Read>(reader: &mut Counting<R>) -> Result<Catalog> {
+        let legacy: HeaderV1 = bincode::deserialize_from(&mut *reader)?;
+        let (mut entries, mut offsets) = (Vec::new(), Vec::new());
+        loop {
+            let offset = reader.pos;
+            let Some(e) = read_next::<_, EntryV1>(&mut *reader)? else {
+                break;
+            };
+            entries.push(e.into());
+            offsets.push(offset);
+        }
+        let header = Header {
+            id: 0,
+            name: crate::DEFAULT_COLLECTION.to_string(),
+            metric: legacy.metric,
+            dim: legacy.dim,
+            params: Params::default(),
+            options: CollectionOptions::default(),
+        };
+        Ok(Catalog {
+            collections: vec![StoredCollection {
+                header,
+                entries,
+                offsets,
+                ivf: None,
+            }],
+            next_id: 1,
+        })
     }
 
-    pub fn append_entry(&self, entry: &StoredEntry) -> Result<()> {
+    /// Appends `record`, returning the offset it was written at.
+    fn append<H, E, I>(&self, record: &Record<H, E, I>) -> Result<u64>
+    where
+        H: Serialize,
+        E: Serialize,
+        I: Serialize,
+    {
         let file = OpenOptions::new().append(true).open(&self.path)?;
+        let offset = file.metadata()?.len();
         let mut writer = BufWriter::new(file);
-        bincode::serialize_into(&mut writer, entry)?;
+        bincode::serialize_into(&mut writer, record)?;
         writer.flush()?;
-        Ok(())
+        Ok(offset)
     }
 
-    pub fn update_header(&self, header: &Header) -> Result<()> {
-        let file = OpenOptions::new().write(true).open(&self.path)?;
+    /// Appends an entry, returning the offset of its record.
+    pub fn append_entry(&self, collection: u32, entry: &StoredEntry) -> Result<u64> {
+        self.append(&Record::<&Header, _>::Entry { collection, entry })
+    }
+
+    /// Reads back the entries whose records start at `offsets`.
+    pub fn read_entries(&self, offsets: &[u64]) -> Result<Vec<StoredEntry>> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        offsets
+            .iter()
+            .map(|&offset| {
+                reader.seek(SeekFrom::Start(offset))?;
+                match bincode::deserialize_from(&mut reader)? {
+                    Record::<Header, StoredEntry>::Entry { entry, .. } => Ok(entry),
+                    _ => Err(anyhow!("no entry at offset {offset}")),
+                }
+            })
+            .collect()
+    }
+
+    /// Appends several entries with a single write, returning the offset
+    /// of each.
+    pub fn append_entries(&self, collection: u32, entries: &[StoredEntry]) -> Result<Vec<u64>> {
+        let file = OpenOptions::new().append(true).open(&self.path)?;
+        let mut offset = file.metadata()?.len();
         let mut writer = BufWriter::new(file);
-        writer.seek(SeekFrom::Start(0))?;
-        bincode::serialize_into(&mut writer, header)?;
+        let mut offsets = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let record = Record::<&Header, _>::Entry { collection, entry };
+            offsets.push(offset);
+            offset += bincode::serialized_size(&record)?;
+            bincode::serialize_into(&mut writer, &record)?;
+        }
         writer.flush()?;
+        Ok(offsets)
+    }
+
+    pub fn update_header(&self, header: &Header) -> Result<()> {
+        self.append(&Record::<_, &StoredEntry>::Collection(header))?;
         Ok(())
     }
 
-    pub fn rewrite(&self, header: &Header, entries: &[StoredEntry]) -> Result<()> {
-        let file = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&self.path)?;
-        let mut writer = BufWriter::new(file);
-        bincode::serialize_into(&mut writer, header)?;
-        for e in entries {
-            bincode::serialize_into(&mut writer, e)?;
+    pub fn drop_collection(&self, collection: u32) -> Result<()> {
+        self.append(&Record::<&Header, &StoredEntry>::Drop { collection })?;
+        Ok(())
+    }
+
+    pub fn append_ivf(&self, collection: u32, ivf: &StoredIvf) -> Result<()> {
+        self.append(&Record::<&Header, &StoredEntry, _>::Ivf { collection, ivf })?;
+        Ok(())
+    }
+
+    /// Replaces the file with one holding just `collections`. The new file is
+    /// written and synced beside the old one, then renamed over it, so a
+    /// failure on the way leaves the old file in place.
+    pub fn rewrite(&self, collections: &[StoredCollection]) -> Result<()> {
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+        let result = Self::write_file(&temp, collections)
+            .and_then(|()| Ok(fs::rename(&temp, &self.path)?));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
         }
-        writer.flush()?;
+        result
+    }
+
+    fn write_file(path: &Path, collections: &[StoredCollection]) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let header = FileHeader {
+            magic: MAGIC,
+            version: VERSION,
+        };
+        bincode::serialize_into(&mut writer, &header)?;
+        for c in collections {
+            let record = Record::<_, &StoredEntry>::Collection(&c.header);
+            bincode::serialize_into(&mut writer, &record)?;
+            // the quantizer goes back between the same entries
+            let at = c.ivf.as_ref().map_or(c.entries.len(), |(at, _)| *at);
+            for (i, entry) in c.entries.iter().enumerate() {
+                if i == at {
+                    Self::write_ivf(&mut writer, c)?;
+                }
+                let record = Record::<&Header, _>::Entry {
+                    collection: c.header.id,
+                    entry,
+                };
+                bincode::serialize_into(&mut writer, &record)?;
+            }
+            if at == c.entries.len() {
+                Self::write_ivf(&mut writer, c)?;
+            }
+        }
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
         Ok(())
     }
+
+    fn write_ivf(writer: &mut impl Write, c: &StoredCollection) -> Result<()> {
+        if let Some((_, ivf)) = &c.ivf {
+            let record = Record::<&Header, &StoredEntry, _>::Ivf {
+                collection: c.header.id,
+                ivf,
+            };
+            bincode::serialize_into(writer, &record)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reader that keeps track of how far it has read.
+struct Counting<R> {
+    inner: R,
+    pos: u64,
+}
+
+impl<R> Counting<R> {
+    fn new(inner: R) -> Self {
+        Self { inner, pos: 0 }
+    }
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+/// Reads the next value, returning `None` at a clean end of file.
+fn read_next<R: Read, T: for<'de> Deserialize<'de>>(reader: &mut R) -> Result<Option<T>> {
+    match bincode::deserialize_from::<_, T>(reader) {
+        Ok(v) => Ok(Some(v)),
+        Err(e) => {
+            if let bincode::ErrorKind::Io(ref io_err) = *e {
+                if io_err.kind() == std::io::ErrorKind::UnexpectedEof {
+                    return Ok(None);
+                }
+            }
+            Err(e.into())
+        }
+    }
 }
@@ -0,0 +1,39 @@
+//! Record layout of version 1 files, which held a single unnamed collection.
+//! They are only read, converted to the current layout and written back.
+
+use super::{StoredEntry, Vectors};
+use crate::types::{Id, Metadata, Metric};
+use serde::Deserialize;
+
+/// Rest of the file header of version 1.
+#[derive(Deserialize)]
+pub struct HeaderV1 {
+    pub metric: Metric,
+    pub dim: u32,
+}
+
+/// Entry of version 1.
+#[derive(Deserialize)]
+pub struct EntryV1 {
+    id: usize,
+    vector: Vec<f32>,
+    metadata: Metadata,
+    deleted: bool,
+}
+
+impl From<EntryV1> for StoredEntry {
+    fn from(e: EntryV1) -> Self {
+        // tombstones carry no vector
+        let vectors = if e.deleted { Vec::new() } else { vec![e.vector] };
+        Self {
+            id: Id::Int(e.id),
+            vectors: Vectors::F32(vectors),
+            metadata: e.metadata,
+            namespace: None,
+            sparse: None,
+            binary: None,
+            deleted: e.deleted,
+            lists: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use ordered_float::NotNan;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::types::Metadata;
+
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+/// Metadata field covered by the keyword index.
+#[repr(u8)]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum TextField {
+    Label = 1,
+    Description = 2,
+}
+
+impl std::str::FromStr for TextField {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "label" => Ok(TextField::Label),
+            "description" => Ok(TextField::Description),
+            _ => Err(anyhow::anyhow!("unknown text field: {s}")),
+        }
+    }
+}
+
+/// Splits text into lowercase alphanumeric words.
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+}
+
+/// Inverted index over the text fields of a collection, scored with BM25.
+/// Documents are addressed by their entry position.
+pub struct TextIndex {
+    fields: Vec<TextField>,
+    postings: HashMap<String, Vec<(usize, u32)>>,
+    /// Token count per document, `None` once removed.
+    docs: Vec<Option<u32>>,
+    live: usize,
+    total_len: u64,
+}
+
+impl TextIndex {
+    pub fn new(fields: Vec<TextField>) -> Self {
+        Self {
+            fields,
+            postings: HashMap::new(),
+            docs: Vec::new(),
+            live: 0,
+            total_len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, pos: usize, metadata: &Metadata) {
+        let mut freqs: HashMap<String, u32> = HashMap::new();
+        let mut len = 0;
+        for field in &self.fields {
+            let text = match field {
+                TextField::Label => Some(metadata.label.as_str()),
+                TextField::Description => metadata.description.as_deref(),
+            };
+            for token in text.into_iter().flat_map(tokenize) {
+                *freqs.entry(token).or_insert(0) += 1;
+                len += 1;
+            }
+        }
+        for (token, tf) in freqs {
+            self.postings.entry(token).or_default().push((pos, tf));
+        }
+        if self.docs.len() <= pos {
+            self.docs.resize(pos + 1, None);
+        }
+        self.docs[pos] = Some(len);
+        self.live += 1;
+        self.total_len += len as u64;
+    }
+
+    pub fn remove(&mut self, pos: usize) {
+        if let Some(len) = self.docs.get_mut(pos).and_then(Option::take) {
+            self.live -= 1;
+            self.total_len -= len as u64;
+        }
+    }
+
+    /// Returns up to `k` documents accepted by `filter`, best BM25 score first.
+    pub fn search<F>(&self, query: &str, k: usize, filter: F) -> Vec<(usize, f32)>
+    where
+        F: Fn(usize) -> bool,
+    {
+        if self.live == 0 {
+            return Vec::new();
+        }
+        let n = self.live as f32;
+        let avg_len = self.total_len as f32 / n;
+        let mut scores: HashMap<usize, f32> = HashMap::new();
+        let mut terms: Vec<String> = tokenize(query).collect();
+        terms.sort();
+        terms.dedup();
+        for term in terms {
+            let Some(postings) = self.postings.get(&term) else {
+                continue;
+            };
+            let live: Vec<(usize, u32, u32)> = postings
+                .iter()
+                .filter_map(|&(pos, tf)| self.docs[pos].map(|len| (pos, tf, len)))
+                .collect();
+            let df = live.len() as f32;
+            let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+            for (pos, tf, len) in live {
+                if !filter(pos) {
+                    continue;
+                }
+                let tf = tf as f32;
+                let norm = tf + K1 * (1.0 - B + B * len as f32 / avg_len.max(f32::EPSILON));
+                *scores.entry(pos).or_insert(0.0) += idf * tf * (K1 + 1.0) / norm;
+            }
+        }
+        let mut results: Vec<(usize, f32)> = scores.into_iter().collect();
+        results.sort_by_key(|&(pos, score)| (std::cmp::Reverse(NotNan::new(score).unwrap()), pos));
+        results.truncate(k);
+        results
+    }
+}
@@ -1,24 +1,214 @@
+use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
 pub struct Metadata {
     pub label: String,
     pub description: Option<String>,
 }
 
-impl Default for Metadata {
-    fn default() -> Self {
-        Self { label: String::new(), description: None }
+/// External identifier of an entry. All ids of a collection have the
+/// [`IdKind`] the collection was created with.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Id {
+    Int(usize),
+    Str(String),
+    Uuid(u128),
+}
+
+impl Id {
+    pub fn kind(&self) -> IdKind {
+        match self {
+            Id::Int(_) => IdKind::Int,
+            Id::Str(_) => IdKind::Str,
+            Id::Uuid(_) => IdKind::Uuid,
+        }
+    }
+
+    /// Parses `s` as an id of the given kind. UUIDs are accepted with or
+    /// without hyphens.
+    pub fn parse(s: &str, kind: IdKind) -> anyhow::Result<Self> {
+        match kind {
+            IdKind::Int => Ok(Id::Int(s.parse()?)),
+            IdKind::Str => Ok(Id::Str(s.to_string())),
+            IdKind::Uuid => {
+                let hex: String = s.chars().filter(|c| *c != '-').collect();
+                if hex.len() != 32 {
+                    return Err(anyhow::anyhow!("invalid uuid: {s}"));
+                }
+                Ok(Id::Uuid(u128::from_str_radix(&hex, 16)?))
+            }
+        }
+    }
+}
+
+impl From<usize> for Id {
+    fn from(id: usize) -> Self {
+        Id::Int(id)
+    }
+}
+
+impl From<&str> for Id {
+    fn from(id: &str) -> Self {
+        Id::Str(id.to_string())
+    }
+}
+
+impl From<String> for Id {
+    fn from(id: String) -> Self {
+        Id::Str(id)
+    }
+}
+
+impl From<&Id> for Id {
+    fn from(id: &Id) -> Self {
+        id.clone()
+    }
+}
+
+impl PartialEq<usize> for Id {
+    fn eq(&self, other: &usize) -> bool {
+        matches!(self, Id::Int(id) if id == other)
+    }
+}
+
+impl PartialEq<&str> for Id {
+    fn eq(&self, other: &&str) -> bool {
+        matches!(self, Id::Str(id) if id == other)
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Id::Int(id) => write!(f, "{id}"),
+            Id::Str(id) => write!(f, "{id}"),
+            Id::Uuid(id) => {
+                let hex = format!("{id:032x}");
+                write!(
+                    f,
+                    "{}-{}-{}-{}-{}",
+                    &hex[0..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..32]
+                )
+            }
+        }
+    }
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+pub enum IdKind {
+    /// Unsigned integer ids.
+    #[default]
+    Int = 1,
+    /// String ids such as slugs.
+    Str = 2,
+    /// 128-bit ids such as UUIDs.
+    Uuid = 3,
+}
+
+impl FromStr for IdKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "int" => Ok(IdKind::Int),
+            "string" => Ok(IdKind::Str),
+            "uuid" => Ok(IdKind::Uuid),
+            _ => Err(anyhow::anyhow!("unknown id kind: {s}")),
+        }
     }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct SearchResult {
-    pub id: usize,
+    pub id: Id,
     pub distance: f32,
     pub metadata: Metadata,
 }
 
+/// Results of a vector search, nearest first. Dereferences to the results
+/// themselves.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct SearchResults {
+    pub results: Vec<SearchResult>,
+    /// Whether all `k` results asked for were found. It is false only when
+    /// the collection, or the part of it the search is restricted to, holds
+    /// fewer entries.
+    pub complete: bool,
+}
+
+impl Deref for SearchResults {
+    type Target = [SearchResult];
+
+    fn deref(&self) -> &[SearchResult] {
+        &self.results
+    }
+}
+
+impl IntoIterator for SearchResults {
+    type Item = SearchResult;
+    type IntoIter = std::vec::IntoIter<SearchResult>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.results.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a SearchResults {
+    type Item = &'a SearchResult;
+    type IntoIter = std::slice::Iter<'a, SearchResult>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.results.iter()
+    }
+}
+
+/// Result of a keyword or hybrid search. Higher scores are better.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ScoredResult {
+    pub id: Id,
+    pub score: f32,
+    pub metadata: Metadata,
+}
+
+/// How keyword and vector rankings are combined by a hybrid search.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fusion {
+    /// Reciprocal rank fusion: each ranking contributes `1 / (k + rank)`.
+    /// `k = 60` is the usual choice.
+    ReciprocalRank { k: f32 },
+    /// Both scores are min-max normalized per query and mixed as
+    /// `alpha * vector + (1 - alpha) * keyword`.
+    Weighted { alpha: f32 },
+}
+
+impl Default for Fusion {
+    fn default() -> Self {
+        Fusion::ReciprocalRank { k: 60.0 }
+    }
+}
+
+/// How [`crate::VectorDB::recommend`] turns positive and negative examples
+/// into a ranking.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Recommend {
+    /// Searches for a single target, the mean of the positives pushed away
+    /// from the mean of the negatives: `2 * mean(pos) - mean(neg)`.
+    #[default]
+    AverageVector,
+    /// Ranks candidates by their distance to the closest positive. Candidates
+    /// closer to some negative than to every positive come last.
+    BestScore,
+}
+
 #[repr(u8)]
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum Metric {
@@ -26,6 +216,62 @@ pub enum Metric {
     Cosine = 1,
     /// Euclidean distance metric.
     Euclidean = 2,
+    /// Manhattan (L1) distance metric.
+    Manhattan = 3,
+    /// Chebyshev (L∞) distance metric.
+    Chebyshev = 4,
+    /// Minkowski distance metric of order `p`, which must be at least 1.
+    /// See [`Metric::minkowski`].
+    Minkowski(OrderedFloat<f32>) = 5,
+    /// Hamming distance between binary vectors.
+    Hamming = 6,
+    /// Jaccard (Tanimoto) distance between binary vectors.
+    Jaccard = 7,
     // When adding new variants, assign explicit discriminant values to ensure
     // backward compatibility with existing files.
 }
+
+impl Metric {
+    /// The Minkowski metric of order `p`.
+    pub fn minkowski(p: f32) -> Self {
+        Metric::Minkowski(OrderedFloat(p))
+    }
+
+    /// Whether collections with this metric hold binary vectors.
+    pub fn is_binary(self) -> bool {
+        matches!(self, Metric::Hamming | Metric::Jaccard)
+    }
+}
+
+impl FromStr for Metric {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cosine" => Ok(Metric::Cosine),
+            "euclidean" => Ok(Metric::Euclidean),
+            "manhattan" => Ok(Metric::Manhattan),
+            "chebyshev" => Ok(Metric::Chebyshev),
+            "hamming" => Ok(Metric::Hamming),
+            "jaccard" | "tanimoto" => Ok(Metric::Jaccard),
+            other => match other.strip_prefix("minkowski:") {
+                Some(p) => Ok(Metric::minkowski(p.parse()?)),
+                None => Err(anyhow::anyhow!("unknown metric: {s}")),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Metric::Cosine => write!(f, "cosine"),
+            Metric::Euclidean => write!(f, "euclidean"),
+            Metric::Manhattan => write!(f, "manhattan"),
+            Metric::Chebyshev => write!(f, "chebyshev"),
+            Metric::Minkowski(p) => write!(f, "minkowski:{p}"),
+            Metric::Hamming => write!(f, "hamming"),
+            Metric::Jaccard => write!(f, "jaccard"),
+        }
+    }
+}
@@ -1,210 +1,1535 @@
 use anyhow::{Result, anyhow};
-use hnsw::Searcher;
-use ordered_float::NotNan;
+use ordered_float::{NotNan, OrderedFloat};
 use rayon::prelude::*;
-use std::collections::HashSet;
-use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::Path;
 
-use crate::metrics::Index;
-use crate::params::Params;
-use crate::storage::{Header, Storage};
-use crate::storage::{StoredEntry, VERSION};
-use crate::types::{Metadata, Metric, SearchResult};
+use crate::binary::{self, BinaryVector};
+use crate::element;
+#[cfg(feature = "formats")]
+use crate::formats::Line;
+use crate::eval::{self, Evaluation, Rebuild, Tuning, AUTOTUNE_K};
+use crate::graph::Searcher;
+use crate::metrics::{self, Index};
+use crate::params::{CollectionOptions, ElementType, IndexKind, Params, Quantization};
+use crate::sparse::{SparseIndex, SparseVector};
+use crate::storage::{Header, Storage, StoredCollection, StoredEntry, StoredIvf, Vectors};
+use crate::text::TextIndex;
+use crate::types::{
+    Fusion, Id, Metadata, Metric, Recommend, ScoredResult, SearchResult, SearchResults,
+};
 
 #[derive(Clone)]
 struct Entry {
-    id: usize,
+    id: Id,
+    /// Index nodes holding the entry's vectors.
+    nodes: Range<usize>,
+    /// File offset of the entry's record.
+    offset: u64,
     metadata: Metadata,
+    namespace: Option<String>,
     deleted: bool,
 }
 
+/// Candidates per requested result that a quantized search rescores, unless
+/// the query asks for another factor.
+pub const DEFAULT_OVERSAMPLING: f32 = 3.0;
+
 pub struct VectorDB<const M: usize = 12, const M0: usize = 24> {
     storage: Storage,
-    path: PathBuf,
+    collection: u32,
+    name: String,
     metric: Metric,
     dim: usize,
     index: Index<M, M0>,
-    searcher: Searcher<u32>,
+    searcher: Searcher,
     entries: Vec<Entry>,
-    ids: HashSet<usize>,
+    /// Position in `entries` of the entry owning each index node.
+    owners: Vec<usize>,
+    /// Original norm of each node's vector, for cosine collections that keep
+    /// magnitudes and hold their vectors in the index. Empty otherwise.
+    norms: Vec<f32>,
+    /// Position in `entries` of each live id.
+    ids: HashMap<Id, usize>,
+    /// Live entry count per namespace.
+    namespaces: HashMap<String, usize>,
+    /// Keyword index, present when the collection has text fields.
+    text: Option<TextIndex>,
+    sparse: SparseIndex,
     params: Params,
+    options: CollectionOptions,
 }
 
 impl<const M: usize, const M0: usize> VectorDB<M, M0> {
+    /// Opens the default collection of the file at `path`, creating the file
+    /// and the collection if needed. Parameters stored with an existing
+    /// collection are kept.
     pub fn open<P: AsRef<Path>>(path: P, metric: Metric) -> Result<Self> {
-        Self::open_with_params(path, metric, Params::default())
+        Self::open_collection(path, crate::DEFAULT_COLLECTION, metric, None, None)
     }
 
+    /// Like [`VectorDB::open`], but replaces the stored parameters with `params`.
     pub fn open_with_params<P: AsRef<Path>>(
         path: P,
         metric: Metric,
         params: Params,
     ) -> Result<Self> {
-        let path_buf = path.as_ref().to_path_buf();
-        if path.as_ref().exists() {
-            let (storage, header, stored_entries) = Storage::open(&path_buf)?;
-            if header.metric != metric {
-                return Err(anyhow!("Metric mismatch"));
+        Self::open_collection(path, crate::DEFAULT_COLLECTION, metric, Some(params), None)
+    }
+
+    /// Like [`VectorDB::open_with_params`], but also sets the options used if
+    /// the collection has to be created. Opening an existing collection with
+    /// different options fails.
+    pub fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        metric: Metric,
+        params: Params,
+        options: CollectionOptions,
+    ) -> Result<Self> {
+        Self::open_collection(
+            path,
+            crate::DEFAULT_COLLECTION,
+            metric,
+            Some(params),
+            Some(options),
+        )
+    }
+
+    fn open_collection<P: AsRef<Path>>(
+        path: P,
+        name: &str,
+        metric: Metric,
+        params: Option<Params>,
+        options: Option<CollectionOptions>,
+    ) -> Result<Self> {
+        let (storage, catalog) = Storage::open_or_create(path)?;
+        let stored = catalog
+            .collections
+            .into_iter()
+            .find(|c| c.header.name == name);
+        match stored {
+            Some(mut c) => {
+                if c.header.metric != metric {
+                    return Err(anyhow!("Metric mismatch"));
+                }
+                let options = options.map(|mut o| {
+                    o.fit(metric);
+                    o
+                });
+                if options.is_some_and(|o| o != c.header.options) {
+                    return Err(anyhow!("Options mismatch"));
+                }
+                if let Some(params) = params.filter(|&p| p != c.header.params) {
+                    c.header.params = params;
+                    storage.update_header(&c.header)?;
+                }
+                Self::load(storage, c)
             }
-            let mut db = Self::new_empty(storage, path_buf, metric, header.dim as usize, params);
-            for e in stored_entries {
-                db.apply_entry(e)?;
+            None => {
+                let header = Header {
+                    id: catalog.next_id,
+                    name: name.to_string(),
+                    metric,
+                    dim: 0,
+                    params: params.unwrap_or_default(),
+                    options: options.unwrap_or_default(),
+                };
+                Self::create(storage, header)
             }
-            Ok(db)
-        } else {
-            let storage = Storage::create(&path_buf, metric)?;
-            let db = Self::new_empty(storage, path_buf, metric, 0, params);
-            Ok(db)
         }
     }
 
-    fn new_empty(
-        storage: Storage,
-        path: PathBuf,
-        metric: Metric,
-        dim: usize,
-        params: Params,
-    ) -> Self {
+    /// Registers a new, empty collection described by `header` in `storage`.
+    pub(crate) fn create(storage: Storage, mut header: Header) -> Result<Self> {
+        header.options.fit(header.metric);
+        if let Metric::Minkowski(OrderedFloat(p)) = header.metric {
+            if !(p >= 1.0 && p.is_finite()) {
+                return Err(anyhow!("minkowski order must be a finite number of at least 1"));
+            }
+        }
+        if header.metric.is_binary() && header.options.quantization != Quantization::None {
+            return Err(anyhow!("binary collections cannot be quantized"));
+        }
+        if header.metric.is_binary() && header.options.element_type != ElementType::F32 {
+            return Err(anyhow!("binary collections have no element type"));
+        }
+        if let IndexKind::Ivf { nlist, nprobe } = header.options.index {
+            if header.metric.is_binary() {
+                return Err(anyhow!("binary collections have no ivf index"));
+            }
+            if header.options.quantization != Quantization::None {
+                return Err(anyhow!("ivf collections cannot be quantized"));
+            }
+            if nlist == 0 || nprobe == 0 {
+                return Err(anyhow!("nlist and nprobe must be positive"));
+            }
+        }
+        Self::check_m(&header)?;
+        storage.update_header(&header)?;
+        Ok(Self::new_empty(storage, header))
+    }
+
+    /// Rebuilds a collection from the records read back from storage.
+    pub(crate) fn load(storage: Storage, stored: StoredCollection) -> Result<Self> {
+        Self::check_m(&stored.header)?;
+        let mut db = Self::new_empty(storage, stored.header);
+        // Entries written before the latest training were moved by it.
+        let (trained, mut assignments) = match stored.ivf {
+            Some((at, ivf)) => {
+                let ivf_index = db.index.ivf_mut().ok_or(anyhow!("ivf record in another index"))?;
+                ivf_index.set_centroids(ivf.centroids);
+                (at, ivf.assignments.into_iter().collect())
+            }
+            None => (0, HashMap::new()),
+        };
+        // Values that a later record replaces or deletes never reach the
+        // index, so they need not be removed from it again.
+        let last: HashMap<&Id, usize> =
+            stored.entries.iter().enumerate().map(|(i, e)| (&e.id, i)).collect();
+        let replaced: Vec<bool> =
+            stored.entries.iter().enumerate().map(|(i, e)| last[&e.id] != i).collect();
+        let records = stored.entries.into_iter().zip(stored.offsets).zip(replaced);
+        for (i, ((mut e, offset), replaced)) in records.enumerate() {
+            if replaced {
+                continue;
+            }
+            if i < trained {
+                e.lists = assignments.remove(&e.id).unwrap_or_default();
+            }
+            db.apply_entry(e, offset)?;
+        }
+        db.train_if_needed()?;
+        Ok(db)
+    }
+
+    /// Fails if the collection of `header` records an `M` other than this one.
+    fn check_m(header: &Header) -> Result<()> {
+        match header.options.m {
+            Some(m) if m != M => Err(anyhow!("collection {} needs M = {m}, not {M}", header.name)),
+            _ => Ok(()),
+        }
+    }
+
+    fn new_empty(storage: Storage, header: Header) -> Self {
+        let text = if header.options.text_fields.is_empty() {
+            None
+        } else {
+            Some(TextIndex::new(header.options.text_fields.clone()))
+        };
+        let index_metric = match header.options.quantization {
+            Quantization::None => header.metric,
+            Quantization::Binary => Metric::Hamming,
+        };
         Self {
             storage,
-            path,
-            metric,
-            dim,
-            index: Index::new_params(metric, params.ef_construction),
+            collection: header.id,
+            name: header.name,
+            metric: header.metric,
+            dim: header.dim as usize,
+            index: Index::new_params(
+                index_metric,
+                header.options.element_type,
+                header.options.index,
+                header.params.ef_construction,
+            ),
             searcher: Searcher::default(),
             entries: Vec::new(),
-            ids: HashSet::new(),
-            params,
+            owners: Vec::new(),
+            norms: Vec::new(),
+            ids: HashMap::new(),
+            namespaces: HashMap::new(),
+            text,
+            sparse: SparseIndex::default(),
+            params: header.params,
+            options: header.options,
         }
     }
 
-    fn apply_entry(&mut self, entry: StoredEntry) -> Result<()> {
-        if entry.deleted {
-            if let Some(pos) = self.entries.iter_mut().position(|e| e.id == entry.id && !e.deleted) {
-                self.entries[pos].deleted = true;
-                self.ids.remove(&entry.id);
+    fn header(&self) -> Header {
+        Header {
+            id: self.collection,
+            name: self.name.clone(),
+            metric: self.metric,
+            dim: self.dim as u32,
+            params: self.params,
+            options: self.options.clone(),
+        }
+    }
+
+    fn mark_deleted(&mut self, pos: usize) {
+        for node in self.entries[pos].nodes.clone() {
+            self.index.remove(node);
+        }
+        let entry = &mut self.entries[pos];
+        entry.deleted = true;
+        self.ids.remove(&entry.id);
+        if let Some(text) = &mut self.text {
+            text.remove(pos);
+        }
+        self.sparse.remove(pos);
+        if let Some(ns) = &entry.namespace {
+            if let Some(count) = self.namespaces.get_mut(ns) {
+                *count -= 1;
+                if *count == 0 {
+                    self.namespaces.remove(ns);
+                }
             }
-            return Ok(());
         }
+    }
 
-        if self.ids.contains(&entry.id) {
-            // previous value exists, mark deleted
-            if let Some(pos) = self.entries.iter_mut().position(|e| e.id == entry.id && !e.deleted) {
-                self.entries[pos].deleted = true;
-                self.ids.remove(&entry.id);
+    fn insert_entry(&mut self, entry: StoredEntry, offset: u64) {
+        let pos = self.entries.len();
+        let first = self.owners.len();
+        for (i, mut vector) in entry.vectors.into_f32().into_iter().enumerate() {
+            if self.metric == Metric::Cosine {
+                let norm = metrics::normalize(&mut vector);
+                if self.keeps_norms() {
+                    self.norms.push(norm);
+                }
+            }
+            match (self.options.quantization, self.index.ivf_mut()) {
+                (Quantization::None, Some(ivf)) if i < entry.lists.len() => {
+                    ivf.insert_into(vector, entry.lists[i]);
+                }
+                (Quantization::None, _) => self.index.insert(vector, &mut self.searcher),
+                (Quantization::Binary, _) => {
+                    self.index.insert_binary(binary::sign_bits(&vector), &mut self.searcher)
+                }
             }
+            self.owners.push(pos);
+        }
+        if let Some(binary) = entry.binary {
+            self.index.insert_binary(binary.into_words(), &mut self.searcher);
+            self.owners.push(pos);
+        }
+        if let Some(ns) = &entry.namespace {
+            *self.namespaces.entry(ns.clone()).or_insert(0) += 1;
+        }
+        if let Some(text) = &mut self.text {
+            text.insert(pos, &entry.metadata);
+        }
+        if let Some(sparse) = entry.sparse {
+            self.sparse.insert(pos, sparse);
         }
+        self.ids.insert(entry.id.clone(), pos);
+        self.entries.push(Entry {
+            id: entry.id,
+            nodes: first..self.owners.len(),
+            offset,
+            metadata: entry.metadata,
+            namespace: entry.namespace,
+            deleted: false,
+        });
+    }
+
+    fn keeps_norms(&self) -> bool {
+        self.metric == Metric::Cosine
+            && self.options.keep_magnitude
+            && self.options.quantization == Quantization::None
+    }
+
+    fn apply_entry(&mut self, entry: StoredEntry, offset: u64) -> Result<()> {
+        // a tombstone, or a previous value that is replaced
+        if let Some(&pos) = self.ids.get(&entry.id) {
+            self.mark_deleted(pos);
+        }
+        if entry.deleted {
+            return Ok(());
+        }
+
+        if let Some(dim) = self.check_vectors(&entry)? {
+            self.dim = dim;
+        }
+        self.insert_entry(entry, offset);
+        Ok(())
+    }
 
+    /// Checks that `entry` holds vectors of the collection's kind and
+    /// dimension. Returns the dimension they give an empty collection.
+    fn check_vectors(&self, entry: &StoredEntry) -> Result<Option<usize>> {
+        let dim = if self.metric.is_binary() {
+            if !entry.vectors.is_empty() {
+                return Err(anyhow!("binary collection"));
+            }
+            entry.binary.as_ref().ok_or(anyhow!("no vectors"))?.len()
+        } else {
+            if entry.binary.is_some() {
+                return Err(anyhow!("not a binary collection"));
+            }
+            entry.vectors.dim()?
+        };
         if self.dim == 0 {
-            self.dim = entry.vector.len();
-        } else if entry.vector.len() != self.dim {
+            Ok(Some(dim))
+        } else if dim != self.dim {
+            Err(anyhow!("dimension mismatch"))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn check_query(&self, query: &[f32]) -> Result<()> {
+        if self.metric.is_binary() {
+            return Err(anyhow!("binary collection"));
+        }
+        if query.len() != self.dim {
             return Err(anyhow!("dimension mismatch"));
         }
-        self.index.insert(entry.vector, &mut self.searcher);
-        self.entries.push(Entry { id: entry.id, metadata: entry.metadata, deleted: false });
-        self.ids.insert(entry.id);
         Ok(())
     }
 
-    pub fn add(&mut self, id: usize, vector: Vec<f32>, metadata: Metadata) -> Result<()> {
-        if self.ids.contains(&id) {
+    pub fn add(&mut self, id: impl Into<Id>, vector: Vec<f32>, metadata: Metadata) -> Result<()> {
+        self.add_entry(StoredEntry::new(id.into(), vector, metadata))
+    }
+
+    /// Adds an entry that also carries a sparse vector for
+    /// [`VectorDB::search_sparse`].
+    pub fn add_with_sparse(
+        &mut self,
+        id: impl Into<Id>,
+        vector: Vec<f32>,
+        sparse: SparseVector,
+        metadata: Metadata,
+    ) -> Result<()> {
+        self.add_entry(StoredEntry {
+            sparse: Some(sparse),
+            ..StoredEntry::new(id.into(), vector, metadata)
+        })
+    }
+
+    /// Adds an entry owning several vectors, such as per-token embeddings of a
+    /// late-interaction model. Searches return it once, scored by its closest
+    /// vector; [`VectorDB::search_multi`] scores it by MaxSim.
+    pub fn add_multi(
+        &mut self,
+        id: impl Into<Id>,
+        vectors: Vec<Vec<f32>>,
+        metadata: Metadata,
+    ) -> Result<()> {
+        self.add_entry(StoredEntry::multi(id.into(), vectors, metadata))
+    }
+
+    /// Adds an entry to a binary collection, one using [`Metric::Hamming`] or
+    /// [`Metric::Jaccard`].
+    pub fn add_binary(
+        &mut self,
+        id: impl Into<Id>,
+        vector: BinaryVector,
+        metadata: Metadata,
+    ) -> Result<()> {
+        self.add_entry(StoredEntry::binary(id.into(), vector, metadata))
+    }
+
+    /// Adds an entry to `namespace`. Ids are unique across all namespaces of
+    /// the collection.
+    pub fn add_in(
+        &mut self,
+        namespace: &str,
+        id: impl Into<Id>,
+        vector: Vec<f32>,
+        metadata: Metadata,
+    ) -> Result<()> {
+        self.add_entry(StoredEntry {
+            namespace: Some(namespace.to_string()),
+            ..StoredEntry::new(id.into(), vector, metadata)
+        })
+    }
+
+    /// Adds several entries with a single write to the file. Either all of
+    /// them are added or, if one is invalid, none.
+    pub fn add_batch(&mut self, entries: Vec<(Id, Vec<f32>, Metadata)>) -> Result<()> {
+        let mut batch = Vec::with_capacity(entries.len());
+        let mut ids = HashSet::new();
+        let dim = self.dim;
+        for (id, vector, metadata) in entries {
+            let entry = StoredEntry::new(id, vector, metadata);
+            let mut checked = self.check_entry(&entry);
+            if checked.is_ok() && !ids.insert(entry.id.clone()) {
+                checked = Err(anyhow!("duplicate id"));
+            }
+            if let Err(e) = checked {
+                // the first entry may have set the dimension
+                self.dim = dim;
+                return Err(e);
+            }
+            batch.push(self.prepare_entry(entry));
+        }
+        if self.dim != dim {
+            self.storage.update_header(&self.header())?;
+        }
+        let offsets = self.storage.append_entries(self.collection, &batch)?;
+        for (entry, offset) in batch.into_iter().zip(offsets) {
+            self.insert_entry(entry, offset);
+        }
+        self.train_if_needed()
+    }
+
+    /// Adds the records of `lines` in batches of `batch` entries. A line
+    /// without a record, or whose entry cannot be added, is passed to
+    /// `reject` with the reason and the import goes on. After each batch
+    /// `progress` gets the number of lines read so far. Returns the number
+    /// of entries added.
+    #[cfg(feature = "formats")]
+    pub fn import(
+        &mut self,
+        lines: impl IntoIterator<Item = Result<Line>>,
+        batch: usize,
+        mut reject: impl FnMut(&Line, &anyhow::Error) -> Result<()>,
+        mut progress: impl FnMut(usize),
+    ) -> Result<usize> {
+        let mut lines = lines.into_iter();
+        let (mut read, mut added) = (0, 0);
+        loop {
+            let mut pending = Vec::with_capacity(batch.max(1));
+            for line in lines.by_ref() {
+                let line = line?;
+                read += 1;
+                match &line.record {
+                    Ok(_) => pending.push(line),
+                    Err(e) => reject(&line, e)?,
+                }
+                if pending.len() == batch.max(1) {
+                    break;
+                }
+            }
+            if pending.is_empty() {
+                return Ok(added);
+            }
+            let entries = pending
+                .iter()
+                .filter_map(|line| line.record.as_ref().ok())
+                .map(|r| (r.id.clone(), r.vector.clone(), r.metadata.clone()))
+                .collect();
+            if self.add_batch(entries).is_ok() {
+                added += pending.len();
+            } else {
+                // add what can be added, one entry at a time
+                for line in &pending {
+                    let Ok(r) = &line.record else { continue };
+                    match self.add(r.id.clone(), r.vector.clone(), r.metadata.clone()) {
+                        Ok(()) => added += 1,
+                        Err(e) => reject(line, &e)?,
+                    }
+                }
+            }
+            progress(read);
+        }
+    }
+
+    fn add_entry(&mut self, entry: StoredEntry) -> Result<()> {
+        let dim = self.dim;
+        self.check_entry(&entry)?;
+        if self.dim != dim {
+            self.storage.update_header(&self.header())?;
+        }
+        let entry = self.prepare_entry(entry);
+        let offset = self.storage.append_entry(self.collection, &entry)?;
+        self.insert_entry(entry, offset);
+        self.train_if_needed()
+    }
+
+    /// Checks that `entry` may be added, taking its dimension if it is the
+    /// first.
+    fn check_entry(&mut self, entry: &StoredEntry) -> Result<()> {
+        if entry.id.kind() != self.options.id_kind {
+            return Err(anyhow!("id kind mismatch"));
+        }
+        if self.ids.contains_key(&entry.id) {
             return Err(anyhow!("duplicate id"));
         }
-        if self.dim == 0 {
-            self.dim = vector.len();
-            let header = Header {
-                magic: crate::storage::MAGIC,
-                version: VERSION,
-                metric: self.metric,
-                dim: self.dim as u32,
-            };
-            self.storage.update_header(&header)?;
-        } else if vector.len() != self.dim {
-            return Err(anyhow!("dimension mismatch"));
+        if let Vectors::F32(vectors) = &entry.vectors {
+            for v in vectors {
+                element::check(v, self.options.element_type)?;
+            }
+        }
+        if let Some(dim) = self.check_vectors(entry)? {
+            self.dim = dim;
         }
-        let stored = StoredEntry {
-            id,
-            vector: vector.clone(),
-            metadata: metadata.clone(),
-            deleted: false,
-        };
-        self.index.insert(vector, &mut self.searcher);
-        self.storage.append_entry(&stored)?;
-        self.entries.push(Entry { id, metadata, deleted: false });
-        self.ids.insert(id);
         Ok(())
     }
 
-    pub fn search(&self, query: &[f32], k: usize) -> Result<Vec<SearchResult>> {
+    /// Converts the vectors of a checked entry to the element type, and in a
+    /// trained IVF collection assigns them to lists.
+    fn prepare_entry(&self, mut entry: StoredEntry) -> StoredEntry {
+        entry.vectors = entry.vectors.convert(self.options.element_type);
+        if let Some(ivf) = self.index.ivf().filter(|ivf| ivf.is_trained()) {
+            entry.lists = entry
+                .vectors
+                .clone()
+                .into_f32()
+                .into_iter()
+                .map(|mut v| {
+                    if self.metric == Metric::Cosine {
+                        metrics::normalize(&mut v);
+                    }
+                    ivf.nearest_list(&v)
+                })
+                .collect();
+        }
+        entry
+    }
+
+    /// Trains the quantizer of an IVF collection that has grown enough.
+    fn train_if_needed(&mut self) -> Result<()> {
+        if self.index.ivf().is_some_and(|ivf| ivf.needs_training()) {
+            self.train()?;
+        }
+        Ok(())
+    }
+
+    /// Trains the k-means quantizer of an IVF collection on the vectors it
+    /// holds and moves them into the lists of their nearest centroids. This
+    /// happens by itself once the collection reaches
+    /// [`crate::TRAINING_POINTS_PER_LIST`] vectors per list; training again
+    /// later follows vectors added since.
+    pub fn train(&mut self) -> Result<()> {
+        let ivf = self.index.ivf_mut().ok_or(anyhow!("not an ivf collection"))?;
+        ivf.train();
+        if !ivf.is_trained() {
+            return Err(anyhow!("no vectors to train on"));
+        }
+        let ivf = self.index.ivf().unwrap();
+        let assignments = self
+            .entries
+            .iter()
+            .filter(|e| !e.deleted)
+            .map(|e| (e.id.clone(), e.nodes.clone().map(|n| ivf.list(n)).collect()))
+            .collect();
+        let stored = StoredIvf {
+            centroids: ivf.centroids().to_vec(),
+            assignments,
+        };
+        self.storage.append_ivf(self.collection, &stored)
+    }
+
+    /// Returns the `k` entries nearest to `query`. A graph search that comes
+    /// up short is repeated with a wider beam, and in the end with a scan of
+    /// the whole collection, so fewer than `k` results mean that there are
+    /// no more entries.
+    pub fn search(&self, query: &[f32], k: usize) -> Result<SearchResults> {
+        let hits = self.search_hits(query, k, self.params.ef_search, DEFAULT_OVERSAMPLING)?;
+        Ok(self.results(hits, k))
+    }
+
+    /// Like [`VectorDB::search`], but the graph search starts with a beam of
+    /// `ef_search` instead of the one in the collection's [`Params`].
+    pub fn search_with_ef(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef_search: usize,
+    ) -> Result<SearchResults> {
+        Ok(self.results(self.search_hits(query, k, ef_search, DEFAULT_OVERSAMPLING)?, k))
+    }
+
+    /// Like [`VectorDB::search`], but a quantized collection rescores
+    /// `oversampling * k` candidates with the full vectors. Other collections
+    /// ignore `oversampling`.
+    pub fn search_with_oversampling(
+        &self,
+        query: &[f32],
+        k: usize,
+        oversampling: f32,
+    ) -> Result<SearchResults> {
+        if oversampling.is_nan() || oversampling < 1.0 {
+            return Err(anyhow!("oversampling must be at least 1"));
+        }
+        let hits = self.search_hits(query, k, self.params.ef_search, oversampling)?;
+        Ok(self.results(hits, k))
+    }
+
+    fn search_hits(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef_search: usize,
+        oversampling: f32,
+    ) -> Result<Vec<(usize, f32)>> {
+        self.check_query(query)?;
+        let real_k = k.min(self.len());
+        // multi-vector entries take several neighbor slots each
+        let ef = ef_search.max(real_k * 2) * self.index.len() / self.len().max(1);
+        self.rescored_search(query, real_k, ef, oversampling, |e| !e.deleted)
+    }
+
+    /// Searches a binary collection. Distances are bit counts for
+    /// [`Metric::Hamming`] and `1 - similarity` for [`Metric::Jaccard`].
+    pub fn search_binary(&self, query: &BinaryVector, k: usize) -> Result<SearchResults> {
+        if !self.metric.is_binary() {
+            return Err(anyhow!("not a binary collection"));
+        }
         if query.len() != self.dim {
             return Err(anyhow!("dimension mismatch"));
         }
-        let valid = self.entries.iter().filter(|e| !e.deleted).count();
-        let real_k = k.min(valid);
-        let mut neighbors = vec![
+        let real_k = k.min(self.len());
+        let mut ef = self.params.ef_search.max(real_k * 2).max(1);
+        let q = query.words().to_vec();
+        // see `rescored_search`
+        while ef < self.index.len() {
+            let mut neighbors = self.neighbor_buffer(ef);
+            let found = Searcher::pooled(|searcher| {
+                self.index.nearest_binary(&q, ef, searcher, &mut neighbors)
+            });
+            let hits = self.node_hits(found, real_k, |e| !e.deleted);
+            if hits.len() >= real_k {
+                return Ok(self.results(hits, k));
+            }
+            ef *= 2;
+        }
+        let mut hits: Vec<(usize, f32)> = (0..self.entries.len())
+            .filter(|&pos| !self.entries[pos].deleted)
+            .map(|pos| {
+                let words = self.index.binary_feature(self.entries[pos].nodes.start);
+                let distance = match self.metric {
+                    Metric::Jaccard => 1.0 - binary::jaccard(query.words(), words),
+                    _ => binary::hamming(query.words(), words) as f32,
+                };
+                (pos, distance)
+            })
+            .collect();
+        hits.sort_by_key(|&(pos, d)| (NotNan::new(d).unwrap(), pos));
+        hits.truncate(real_k);
+        Ok(self.results(hits, k))
+    }
+
+    /// Returns the `k` entries nearest to the one stored under `id`,
+    /// excluding that entry. A multi-vector entry is searched by its first
+    /// vector.
+    pub fn search_by_id(&self, id: impl Into<Id>, k: usize) -> Result<SearchResults> {
+        let id = id.into();
+        let query = self.example(&id)?;
+        let real_k = k.min(self.len() - 1);
+        let ef = self.params.ef_search.max(real_k * 2) * self.index.len()
+            / self.len().max(1);
+        let hits = self.hnsw_search(&query, real_k, ef, |e| !e.deleted && e.id != id)?;
+        Ok(self.results(hits, k))
+    }
+
+    /// Recommends `k` entries similar to the `positive` examples and unlike
+    /// the `negative` ones. The examples themselves are never returned.
+    pub fn recommend(
+        &self,
+        positive: &[Id],
+        negative: &[Id],
+        k: usize,
+        strategy: Recommend,
+    ) -> Result<SearchResults> {
+        if positive.is_empty() {
+            return Err(anyhow!("no positive examples"));
+        }
+        let pos_vectors: Vec<Vec<f32>> =
+            positive.iter().map(|id| self.example(id)).collect::<Result<_>>()?;
+        let neg_vectors: Vec<Vec<f32>> =
+            negative.iter().map(|id| self.example(id)).collect::<Result<_>>()?;
+        let excluded: HashSet<&Id> = positive.iter().chain(negative).collect();
+
+        let real_k = k.min(self.len() - excluded.len());
+        let ef = self.params.ef_search.max(real_k * 2) * self.index.len()
+            / self.len().max(1);
+        let accept = |e: &Entry| !e.deleted && !excluded.contains(&e.id);
+        let hits = match strategy {
+            Recommend::AverageVector => {
+                let mut target = mean(&pos_vectors);
+                if !neg_vectors.is_empty() {
+                    let neg = mean(&neg_vectors);
+                    for (t, n) in target.iter_mut().zip(neg) {
+                        *t += *t - n;
+                    }
+                }
+                self.hnsw_search(&target, real_k, ef, accept)?
+            }
+            Recommend::BestScore => {
+                let depth = ef.min(self.len() - excluded.len());
+                let mut candidates = HashSet::new();
+                for v in &pos_vectors {
+                    let hits = self.hnsw_search(v, depth, ef, accept)?;
+                    candidates.extend(hits.into_iter().map(|(pos, _)| pos));
+                }
+                let candidates: Vec<usize> = candidates.into_iter().collect();
+                let vectors = self.vectors(&candidates)?;
+                let closest = |i: usize, examples: &[Vec<f32>]| {
+                    examples
+                        .iter()
+                        .map(|v| self.closest(v, &vectors[i]))
+                        .fold(f32::INFINITY, f32::min)
+                };
+                let mut ranked: Vec<(bool, usize, f32)> = (0..candidates.len())
+                    .map(|i| {
+                        let d = closest(i, &pos_vectors);
+                        (closest(i, &neg_vectors) < d, candidates[i], d)
+                    })
+                    .collect();
+                ranked.sort_by_key(|&(rejected, pos, d)| (rejected, NotNan::new(d).unwrap(), pos));
+                let mut hits: Vec<(usize, f32)> =
+                    ranked.into_iter().map(|(_, pos, d)| (pos, d)).collect();
+                hits.truncate(real_k);
+                hits
+            }
+        };
+        Ok(self.results(hits, k))
+    }
+
+    /// First vector of the live entry stored under `id`.
+    fn example(&self, id: &Id) -> Result<Vec<f32>> {
+        if self.metric.is_binary() {
+            return Err(anyhow!("binary collection"));
+        }
+        let pos = *self.ids.get(id).ok_or(anyhow!("not found"))?;
+        let mut vectors = self.vectors(&[pos])?.remove(0);
+        Ok(vectors.swap_remove(0).into_owned())
+    }
+
+    /// Diversified search with Maximal Marginal Relevance. The `fetch_k`
+    /// nearest entries are picked one at a time, each maximising
+    /// `lambda * relevance - (1 - lambda) * redundancy`, where redundancy is
+    /// the similarity to the closest entry already picked. `lambda = 1` gives
+    /// plain top-k, `lambda = 0` maximal diversity.
+    ///
+    /// Results carry their distance to `query`.
+    pub fn search_mmr(
+        &self,
+        query: &[f32],
+        k: usize,
+        fetch_k: usize,
+        lambda: f32,
+    ) -> Result<SearchResults> {
+        if !(0.0..=1.0).contains(&lambda) {
+            return Err(anyhow!("lambda must be between 0 and 1"));
+        }
+        let ef = self.params.ef_search;
+        let mut candidates = self.search_hits(query, fetch_k.max(k), ef, DEFAULT_OVERSAMPLING)?;
+        let positions: Vec<usize> = candidates.iter().map(|h| h.0).collect();
+        let mut vectors = self.vectors(&positions)?;
+        // distances stand in for negated similarities
+        let mut redundancy = vec![0.0; candidates.len()];
+        let mut picked: Vec<(usize, f32)> = Vec::new();
+        while picked.len() < k && !candidates.is_empty() {
+            let best = (0..candidates.len())
+                .max_by_key(|&i| {
+                    let penalty = if picked.is_empty() { 0.0 } else { redundancy[i] };
+                    let score = -lambda * candidates[i].1 - (1.0 - lambda) * penalty;
+                    (NotNan::new(score).unwrap(), std::cmp::Reverse(i))
+                })
+                .unwrap();
+            let hit = candidates.remove(best);
+            redundancy.remove(best);
+            let hit_vectors = vectors.remove(best);
+            for (i, candidate) in vectors.iter().enumerate() {
+                let similarity = -self.set_distance(candidate, &hit_vectors);
+                redundancy[i] = if picked.is_empty() {
+                    similarity
+                } else {
+                    redundancy[i].max(similarity)
+                };
+            }
+            picked.push(hit);
+        }
+        Ok(self.results(picked, k))
+    }
+
+    /// Late-interaction search over multi-vector entries. Each entry is
+    /// scored by MaxSim: for every query vector, the distance to the entry's
+    /// closest vector, summed over the query. Lower is better.
+    ///
+    /// Candidates are the entries owning the nearest nodes of any query
+    /// vector; they are then rescored exactly.
+    pub fn search_multi(&self, queries: &[Vec<f32>], k: usize) -> Result<SearchResults> {
+        if queries.is_empty() {
+            return Err(anyhow!("no vectors"));
+        }
+        for q in queries {
+            self.check_query(q)?;
+        }
+        let queries: Vec<Cow<'_, [f32]>> =
+            queries.iter().map(|q| self.query_vector(q)).collect::<Result<_>>()?;
+        let real_k = k.min(self.len());
+        let ef = self.params.ef_search.max(real_k * 2);
+        let depth = ef.min(self.len());
+        let mut candidates = HashSet::new();
+        for q in &queries {
+            let hits = self.hnsw_search(q, depth, ef, |e| !e.deleted)?;
+            candidates.extend(hits.into_iter().map(|(pos, _)| pos));
+        }
+        let candidates: Vec<usize> = candidates.into_iter().collect();
+        let vectors = self.vectors(&candidates)?;
+        let mut hits: Vec<(usize, f32)> = candidates
+            .into_iter()
+            .zip(&vectors)
+            .map(|(pos, vectors)| {
+                let score = queries.iter().map(|q| self.closest(q, vectors)).sum();
+                (pos, score)
+            })
+            .collect();
+        hits.sort_by_key(|&(pos, d)| (NotNan::new(d).unwrap(), pos));
+        hits.truncate(real_k);
+        Ok(self.results(hits, k))
+    }
+
+    /// BM25 keyword search over the collection's text fields.
+    pub fn search_text(&self, query: &str, k: usize) -> Result<Vec<ScoredResult>> {
+        let text = self.text.as_ref().ok_or(anyhow!("no text fields configured"))?;
+        let hits = text.search(query, k, |pos| !self.entries[pos].deleted);
+        Ok(hits
+            .into_iter()
+            .map(|(pos, score)| self.scored(pos, score))
+            .collect())
+    }
+
+    /// Combines a vector search for `query` and a keyword search for `text`.
+    /// Each side contributes its best `max(ef_search, k)` candidates.
+    pub fn search_hybrid(
+        &self,
+        query: &[f32],
+        text: &str,
+        k: usize,
+        fusion: Fusion,
+    ) -> Result<Vec<ScoredResult>> {
+        self.check_query(query)?;
+        check_fusion(fusion)?;
+        let index = self.text.as_ref().ok_or(anyhow!("no text fields configured"))?;
+        let depth = self.params.ef_search.max(k);
+        let vector_hits = self.hnsw_search(query, depth.min(self.len()), depth, |e| !e.deleted)?;
+        let text_hits = index.search(text, depth, |pos| !self.entries[pos].deleted);
+        Ok(self.fuse(&vector_hits, &text_hits, k, fusion))
+    }
+
+    /// Dot-product search over the entries' sparse vectors. Higher scores are
+    /// better; entries without a sparse vector are never returned.
+    pub fn search_sparse(&self, query: &SparseVector, k: usize) -> Result<Vec<ScoredResult>> {
+        let hits = self.sparse.search(query, k, |pos| !self.entries[pos].deleted);
+        Ok(hits
+            .into_iter()
+            .map(|(pos, score)| self.scored(pos, score))
+            .collect())
+    }
+
+    /// Combines a dense search for `query` and a sparse search for `sparse`.
+    /// Each side contributes its best `max(ef_search, k)` candidates.
+    pub fn search_dense_sparse(
+        &self,
+        query: &[f32],
+        sparse: &SparseVector,
+        k: usize,
+        fusion: Fusion,
+    ) -> Result<Vec<ScoredResult>> {
+        self.check_query(query)?;
+        check_fusion(fusion)?;
+        let depth = self.params.ef_search.max(k);
+        let dense_hits = self.hnsw_search(query, depth.min(self.len()), depth, |e| !e.deleted)?;
+        let sparse_hits = self.sparse.search(sparse, depth, |pos| !self.entries[pos].deleted);
+        Ok(self.fuse(&dense_hits, &sparse_hits, k, fusion))
+    }
+
+    /// Merges vector hits (by distance) with hits of another ranking (by
+    /// score, higher first).
+    fn fuse(
+        &self,
+        vector_hits: &[(usize, f32)],
+        other_hits: &[(usize, f32)],
+        k: usize,
+        fusion: Fusion,
+    ) -> Vec<ScoredResult> {
+        let mut scores: HashMap<usize, f32> = HashMap::new();
+        match fusion {
+            Fusion::ReciprocalRank { k: c } => {
+                let ranked = vector_hits.iter().map(|h| h.0).enumerate();
+                for (rank, pos) in ranked.chain(other_hits.iter().map(|h| h.0).enumerate()) {
+                    *scores.entry(pos).or_insert(0.0) += 1.0 / (c + rank as f32 + 1.0);
+                }
+            }
+            Fusion::Weighted { alpha } => {
+                // Distances are turned into similarities by flipping their sign.
+                let similarities: Vec<(usize, f32)> =
+                    vector_hits.iter().map(|&(pos, d)| (pos, -d)).collect();
+                for (hits, weight) in [(&similarities[..], alpha), (other_hits, 1.0 - alpha)] {
+                    for (pos, score) in min_max(hits) {
+                        *scores.entry(pos).or_insert(0.0) += weight * score;
+                    }
+                }
+            }
+        }
+        let mut fused: Vec<(usize, f32)> = scores.into_iter().collect();
+        fused.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        fused.truncate(k);
+        fused
+            .into_iter()
+            .map(|(pos, score)| self.scored(pos, score))
+            .collect()
+    }
+
+    /// Searches only the entries of the given namespaces. Entries outside of
+    /// them are never returned.
+    pub fn search_in(
+        &self,
+        namespaces: &[&str],
+        query: &[f32],
+        k: usize,
+    ) -> Result<SearchResults> {
+        self.check_query(query)?;
+        let selected: usize = namespaces
+            .iter()
+            .filter_map(|ns| self.namespaces.get(*ns))
+            .sum();
+        let real_k = k.min(selected);
+        if real_k == 0 {
+            return Ok(self.results(Vec::new(), k));
+        }
+        let in_scope = |e: &Entry| {
+            !e.deleted
+                && e.namespace
+                    .as_deref()
+                    .is_some_and(|ns| namespaces.contains(&ns))
+        };
+        // Widen the beam by the inverse of the namespaces' share of the
+        // collection so that enough in-scope candidates survive filtering.
+        // Once that covers a large part of the graph a plain scan is cheaper.
+        let ef = self.params.ef_search.max(real_k * 2) * self.index.len() / selected;
+        let hits = if ef * 2 >= self.index.len() {
+            self.exact_search(query, real_k, in_scope)?
+        } else {
+            self.hnsw_search(query, real_k, ef, in_scope)?
+        };
+        Ok(self.results(hits, k))
+    }
+
+    /// Approximate nearest entries accepted by `filter`, as positions and
+    /// distances ordered by distance.
+    fn hnsw_search<F>(&self, query: &[f32], k: usize, ef: usize, filter: F) -> Result<Vec<(usize, f32)>>
+    where
+        F: Fn(&Entry) -> bool,
+    {
+        self.rescored_search(query, k, ef, DEFAULT_OVERSAMPLING, filter)
+    }
+
+    /// [`VectorDB::hnsw_search`] with the oversampling factor of quantized
+    /// collections given explicitly.
+    fn rescored_search<F>(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef: usize,
+        oversampling: f32,
+        filter: F,
+    ) -> Result<Vec<(usize, f32)>>
+    where
+        F: Fn(&Entry) -> bool,
+    {
+        let query = &*self.query_vector(query)?;
+        let shortlist = match self.options.quantization {
+            Quantization::None => k,
+            Quantization::Binary => (k as f32 * oversampling).ceil() as usize,
+        };
+        let mut ef = ef.max(shortlist).max(1);
+        // Nodes of entries that `filter` rejects, and further nodes of
+        // multi-vector entries, take up room in the beam. It is doubled until
+        // enough entries are left; a beam covering every node visits the
+        // whole graph anyway.
+        while ef < self.index.len() {
+            let hits = self.beam_search(query, k, shortlist, ef, &filter)?;
+            if hits.len() >= k {
+                return Ok(hits);
+            }
+            ef *= 2;
+        }
+        self.exact_search(query, k, filter)
+    }
+
+    /// One graph search of [`VectorDB::rescored_search`] with a beam of `ef`.
+    fn beam_search<F>(
+        &self,
+        query: &[f32],
+        k: usize,
+        shortlist: usize,
+        ef: usize,
+        filter: &F,
+    ) -> Result<Vec<(usize, f32)>>
+    where
+        F: Fn(&Entry) -> bool,
+    {
+        let mut neighbors = self.neighbor_buffer(ef);
+        if self.options.quantization == Quantization::None {
+            let found = Searcher::pooled(|searcher| {
+                self.index.nearest(query, ef, searcher, &mut neighbors)
+            });
+            return Ok(self.node_hits(found, k, filter));
+        }
+        // Hamming distances over sign bits only shortlist candidates; their
+        // order comes from the full vectors.
+        let q = binary::sign_bits(query);
+        let found = Searcher::pooled(|searcher| {
+            self.index.nearest_binary(&q, ef, searcher, &mut neighbors)
+        });
+        let candidates: Vec<usize> = self
+            .node_hits(found, shortlist, filter)
+            .into_iter()
+            .map(|(pos, _)| pos)
+            .collect();
+        let mut hits: Vec<(usize, f32)> = candidates
+            .iter()
+            .zip(self.vectors(&candidates)?)
+            .map(|(&pos, vectors)| (pos, self.closest(query, &vectors)))
+            .collect();
+        hits.sort_by_key(|&(pos, d)| (NotNan::new(d).unwrap(), pos));
+        hits.truncate(k);
+        Ok(hits)
+    }
+
+    /// `query` scaled to unit length in a cosine collection and rounded to the
+    /// collection's element type, like the vectors it is compared with. A
+    /// zero vector stays zero and is at distance 1 from every vector.
+    fn query_vector<'q>(&self, query: &'q [f32]) -> Result<Cow<'q, [f32]>> {
+        let element_type = self.options.element_type;
+        if self.metric != Metric::Cosine && element_type == ElementType::F32 {
+            element::check(query, element_type)?;
+            return Ok(Cow::Borrowed(query));
+        }
+        let mut query = query.to_vec();
+        if self.metric == Metric::Cosine {
+            metrics::normalize(&mut query);
+        }
+        element::round(&mut query, element_type)?;
+        Ok(Cow::Owned(query))
+    }
+
+    fn neighbor_buffer(&self, ef: usize) -> Vec<space::Neighbor<u32>> {
+        vec![
             space::Neighbor {
                 index: !0,
                 distance: 0
             };
-            self.entries.len()
-        ];
-        let mut searcher = Searcher::default();
-        let q = query.to_vec();
-        let found = self.index.nearest(
-            &q,
-            self.params.ef_search.max(real_k * 2),
-            &mut searcher,
-            &mut neighbors,
-        );
-        let mut results: Vec<SearchResult> = found
+            ef.min(self.index.len())
+        ]
+    }
+
+    /// Turns graph neighbors into the `k` best entries accepted by `filter`.
+    fn node_hits<F>(&self, found: &[space::Neighbor<u32>], k: usize, filter: F) -> Vec<(usize, f32)>
+    where
+        F: Fn(&Entry) -> bool,
+    {
+        // Neighbors come nearest first, so the first node seen of an entry
+        // carries its best distance.
+        let mut seen = HashSet::new();
+        let mut hits: Vec<(usize, f32)> = found
             .iter()
-            .filter_map(|n| {
-                let entry = &self.entries[n.index];
-                if entry.deleted {
-                    None
-                } else {
-                    Some(SearchResult {
-                        id: entry.id,
-                        distance: f32::from_bits(n.distance),
-                        metadata: entry.metadata.clone(),
+            .map(|n| (self.owners[n.index], f32::from_bits(n.distance)))
+            .filter(|&(pos, _)| filter(&self.entries[pos]) && seen.insert(pos))
+            .collect();
+        hits.sort_by_key(|&(_, d)| NotNan::new(d).unwrap());
+        hits.truncate(k);
+        hits
+    }
+
+    /// Brute-force scan over all entries accepted by `filter`.
+    fn exact_search<F>(&self, query: &[f32], k: usize, filter: F) -> Result<Vec<(usize, f32)>>
+    where
+        F: Fn(&Entry) -> bool,
+    {
+        let query = &*self.query_vector(query)?;
+        let positions: Vec<usize> = (0..self.entries.len())
+            .filter(|&i| !self.entries[i].nodes.is_empty() && filter(&self.entries[i]))
+            .collect();
+        let mut hits: Vec<(usize, f32)> = positions
+            .iter()
+            .zip(self.vectors(&positions)?)
+            .map(|(&pos, vectors)| (pos, self.closest(query, &vectors)))
+            .collect();
+        hits.sort_by_key(|&(_, d)| NotNan::new(d).unwrap());
+        hits.truncate(k);
+        Ok(hits)
+    }
+
+    /// The `f32` vectors of the entries at `positions`, as the index compares
+    /// them. Quantized collections read them back from storage.
+    fn vectors(&self, positions: &[usize]) -> Result<Vec<Vec<Cow<'_, [f32]>>>> {
+        if self.options.quantization == Quantization::None {
+            return Ok(positions
+                .iter()
+                .map(|&pos| {
+                    let nodes = self.entries[pos].nodes.clone();
+                    nodes.map(|n| self.index.feature(n)).collect()
+                })
+                .collect());
+        }
+        let offsets: Vec<u64> = positions.iter().map(|&pos| self.entries[pos].offset).collect();
+        let stored = self.storage.read_entries(&offsets)?;
+        Ok(stored
+            .into_iter()
+            .map(|e| {
+                e.vectors
+                    .into_f32()
+                    .into_iter()
+                    .map(|mut v| {
+                        if self.metric == Metric::Cosine {
+                            metrics::normalize(&mut v);
+                        }
+                        Cow::Owned(v)
                     })
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// The vectors of the entries at `positions` for reads: as they were
+    /// added, or at unit length in a cosine collection that does not keep
+    /// magnitudes.
+    fn entry_vectors(&self, positions: &[usize]) -> Result<Vec<Vec<Vec<f32>>>> {
+        let restore = self.metric == Metric::Cosine && self.options.keep_magnitude;
+        if restore && self.options.quantization != Quantization::None {
+            let offsets: Vec<u64> = positions.iter().map(|&pos| self.entries[pos].offset).collect();
+            let stored = self.storage.read_entries(&offsets)?;
+            return Ok(stored.into_iter().map(|e| e.vectors.into_f32()).collect());
+        }
+        let vectors = self.vectors(positions)?;
+        Ok(positions
+            .iter()
+            .zip(vectors)
+            .map(|(&pos, vectors)| {
+                let mut vectors: Vec<Vec<f32>> =
+                    vectors.into_iter().map(Cow::into_owned).collect();
+                if restore {
+                    let norms = &self.norms[self.entries[pos].nodes.clone()];
+                    for (v, norm) in vectors.iter_mut().zip(norms) {
+                        v.iter_mut().for_each(|x| *x *= norm);
+                    }
                 }
+                vectors
             })
-            .collect();
-        results.sort_by_key(|r| NotNan::new(r.distance).unwrap());
-        results.truncate(real_k);
-        Ok(results)
+            .collect())
+    }
+
+    fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        metrics::distance(self.metric, a, b)
+    }
+
+    /// Distance from `query` to the closest of `vectors`.
+    fn closest(&self, query: &[f32], vectors: &[Cow<'_, [f32]>]) -> f32 {
+        vectors
+            .iter()
+            .map(|v| self.distance(query, v))
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// Distance between the closest vectors of two entries.
+    fn set_distance(&self, a: &[Cow<'_, [f32]>], b: &[Cow<'_, [f32]>]) -> f32 {
+        a.iter()
+            .map(|v| self.closest(v, b))
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// Turns the hits of a search for `k` entries into its results.
+    fn results(&self, hits: Vec<(usize, f32)>, k: usize) -> SearchResults {
+        SearchResults {
+            complete: hits.len() >= k,
+            results: hits
+                .into_iter()
+                .map(|(pos, distance)| {
+                    let entry = &self.entries[pos];
+                    SearchResult {
+                        id: entry.id.clone(),
+                        distance,
+                        metadata: entry.metadata.clone(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn scored(&self, pos: usize, score: f32) -> ScoredResult {
+        let entry = &self.entries[pos];
+        ScoredResult {
+            id: entry.id.clone(),
+            score,
+            metadata: entry.metadata.clone(),
+        }
     }
 
     pub fn dimension(&self) -> usize {
         self.dim
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-    pub fn remove(&mut self, id: usize) -> Result<()> {
-        let pos = self.entries.iter_mut().position(|e| e.id == id && !e.deleted)
-            .ok_or(anyhow!("not found"))?;
-        self.entries[pos].deleted = true;
-        self.ids.remove(&id);
-        let tomb = StoredEntry { id, vector: Vec::new(), metadata: Metadata::default(), deleted: true };
-        self.storage.append_entry(&tomb)?;
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    pub fn params(&self) -> Params {
+        self.params
+    }
+
+    pub fn options(&self) -> &CollectionOptions {
+        &self.options
+    }
+
+    /// Returns the sparse vector stored under `id`, if it has one.
+    pub fn get_sparse(&self, id: impl Into<Id>) -> Option<&SparseVector> {
+        let pos = *self.ids.get(&id.into())?;
+        self.sparse.get(pos)
+    }
+
+    /// Returns the vector and metadata stored under `id`. For a multi-vector
+    /// entry this is its first vector. Entries of binary collections are
+    /// read with [`VectorDB::get_binary`]. Cosine collections return unit
+    /// vectors unless they keep magnitudes.
+    pub fn get(&self, id: impl Into<Id>) -> Option<(Vec<f32>, Metadata)> {
+        if self.metric.is_binary() {
+            return None;
+        }
+        let pos = *self.ids.get(&id.into())?;
+        let mut vectors = self.entry_vectors(&[pos]).ok()?.remove(0);
+        Some((vectors.swap_remove(0), self.entries[pos].metadata.clone()))
+    }
+
+    /// Returns all vectors stored under `id`.
+    pub fn get_vectors(&self, id: impl Into<Id>) -> Option<Vec<Vec<f32>>> {
+        if self.metric.is_binary() {
+            return None;
+        }
+        let pos = *self.ids.get(&id.into())?;
+        self.entry_vectors(&[pos]).ok().map(|mut v| v.remove(0))
+    }
+
+    /// The live entries in the order they were added, each with all its
+    /// vectors as [`VectorDB::get_vectors`] returns them. Vectors are read in
+    /// chunks, so a quantized collection opens its file once per chunk rather
+    /// than once per entry. Binary collections have no entries here.
+    pub fn iter_entries(&self) -> impl Iterator<Item = Result<(&Id, Vec<Vec<f32>>, &Metadata)>> {
+        let positions: Vec<usize> = if self.metric.is_binary() {
+            Vec::new()
+        } else {
+            (0..self.entries.len()).filter(|&pos| !self.entries[pos].deleted).collect()
+        };
+        let chunks: Vec<Vec<usize>> = positions.chunks(1024).map(<[usize]>::to_vec).collect();
+        chunks.into_iter().flat_map(move |chunk| {
+            let read = self.entry_vectors(&chunk).map(|vectors| {
+                chunk.iter().zip(vectors).map(|(&pos, vectors)| {
+                    let entry = &self.entries[pos];
+                    Ok((&entry.id, vectors, &entry.metadata))
+                })
+            });
+            match read {
+                Ok(entries) => entries.collect::<Vec<_>>(),
+                Err(e) => vec![Err(e)],
+            }
+        })
+    }
+
+    /// Returns the binary vector and metadata stored under `id`.
+    pub fn get_binary(&self, id: impl Into<Id>) -> Option<(BinaryVector, Metadata)> {
+        if !self.metric.is_binary() {
+            return None;
+        }
+        let entry = &self.entries[*self.ids.get(&id.into())?];
+        let words = self.index.binary_feature(entry.nodes.start).clone();
+        Some((BinaryVector::from_words(self.dim, words), entry.metadata.clone()))
+    }
+
+    /// Whether a live entry holds more than one vector.
+    pub fn has_multi_vector_entries(&self) -> bool {
+        self.entries.iter().any(|e| !e.deleted && e.nodes.len() > 1)
+    }
+
+    pub fn contains(&self, id: impl Into<Id>) -> bool {
+        self.ids.contains_key(&id.into())
+    }
+
+    /// Ids of the live entries, in the order they were added.
+    pub fn ids(&self) -> impl Iterator<Item = &Id> {
+        self.entries.iter().filter(|e| !e.deleted).map(|e| &e.id)
+    }
+
+    pub fn metadata(&self, id: impl Into<Id>) -> Option<&Metadata> {
+        Some(&self.entries[*self.ids.get(&id.into())?].metadata)
+    }
+
+    /// Number of live entries.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Live entry count of `namespace`.
+    pub fn namespace_len(&self, namespace: &str) -> usize {
+        self.namespaces.get(namespace).copied().unwrap_or(0)
+    }
+
+    /// All non-empty namespaces with their live entry counts.
+    pub fn namespaces(&self) -> Vec<(&str, usize)> {
+        let mut namespaces: Vec<(&str, usize)> = self
+            .namespaces
+            .iter()
+            .map(|(ns, count)| (ns.as_str(), *count))
+            .collect();
+        namespaces.sort();
+        namespaces
+    }
+
+    /// Removes the entry stored under `id`. Its vectors leave the graph
+    /// right away, with their neighbors relinked around them, so removed
+    /// entries cost nothing at search time.
+    pub fn remove(&mut self, id: impl Into<Id>) -> Result<()> {
+        let id = id.into();
+        let pos = *self.ids.get(&id).ok_or(anyhow!("not found"))?;
+        self.mark_deleted(pos);
+        self.storage.append_entry(self.collection, &StoredEntry::tombstone(id))?;
         Ok(())
     }
 
-    pub fn update(&mut self, id: usize, vector: Vec<f32>, metadata: Metadata) -> Result<()> {
-        if vector.len() != self.dim {
-            return Err(anyhow!("dimension mismatch"));
+    /// Removes every entry of `namespace`, returning how many were removed.
+    pub fn remove_namespace(&mut self, namespace: &str) -> Result<usize> {
+        let positions: Vec<usize> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| !e.deleted && e.namespace.as_deref() == Some(namespace))
+            .map(|(i, _)| i)
+            .collect();
+        let tombs: Vec<StoredEntry> = positions
+            .iter()
+            .map(|&pos| StoredEntry::tombstone(self.entries[pos].id.clone()))
+            .collect();
+        self.storage.append_entries(self.collection, &tombs)?;
+        for &pos in &positions {
+            self.mark_deleted(pos);
         }
-        let pos = self.entries.iter_mut().position(|e| e.id == id && !e.deleted)
-            .ok_or(anyhow!("not found"))?;
-        self.entries[pos].deleted = true;
-        self.ids.remove(&id);
-        let tomb = StoredEntry { id, vector: Vec::new(), metadata: Metadata::default(), deleted: true };
-        self.storage.append_entry(&tomb)?;
-        self.add(id, vector, metadata)
+        Ok(positions.len())
     }
 
-    pub fn search_batch(&self, queries: &[Vec<f32>], k: usize) -> Result<Vec<Vec<SearchResult>>> {
+    pub fn update(&mut self, id: impl Into<Id>, vector: Vec<f32>, metadata: Metadata) -> Result<()> {
+        self.check_query(&vector)?;
+        element::check(&vector, self.options.element_type)?;
+        let id = id.into();
+        let pos = *self.ids.get(&id).ok_or(anyhow!("not found"))?;
+        // the namespace and sparse vector carry over to the new value
+        let namespace = self.entries[pos].namespace.clone();
+        let sparse = self.sparse.get(pos).cloned();
+        self.mark_deleted(pos);
+        self.storage.append_entry(self.collection, &StoredEntry::tombstone(id.clone()))?;
+        self.add_entry(StoredEntry {
+            namespace,
+            sparse,
+            ..StoredEntry::new(id, vector, metadata)
+        })
+    }
+
+    pub fn search_batch(&self, queries: &[Vec<f32>], k: usize) -> Result<Vec<SearchResults>> {
         queries.par_iter().map(|q| self.search(q, k)).collect()
     }
+
+    /// Ids of the `k` entries nearest to each of `queries`, nearest first,
+    /// found by comparing every query with every entry.
+    pub fn ground_truth(&self, queries: &[Vec<f32>], k: usize) -> Result<Vec<Vec<Id>>> {
+        queries
+            .par_iter()
+            .map(|q| {
+                self.check_query(q)?;
+                let hits = self.exact_search(q, k, |e| !e.deleted)?;
+                Ok(hits.into_iter().map(|(pos, _)| self.entries[pos].id.clone()).collect())
+            })
+            .collect()
+    }
+
+    /// Measures recall@k and latency of [`VectorDB::search_with_ef`] for each
+    /// of `ef_values`, against `ground_truth` as returned by
+    /// [`VectorDB::ground_truth`]. Queries run one at a time on the calling
+    /// thread.
+    pub fn evaluate(
+        &self,
+        queries: &[Vec<f32>],
+        ground_truth: &[Vec<Id>],
+        k: usize,
+        ef_values: &[usize],
+    ) -> Result<Vec<Evaluation>> {
+        if queries.len() != ground_truth.len() {
+            return Err(anyhow!("ground truth must have one row per query"));
+        }
+        if queries.is_empty() {
+            return Err(anyhow!("no queries"));
+        }
+        ef_values
+            .iter()
+            .map(|&ef| {
+                eval::measure(ef, ground_truth, k, |i| self.search_with_ef(&queries[i], k, ef))
+            })
+            .collect()
+    }
+
+    /// Sets `ef_search` to the smallest beam at which searches for `sample`
+    /// find a share of at least `target_recall` of their true
+    /// [`AUTOTUNE_K`] nearest entries, and stores it in the file. `sample`
+    /// should be queries held out from the collection. When the graph needed
+    /// a beam wider than it was built with, [`Tuning::rebuild`] suggests
+    /// settings for a new collection.
+    pub fn autotune(&mut self, target_recall: f64, sample: &[Vec<f32>]) -> Result<Tuning> {
+        if !(target_recall > 0.0 && target_recall <= 1.0) {
+            return Err(anyhow!("target recall must be in (0, 1]"));
+        }
+        if self.is_empty() {
+            return Err(anyhow!("no vectors"));
+        }
+        let truth = self.ground_truth(sample, AUTOTUNE_K)?;
+        // searches never use a beam narrower than twice the results, so
+        // smaller values would save an ef that was not what was measured
+        let min_ef = 2 * AUTOTUNE_K.min(self.len());
+        let max_ef = self.index.len().max(min_ef);
+        let evaluation = eval::tune(target_recall, min_ef, max_ef, |ef| {
+            Ok(self.evaluate(sample, &truth, AUTOTUNE_K, &[ef])?.remove(0))
+        })?;
+        self.params.ef_search = evaluation.ef_search;
+        self.storage.update_header(&self.header())?;
+        let ef_construction = self.params.ef_construction;
+        let graph = self.options.index == IndexKind::Hnsw;
+        let rebuild = (graph && evaluation.ef_search > ef_construction).then(|| Rebuild {
+            m: if evaluation.ef_search > 2 * ef_construction { 2 * M } else { M },
+            ef_construction: evaluation.ef_search.next_power_of_two(),
+        });
+        Ok(Tuning { evaluation, rebuild })
+    }
+
+    pub(crate) fn collection_id(&self) -> u32 {
+        self.collection
+    }
+}
+
+/// Element-wise mean of `vectors`, which must not be empty.
+fn mean(vectors: &[Vec<f32>]) -> Vec<f32> {
+    let mut sum = vec![0.0; vectors[0].len()];
+    for v in vectors {
+        for (s, x) in sum.iter_mut().zip(v) {
+            *s += x;
+        }
+    }
+    let n = vectors.len() as f32;
+    sum.iter().map(|s| s / n).collect()
+}
+
+/// Checks that the parameter of `fusion` gives finite scores.
+fn check_fusion(fusion: Fusion) -> Result<()> {
+    match fusion {
+        Fusion::ReciprocalRank { k } if !(k >= 0.0 && k.is_finite()) => {
+            Err(anyhow!("fusion k must be a finite number of at least 0"))
+        }
+        Fusion::Weighted { alpha } if !(0.0..=1.0).contains(&alpha) => {
+            Err(anyhow!("fusion alpha must be between 0 and 1"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Rescales scores to `[0, 1]`. A list of equal scores maps to 1.
+fn min_max(hits: &[(usize, f32)]) -> Vec<(usize, f32)> {
+    let min = hits.iter().map(|h| h.1).fold(f32::INFINITY, f32::min);
+    let max = hits.iter().map(|h| h.1).fold(f32::NEG_INFINITY, f32::max);
+    hits.iter()
+        .map(|&(pos, score)| {
+            let scaled = if max > min { (score - min) / (max - min) } else { 1.0 };
+            (pos, scaled)
+        })
+        .collect()
 }
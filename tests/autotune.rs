@@ -0,0 +1,76 @@
+mod common;
+
+use anyhow::Result;
+use common::{meta, vectors};
+use std::fs;
+use vdb::{Metric, Params, VectorDB, AUTOTUNE_K};
+
+#[test]
+fn tunes_and_persists_ef_search() -> Result<()> {
+    let path = "autotune.vdb";
+    let _ = fs::remove_file(path);
+    let params = Params {
+        ef_construction: 8,
+        ef_search: 500,
+    };
+    let sample = vectors(30, 16, 2);
+    let ef = {
+        let mut db = VectorDB::<12, 24>::open_with_params(path, Metric::Euclidean, params)?;
+        for (i, v) in vectors(3000, 16, 1).into_iter().enumerate() {
+            db.add(i, v, meta(""))?;
+        }
+        let tuning = db.autotune(0.98, &sample)?;
+        let ef = tuning.evaluation.ef_search;
+        assert!(tuning.evaluation.recall >= 0.98);
+        assert_eq!(db.params().ef_search, ef);
+        assert_eq!(tuning.rebuild.is_some(), ef > params.ef_construction);
+        // one less misses the target
+        let truth = db.ground_truth(&sample, AUTOTUNE_K)?;
+        if ef > 2 * AUTOTUNE_K {
+            assert!(db.evaluate(&sample, &truth, AUTOTUNE_K, &[ef - 1])?[0].recall < 0.98);
+        }
+        ef
+    };
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    assert_eq!(db.params().ef_search, ef);
+    assert_eq!(db.params().ef_construction, 8);
+
+    assert!(db.autotune(0.0, &sample).is_err());
+    assert!(db.autotune(1.5, &sample).is_err());
+    assert!(db.autotune(0.9, &[vec![0.0; 2]]).is_err());
+    // a beam over the whole collection is exact
+    assert_eq!(db.autotune(1.0, &sample)?.evaluation.recall, 1.0);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn target_reached_at_the_narrowest_beam() -> Result<()> {
+    let path = "autotune_small.vdb";
+    let _ = fs::remove_file(path);
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    for (i, v) in vectors(500, 8, 3).into_iter().enumerate() {
+        db.add(i, v, meta(""))?;
+    }
+    let sample = vectors(10, 8, 4);
+    let tuning = db.autotune(0.1, &sample)?;
+    // searches widen any smaller beam to this, so it is what was measured
+    assert_eq!(tuning.evaluation.ef_search, 2 * AUTOTUNE_K);
+    assert_eq!(db.params().ef_search, 2 * AUTOTUNE_K);
+    let truth = db.ground_truth(&sample, AUTOTUNE_K)?;
+    let narrower = db.evaluate(&sample, &truth, AUTOTUNE_K, &[AUTOTUNE_K])?.remove(0);
+    assert_eq!(narrower.recall, tuning.evaluation.recall);
+    drop(db);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn empty_collection() -> Result<()> {
+    let path = "autotune_empty.vdb";
+    let _ = fs::remove_file(path);
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Cosine)?;
+    assert!(db.autotune(0.9, &vectors(3, 4, 1)).is_err());
+    fs::remove_file(path)?;
+    Ok(())
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use anyhow::Result;
+use common::meta;
+use std::fs;
+use vdb::{BinaryVector, Database, Metric, Params};
+
+fn bits(s: &str) -> BinaryVector {
+    BinaryVector::from_bits(&s.chars().map(|c| c == '1').collect::<Vec<_>>())
+}
+
+#[test]
+fn binary_vector() -> Result<()> {
+    let a = bits("1100");
+    assert_eq!(a.len(), 4);
+    assert_eq!(a.words(), &[0b0011]);
+    assert!(a.get(1) && !a.get(2));
+    assert_eq!(a.hamming(&bits("1010")), 2);
+    assert_eq!(a.jaccard(&bits("1110")), 2.0 / 3.0);
+    assert_eq!(bits("0000").jaccard(&bits("0000")), 1.0);
+
+    assert_eq!(BinaryVector::new(70, vec![1, 1])?.count_ones(), 2);
+    assert!(BinaryVector::new(70, vec![1]).is_err());
+    assert!(BinaryVector::new(4, vec![1 << 4]).is_err());
+    Ok(())
+}
+
+#[test]
+fn binary_collections() -> Result<()> {
+    let path = "binary.vdb";
+    let _ = fs::remove_file(path);
+    {
+        let mut db = Database::<12, 24>::open(path)?;
+        let hashes = db.create_collection("hashes", Metric::Hamming, Params::default())?;
+        hashes.add_binary(1, bits("11110000"), meta("a"))?;
+        hashes.add_binary(2, bits("11100000"), meta("b"))?;
+        hashes.add_binary(3, bits("00001111"), meta("c"))?;
+        assert!(hashes.add_binary(4, bits("1111"), meta("short")).is_err());
+        assert!(hashes.add(4, vec![1.0; 8], meta("dense")).is_err());
+
+        let prints = db.create_collection("prints", Metric::Jaccard, Params::default())?;
+        prints.add_binary(1, bits("1111000000"), meta("a"))?;
+        prints.add_binary(2, bits("1000000000"), meta("b"))?;
+        prints.add_binary(3, bits("1111111100"), meta("c"))?;
+
+        let dense = db.create_collection("dense", Metric::Euclidean, Params::default())?;
+        assert!(dense.add_binary(1, bits("1"), meta("bits")).is_err());
+    }
+    let db = Database::<12, 24>::open(path)?;
+    let hashes = db.collection("hashes")?;
+    assert_eq!(hashes.dimension(), 8);
+    assert_eq!(hashes.get_binary(2).unwrap().0, bits("11100000"));
+    assert!(hashes.get(2).is_none());
+
+    let results = hashes.search_binary(&bits("11110001"), 3)?;
+    assert!(results.iter().map(|r| &r.id).eq(&[1usize, 2, 3]));
+    assert_eq!(results[0].distance, 1.0);
+    assert_eq!(results[1].distance, 2.0);
+    assert!(hashes.search(&[0.0; 8], 3).is_err());
+    assert!(hashes.search_binary(&bits("1111"), 3).is_err());
+
+    // 3 shares all four bits but has four more, 2 shares one of one
+    let prints = db.collection("prints")?;
+    let results = prints.search_binary(&bits("1111000000"), 3)?;
+    assert!(results.iter().map(|r| &r.id).eq(&[1usize, 3, 2]));
+    assert_eq!(results[1].distance, 0.5);
+    assert_eq!(results[2].distance, 0.75);
+    fs::remove_file(path)?;
+    Ok(())
+}
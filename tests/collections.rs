@@ -0,0 +1,143 @@
+mod common;
+
+use anyhow::Result;
+use common::meta;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use vdb::{Database, Metadata, Metric, Params, VectorDB};
+
+#[test]
+fn multiple_collections() -> Result<()> {
+    let path = "collections.vdb";
+    let _ = fs::remove_file(path);
+    {
+        let mut db = Database::<12, 24>::open(path)?;
+        db.create_collection("titles", Metric::Cosine, Params::default())?;
+        db.create_collection("images", Metric::Euclidean, Params::default())?;
+        db.collection_mut("titles")?.add(1, vec![1.0, 0.0, 0.0], meta("t1"))?;
+        db.collection_mut("images")?.add(1, vec![0.0, 1.0], meta("i1"))?;
+        db.collection_mut("images")?.add(2, vec![5.0, 5.0], meta("i2"))?;
+        assert!(db.create_collection("titles", Metric::Cosine, Params::default()).is_err());
+    }
+    {
+        let mut db = Database::<12, 24>::open(path)?;
+        let names: Vec<&str> = db.collection_names().collect();
+        assert_eq!(names, vec!["images", "titles"]);
+        let titles = db.collection("titles")?;
+        assert_eq!(titles.dimension(), 3);
+        assert_eq!(titles.metric(), Metric::Cosine);
+        let images = db.collection("images")?;
+        assert_eq!(images.dimension(), 2);
+        assert_eq!(images.len(), 2);
+        let results = images.search(&[5.0, 4.0], 1)?;
+        assert_eq!(results[0].metadata.label, "i2");
+
+        db.drop_collection("titles")?;
+        assert!(db.collection("titles").is_err());
+    }
+    {
+        let mut db = Database::<12, 24>::open(path)?;
+        assert!(db.collection("titles").is_err());
+        let titles = db.create_collection("titles", Metric::Euclidean, Params::default())?;
+        assert!(titles.is_empty());
+        titles.add(7, vec![1.0], meta("new"))?;
+    }
+    {
+        let db = Database::<12, 24>::open(path)?;
+        let titles = db.collection("titles")?;
+        assert_eq!(titles.len(), 1);
+        assert_eq!(titles.search(&[1.0], 1)?[0].id, 7);
+        assert_eq!(db.collection("images")?.len(), 2);
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn default_collection() -> Result<()> {
+    let path = "default_collection.vdb";
+    let _ = fs::remove_file(path);
+    {
+        let mut db = VectorDB::<12, 24>::open(path, Metric::Cosine)?;
+        db.add(1, vec![1.0, 0.0], meta("a"))?;
+    }
+    {
+        let mut db = Database::<12, 24>::open(path)?;
+        assert_eq!(db.collection(vdb::DEFAULT_COLLECTION)?.len(), 1);
+        db.create_collection("other", Metric::Euclidean, Params::default())?;
+    }
+    let db = VectorDB::<12, 24>::open(path, Metric::Cosine)?;
+    assert_eq!(db.search(&[1.0, 0.0], 1)?[0].id, 1);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct V1Entry {
+    id: usize,
+    vector: Vec<f32>,
+    metadata: Metadata,
+    deleted: bool,
+}
+
+#[test]
+fn migrate_version_1() -> Result<()> {
+    let path = "v1.vdb";
+    let _ = fs::remove_file(path);
+    let mut bytes = bincode::serialize(&(*b"VDB0", 1u8, Metric::Euclidean, 2u32))?;
+    for (id, vector) in [(1, vec![0.0, 0.0]), (2, vec![3.0, 3.0])] {
+        let entry = V1Entry {
+            id,
+            vector,
+            metadata: meta("old"),
+            deleted: false,
+        };
+        bytes.extend(bincode::serialize(&entry)?);
+    }
+    fs::write(path, &bytes)?;
+    // reading what a file needs leaves the old layout alone
+    assert_eq!(vdb::stored_m(path)?, None);
+    assert_eq!(fs::read(path)?, bytes);
+    {
+        let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+        assert!(!Path::new("v1.vdb.tmp").exists());
+        assert_eq!(db.len(), 2);
+        db.add(3, vec![1.0, 1.0], meta("new"))?;
+    }
+    let db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    assert_eq!(db.len(), 3);
+    assert_eq!(db.search(&[3.0, 2.9], 1)?[0].id, 2);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn reopening_keeps_the_file() -> Result<()> {
+    let path = "collections_reopen.vdb";
+    let _ = fs::remove_file(path);
+    let params = Params {
+        ef_construction: 100,
+        ef_search: 40,
+    };
+    VectorDB::<12, 24>::open_with_params(path, Metric::Euclidean, params)?.add(
+        1,
+        vec![1.0, 0.0],
+        meta("a"),
+    )?;
+    let len = fs::metadata(path)?.len();
+    for _ in 0..3 {
+        VectorDB::<12, 24>::open_with_params(path, Metric::Euclidean, params)?;
+    }
+    assert_eq!(fs::metadata(path)?.len(), len);
+    let changed = Params {
+        ef_search: 80,
+        ..params
+    };
+    VectorDB::<12, 24>::open_with_params(path, Metric::Euclidean, changed)?;
+    assert!(fs::metadata(path)?.len() > len);
+    let db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    assert_eq!(db.params(), changed);
+    fs::remove_file(path)?;
+    Ok(())
+}
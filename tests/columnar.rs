@@ -0,0 +1,166 @@
+mod common;
+
+use anyhow::Result;
+use arrow::array::{
+    Array, ArrayRef, Float64Builder, Int64Array, ListBuilder, RecordBatch, StringArray,
+};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::ipc::writer::StreamWriter;
+use common::{meta, metadata, vectors};
+use parquet::arrow::ArrowWriter;
+use std::fs::{self, File};
+use std::sync::Arc;
+use vdb::{
+    is_table, CollectionOptions, Id, IdKind, Metadata, Metric, Params, TableReader, TableWriter,
+    VectorDB,
+};
+
+#[test]
+fn parquet_and_arrow_round_trips() -> Result<()> {
+    // more rows than a written batch holds
+    let data = vectors(10_000, 8, 1);
+    for kind in [IdKind::Int, IdKind::Str] {
+        let id = |i: usize| match kind {
+            IdKind::Int => Id::Int(i),
+            _ => Id::Str(format!("doc-{i}")),
+        };
+        for file in ["columnar.parquet", "columnar.arrow"] {
+            assert!(is_table(file));
+            let mut writer = TableWriter::create(file, kind, 8)?;
+            for (i, v) in data.iter().enumerate() {
+                writer.write(&id(i), v, &metadata(i))?;
+            }
+            assert!(writer.write(&id(0), &[0.0; 3], &Metadata::default()).is_err());
+            assert!(writer.write(&Id::Uuid(1), &data[0], &Metadata::default()).is_err());
+            writer.finish()?;
+            let mut rows = 0;
+            for (i, row) in TableReader::open(file, kind)?.enumerate() {
+                let row = row?;
+                assert_eq!(row.number, i + 1);
+                let record = row.record?;
+                assert_eq!((&record.id, &record.vector), (&id(i), &data[i]));
+                assert_eq!(record.metadata, metadata(i));
+                rows += 1;
+            }
+            assert_eq!(rows, data.len());
+            fs::remove_file(file)?;
+        }
+    }
+    assert!(!is_table("columnar.csv"));
+    assert!(TableWriter::create("columnar.csv", IdKind::Int, 8).is_err());
+    Ok(())
+}
+
+#[test]
+fn export_and_import() -> Result<()> {
+    let path = "columnar_export.vdb";
+    let copy = "columnar_export_copy.vdb";
+    let _ = fs::remove_file(path);
+    let data = vectors(300, 8, 2);
+    let options = CollectionOptions {
+        id_kind: IdKind::Uuid,
+        ..CollectionOptions::default()
+    };
+    let mut db = VectorDB::<12, 24>::open_with_options(
+        path,
+        Metric::Euclidean,
+        Params::default(),
+        options.clone(),
+    )?;
+    let id = |i: usize| Id::Uuid(0xfeed_0000_0000_0000_0000_0000_0000_0000 + i as u128);
+    let entries = (0..data.len()).map(|i| (id(i), data[i].clone(), metadata(i))).collect();
+    db.add_batch(entries)?;
+    db.remove(id(5))?;
+
+    let file = "columnar_export.parquet";
+    let mut writer = TableWriter::create(file, IdKind::Uuid, 8)?;
+    for id in db.ids() {
+        let (vector, metadata) = db.get(id.clone()).unwrap();
+        writer.write(id, &vector, &metadata)?;
+    }
+    writer.finish()?;
+    let _ = fs::remove_file(copy);
+    let mut imported =
+        VectorDB::<12, 24>::open_with_options(copy, Metric::Euclidean, Params::default(), options)?;
+    let reader = TableReader::open(file, IdKind::Uuid)?;
+    let added = imported.import(reader, 64, |_, e| Err(anyhow::anyhow!("{e}")), |_| {})?;
+    assert_eq!(added, data.len() - 1);
+    assert_eq!(imported.ids().collect::<Vec<_>>(), db.ids().collect::<Vec<_>>());
+    for i in [0, 3, 4, 299] {
+        assert_eq!(imported.get(id(i)), Some((data[i].clone(), metadata(i))));
+    }
+    assert!(!imported.contains(id(5)));
+    assert_eq!(imported.search(&data[42], 1)?[0].id, id(42));
+    drop(imported);
+    fs::remove_file(copy)?;
+    fs::remove_file(file)?;
+    drop(db);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// A batch as other tools write one: `i64` ids, vectors as variable-size
+/// lists of `f64`, nulls and no description column.
+fn foreign_batch() -> Result<RecordBatch> {
+    let mut vectors = ListBuilder::new(Float64Builder::new());
+    for row in [Some(vec![1.0, 0.0]), None, Some(vec![0.0, 1.0, 0.0]), Some(vec![0.5, 0.5])] {
+        match row {
+            Some(row) => {
+                vectors.values().append_slice(&row);
+                vectors.append(true);
+            }
+            None => vectors.append(false),
+        }
+    }
+    let vectors = vectors.finish();
+    let schema = Schema::new(vec![
+        Field::new("label", DataType::Utf8, true),
+        Field::new("id", DataType::Int64, true),
+        Field::new("vector", vectors.data_type().clone(), true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(vec![Some("a"), Some("b"), Some("c"), None])),
+        Arc::new(Int64Array::from(vec![Some(10), Some(11), Some(12), Some(13)])),
+        Arc::new(vectors),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+#[test]
+fn foreign_tables() -> Result<()> {
+    let path = "columnar_foreign.vdb";
+    let _ = fs::remove_file(path);
+    let batch = foreign_batch()?;
+    let parquet = File::create("columnar_foreign.parquet")?;
+    let mut writer = ArrowWriter::try_new(parquet, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    let stream = File::create("columnar_foreign.arrows")?;
+    let mut writer = StreamWriter::try_new(stream, &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+
+    for file in ["columnar_foreign.parquet", "columnar_foreign.arrows"] {
+        let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+        let mut rejected = Vec::new();
+        let reject = |row: &vdb::Line, e: &anyhow::Error| {
+            rejected.push((row.number, e.to_string()));
+            Ok(())
+        };
+        let added = db.import(TableReader::open(file, IdKind::Int)?, 10, reject, |_| {})?;
+        assert_eq!(added, 2);
+        assert_eq!(rejected.iter().map(|r| r.0).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(rejected[0].1, "missing vector");
+        assert_eq!(rejected[1].1, "dimension mismatch");
+        assert_eq!(db.get(10).unwrap(), (vec![1.0, 0.0], meta("a")));
+        assert_eq!(db.get(13).unwrap(), (vec![0.5, 0.5], Metadata::default()));
+        drop(db);
+        fs::remove_file(path)?;
+        fs::remove_file(file)?;
+    }
+
+    fs::write("columnar_missing.arrow", b"not arrow")?;
+    assert!(TableReader::open("columnar_missing.arrow", IdKind::Int).is_err());
+    fs::remove_file("columnar_missing.arrow")?;
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Fixtures shared by the integration tests. Each test binary uses only some
+//! of them.
+#![allow(dead_code)]
+
+use anyhow::Result;
+use vdb::{Metadata, VectorDB};
+
+/// Metadata with just a label.
+pub fn meta(label: &str) -> Metadata {
+    Metadata {
+        label: label.into(),
+        description: None,
+    }
+}
+
+/// Deterministic pseudo-random vectors in `[-1, 1)`.
+pub fn vectors(n: usize, dim: usize, seed: u64) -> Vec<Vec<f32>> {
+    let mut state = seed;
+    (0..n)
+        .map(|_| {
+            (0..dim)
+                .map(|_| {
+                    state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                    (state >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Metadata of the `i`th entry, with characters that need quoting in CSV
+/// and JSON and a description on every other entry.
+pub fn metadata(i: usize) -> Metadata {
+    Metadata {
+        label: format!("label, \"{i}\""),
+        description: i.is_multiple_of(2).then(|| format!("entry\n{i}")),
+    }
+}
+
+/// Positions of the `k` vectors of `data` nearest to `query` by Euclidean
+/// distance, among those `keep` accepts.
+pub fn exact(
+    data: &[Vec<f32>],
+    query: &[f32],
+    k: usize,
+    keep: impl Fn(usize) -> bool,
+) -> Vec<usize> {
+    let mut d: Vec<(f32, usize)> = data
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| keep(i))
+        .map(|(i, v)| (v.iter().zip(query).map(|(a, b)| (a - b) * (a - b)).sum(), i))
+        .collect();
+    d.sort_by(|a, b| a.0.total_cmp(&b.0));
+    d.into_iter().take(k).map(|(_, i)| i).collect()
+}
+
+/// Share of the true `k` nearest neighbors of each query that `db` returns,
+/// where the entry with id `i` holds `data[i]` if `keep(i)`.
+pub fn recall(
+    db: &VectorDB,
+    data: &[Vec<f32>],
+    queries: &[Vec<f32>],
+    k: usize,
+    keep: impl Fn(usize) -> bool,
+) -> Result<f32> {
+    let mut hits = 0;
+    for q in queries {
+        let expected = exact(data, q, k, &keep);
+        let results = db.search(q, k)?;
+        assert!(results.complete);
+        assert!(results.iter().all(|r| db.contains(r.id.clone())));
+        hits += results.iter().filter(|r| expected.iter().any(|&e| r.id == e)).count();
+    }
+    Ok(hits as f32 / (queries.len() * k) as f32)
+}
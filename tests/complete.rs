@@ -0,0 +1,75 @@
+mod common;
+
+use anyhow::Result;
+use common::{exact, meta, vectors};
+use std::fs;
+use vdb::{BinaryVector, Metric, Params, VectorDB};
+
+#[test]
+fn namespace_far_from_query() -> Result<()> {
+    let path = "complete_namespace.vdb";
+    let _ = fs::remove_file(path);
+    let params = Params {
+        ef_construction: 40,
+        ef_search: 10,
+    };
+    let mut db = VectorDB::<12, 24>::open_with_params(path, Metric::Euclidean, params)?;
+    // The query sits among the entries of "near", so a beam sized by the
+    // share of "far" finds none of them at first.
+    for (i, v) in vectors(3000, 2, 1).into_iter().enumerate() {
+        db.add_in("near", i, v, meta("near"))?;
+    }
+    let far: Vec<Vec<f32>> = vectors(300, 2, 2)
+        .into_iter()
+        .map(|v| v.iter().map(|x| x + 100.0).collect())
+        .collect();
+    for (i, v) in far.iter().enumerate() {
+        db.add_in("far", 10_000 + i, v.clone(), meta("far"))?;
+    }
+
+    let expected = exact(&far, &[0.0, 0.0], 10, |_| true);
+    let results = db.search_in(&["far"], &[0.0, 0.0], 10)?;
+    assert!(results.complete);
+    assert_eq!(results.len(), 10);
+    assert!(results.iter().zip(&expected).all(|(r, &i)| r.id == 10_000 + i));
+
+    let results = db.search_in(&["far"], &[0.0, 0.0], 500)?;
+    assert!(!results.complete);
+    assert_eq!(results.len(), 300);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn completeness_flag() -> Result<()> {
+    let path = "complete_flag.vdb";
+    let _ = fs::remove_file(path);
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    for (i, v) in vectors(100, 4, 3).into_iter().enumerate() {
+        db.add(i, v, meta(""))?;
+    }
+    for i in 0..60 {
+        db.remove(i)?;
+    }
+    let query = [0.0; 4];
+    assert!(db.search(&query, 40)?.complete);
+    let results = db.search(&query, 50)?;
+    assert!(!results.complete);
+    assert_eq!(results.len(), 40);
+    assert!(db.search(&query, 0)?.complete);
+    assert_eq!(db.search_by_id(70, 39)?.len(), 39);
+    assert!(!db.search_by_id(70, 40)?.complete);
+
+    let bits = "complete_bits.vdb";
+    let _ = fs::remove_file(bits);
+    let mut db = VectorDB::<12, 24>::open(bits, Metric::Hamming)?;
+    for i in 0..50u64 {
+        db.add_binary(i as usize, BinaryVector::new(64, vec![i * 0x9e37_79b9])?, meta(""))?;
+    }
+    let query = BinaryVector::new(64, vec![0])?;
+    assert!(db.search_binary(&query, 50)?.complete);
+    assert!(!db.search_binary(&query, 51)?.complete);
+    fs::remove_file(path)?;
+    fs::remove_file(bits)?;
+    Ok(())
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use anyhow::Result;
+use common::meta;
+use serde::Serialize;
+use std::fs;
+use vdb::{CollectionOptions, Metadata, Metric, Params, VectorDB};
+
+fn fill(db: &mut VectorDB) -> Result<()> {
+    db.add(1, vec![3.0, 4.0], meta("a"))?;
+    db.add(2, vec![0.0, 5.0], meta("b"))?;
+    db.add(3, vec![1e-7, 0.0], meta("tiny"))?;
+    db.add(4, vec![0.0, 0.0], meta("zero"))?;
+    Ok(())
+}
+
+#[test]
+fn normalized_vectors() -> Result<()> {
+    let path = "cosine.vdb";
+    let _ = fs::remove_file(path);
+    let options = CollectionOptions {
+        keep_magnitude: false,
+        ..Default::default()
+    };
+    {
+        let mut db =
+            VectorDB::<12, 24>::open_with_options(path, Metric::Cosine, Params::default(), options)?;
+        fill(&mut db)?;
+    }
+    let db = VectorDB::<12, 24>::open(path, Metric::Cosine)?;
+    assert!(!db.options().keep_magnitude);
+    assert_eq!(db.get(1).unwrap().0, vec![0.6, 0.8]);
+
+    // magnitude does not matter, and tiny vectors still have a direction
+    let results = db.search(&[2.0, 0.0], 4)?;
+    assert!(results.iter().map(|r| &r.id).eq(&[3usize, 1, 2, 4]));
+    assert_eq!(results[0].distance, 0.0);
+    assert_eq!(results[2].distance, 1.0);
+    // a zero vector has no direction and is never close
+    assert_eq!(results[3].distance, 1.0);
+
+    // nor is a zero query close to anything
+    let results = db.search(&[0.0, 0.0], 4)?;
+    assert_eq!(results.len(), 4);
+    assert!(results.iter().all(|r| r.distance == 1.0));
+    assert_eq!(db.search_by_id(4, 3)?.len(), 3);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn kept_magnitudes() -> Result<()> {
+    let path = "cosine_magnitude.vdb";
+    let _ = fs::remove_file(path);
+    {
+        // the default
+        let mut db = VectorDB::<12, 24>::open(path, Metric::Cosine)?;
+        fill(&mut db)?;
+        db.add_multi(5, vec![vec![0.5, 2.0], vec![-1.0, 0.0]], meta("multi"))?;
+    }
+    let db = VectorDB::<12, 24>::open(path, Metric::Cosine)?;
+    assert!(db.options().keep_magnitude);
+    assert_eq!(db.get(1).unwrap().0, vec![3.0, 4.0]);
+    assert_eq!(db.get(4).unwrap().0, vec![0.0, 0.0]);
+    assert_eq!(db.get_vectors(5), Some(vec![vec![0.5, 2.0], vec![-1.0, 0.0]]));
+    let entries = db.iter_entries().collect::<Result<Vec<_>>>()?;
+    assert_eq!(entries[0].1, vec![vec![3.0, 4.0]]);
+    assert_eq!(entries[4].1, vec![vec![0.5, 2.0], vec![-1.0, 0.0]]);
+
+    let results = db.search(&[0.0, 1.0], 2)?;
+    assert!(results.iter().map(|r| &r.id).eq(&[2usize, 5]));
+    assert_eq!(results[0].distance, 0.0);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn entries_find_themselves() -> Result<()> {
+    let path = "cosine_self.vdb";
+    let _ = fs::remove_file(path);
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Cosine)?;
+    let mut state = 7u64;
+    let mut data = Vec::new();
+    for i in 0..300 {
+        let v: Vec<f32> = (0..24)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+            })
+            .collect();
+        db.add(i, v.clone(), meta(""))?;
+        data.push(v);
+    }
+    // the dot product of a unit vector with itself can round to just above 1
+    for (i, v) in data.iter().enumerate() {
+        let results = db.search(v, 3)?;
+        assert_eq!(results[0].id, i);
+        assert!(results[0].distance < 1e-6);
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct V1Entry {
+    id: usize,
+    vector: Vec<f32>,
+    metadata: Metadata,
+    deleted: bool,
+}
+
+/// A database file of the first layout, from before vectors were
+/// normalized, holding `vectors` under ids from 1.
+fn version_1(path: &str, metric: Metric, vectors: &[Vec<f32>]) -> Result<()> {
+    let mut bytes = bincode::serialize(&(*b"VDB0", 1u8, metric, 2u32))?;
+    for (i, vector) in vectors.iter().enumerate() {
+        let entry = V1Entry {
+            id: i + 1,
+            vector: vector.clone(),
+            metadata: meta("old"),
+            deleted: false,
+        };
+        bytes.extend(bincode::serialize(&entry)?);
+    }
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+#[test]
+fn old_files_keep_magnitudes() -> Result<()> {
+    let path = "cosine_old.vdb";
+    let _ = fs::remove_file(path);
+    version_1(path, Metric::Cosine, &[vec![3.0, 4.0], vec![0.0, 0.0]])?;
+    for _ in 0..2 {
+        // once as written, once migrated
+        let db = VectorDB::<12, 24>::open(path, Metric::Cosine)?;
+        assert!(db.options().keep_magnitude);
+        assert_eq!(db.get(1).unwrap().0, vec![3.0, 4.0]);
+        assert_eq!(db.search(&[6.0, 8.0], 1)?[0].id, 1);
+    }
+    fs::remove_file(path)?;
+
+    // other metrics ignore the setting, so default options still match
+    version_1(path, Metric::Euclidean, &[vec![3.0, 4.0]])?;
+    let options = CollectionOptions::default();
+    let db =
+        VectorDB::<12, 24>::open_with_options(path, Metric::Euclidean, Params::default(), options)?;
+    assert_eq!(db.get(1).unwrap().0, vec![3.0, 4.0]);
+    drop(db);
+    fs::remove_file(path)?;
+    Ok(())
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use anyhow::Result;
+use common::{meta, recall, vectors};
+use std::fs;
+use vdb::{Metric, Params, VectorDB};
+
+/// Entries left after the churn below: one in five, plus some that were
+/// removed and added again.
+fn live(i: usize) -> bool {
+    i.is_multiple_of(5) || i % 10 == 6
+}
+
+#[test]
+fn search_after_churn() -> Result<()> {
+    let path = "deletion.vdb";
+    let _ = fs::remove_file(path);
+    let data = vectors(600, 8, 1);
+    let queries = vectors(20, 8, 99);
+    let params = Params {
+        ef_construction: 100,
+        ef_search: 40,
+    };
+    {
+        let mut db = VectorDB::<12, 24>::open_with_params(path, Metric::Euclidean, params)?;
+        for (i, v) in data.iter().enumerate() {
+            db.add(i, v.clone(), meta(""))?;
+        }
+        for i in (0..data.len()).filter(|i| !i.is_multiple_of(5)) {
+            db.remove(i)?;
+        }
+        for i in (0..data.len()).filter(|i| i % 10 == 6) {
+            db.add(i, data[i].clone(), meta("again"))?;
+        }
+        assert_eq!(db.len(), (0..data.len()).filter(|&i| live(i)).count());
+        assert!(recall(&db, &data, &queries, 10, live)? >= 0.9);
+    }
+    let db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    assert!(recall(&db, &data, &queries, 10, live)? >= 0.9);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn remove_everything() -> Result<()> {
+    let path = "deletion_all.vdb";
+    let _ = fs::remove_file(path);
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    for round in 0..3 {
+        for i in 0..100 {
+            db.add(i, vec![i as f32, round as f32], meta(""))?;
+        }
+        for i in 0..100 {
+            db.update(i, vec![i as f32, -1.0], meta("moved"))?;
+        }
+        let results = db.search(&[42.0, -1.0], 3)?;
+        assert_eq!(results[0].id, 42);
+        assert_eq!(results[0].distance, 0.0);
+        for i in 0..100 {
+            db.remove(i)?;
+        }
+        assert!(db.is_empty());
+        assert!(db.search(&[0.0, 0.0], 3)?.is_empty());
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
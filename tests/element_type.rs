@@ -0,0 +1,107 @@
+mod common;
+
+use anyhow::Result;
+use common::{meta, vectors};
+use half::{bf16, f16};
+use std::fs;
+use vdb::{CollectionOptions, Database, ElementType, Metric, Params, VectorDB};
+
+fn with_type(element_type: ElementType) -> CollectionOptions {
+    CollectionOptions {
+        element_type,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn half_precision_collections() -> Result<()> {
+    let path = "element_type.vdb";
+    let _ = fs::remove_file(path);
+    let data = vectors(150, 32, 0x2545_f491_4f6c_dd1d);
+    {
+        let mut db = Database::<12, 24>::open(path)?;
+        for (name, t) in [("full", ElementType::F32), ("half", ElementType::F16), ("brain", ElementType::Bf16)] {
+            let c = db.create_collection_with_options(name, Metric::Euclidean, Params::default(), with_type(t))?;
+            for (i, v) in data.iter().enumerate() {
+                c.add(i, v.clone(), meta(&i.to_string()))?;
+            }
+        }
+        let bad = db.create_collection_with_options("bits", Metric::Hamming, Params::default(), with_type(ElementType::F16));
+        assert!(bad.is_err());
+    }
+    let db = Database::<12, 24>::open(path)?;
+    let half = db.collection("half")?;
+    assert_eq!(half.options().element_type, ElementType::F16);
+    let rounded: Vec<f32> = data[5].iter().map(|&x| f16::from_f32(x).to_f32()).collect();
+    assert_eq!(half.get(5).unwrap().0, rounded);
+    let brain = db.collection("brain")?;
+    let rounded: Vec<f32> = data[5].iter().map(|&x| bf16::from_f32(x).to_f32()).collect();
+    assert_eq!(brain.get(5).unwrap().0, rounded);
+
+    // queries are rounded like the stored vectors, so an entry finds itself
+    let full = db.collection("full")?;
+    for c in [full, half, brain] {
+        let results = c.search(&data[42], 5)?;
+        assert_eq!(results[0].id, 42);
+        assert_eq!(results[0].distance, 0.0);
+    }
+    let expected: Vec<_> = full.search(&data[7], 10)?.into_iter().map(|r| r.id).collect();
+    let found: Vec<_> = half.search(&data[7], 10)?.into_iter().map(|r| r.id).collect();
+    assert!(found.iter().filter(|id| expected.contains(id)).count() >= 8);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn half_precision_is_smaller_on_disk() -> Result<()> {
+    let data = vectors(100, 128, 0x2545_f491_4f6c_dd1d);
+    let mut sizes = Vec::new();
+    for (path, t) in [("element_f32.vdb", ElementType::F32), ("element_f16.vdb", ElementType::F16)] {
+        let _ = fs::remove_file(path);
+        {
+            let mut db = VectorDB::<12, 24>::open_with_options(path, Metric::Cosine, Params::default(), with_type(t))?;
+            for (i, v) in data.iter().enumerate() {
+                db.add(i, v.clone(), meta(""))?;
+            }
+        }
+        sizes.push(fs::metadata(path)?.len());
+        let db = VectorDB::<12, 24>::open(path, Metric::Cosine)?;
+        assert_eq!(db.len(), 100);
+        assert_eq!(db.search(&data[3], 1)?[0].id, 3);
+        fs::remove_file(path)?;
+    }
+    // vector components take 2 bytes instead of 4
+    assert!(sizes[1] < sizes[0] * 6 / 10);
+    Ok(())
+}
+
+#[test]
+fn values_must_fit_the_element_type() -> Result<()> {
+    let path = "element_range.vdb";
+    let _ = fs::remove_file(path);
+    let options = with_type(ElementType::F16);
+    let mut db =
+        VectorDB::<12, 24>::open_with_options(path, Metric::Euclidean, Params::default(), options)?;
+    assert!(db.add(1, vec![70000.0, 0.0], meta("large")).is_err());
+    assert!(db.add(1, vec![f32::NAN, 0.0], meta("nan")).is_err());
+    db.add(1, vec![65504.0, 0.0], meta("largest"))?;
+    assert!(db.add_batch(vec![(2.into(), vec![0.0, -1e6], meta("large"))]).is_err());
+    assert!(db.update(1, vec![f32::INFINITY, 0.0], meta("inf")).is_err());
+    assert_eq!(db.get(1).unwrap().0, vec![65504.0, 0.0]);
+    assert!(db.search(&[70000.0, 0.0], 1).is_err());
+    assert_eq!(db.search(&[65504.0, 0.0], 1)?[0].distance, 0.0);
+    assert_eq!(db.len(), 1);
+
+    // full precision only turns away values that are not finite
+    let full_path = "element_range_f32.vdb";
+    let _ = fs::remove_file(full_path);
+    let mut full = VectorDB::<12, 24>::open(full_path, Metric::Euclidean)?;
+    full.add(1, vec![70000.0, 0.0], meta("large"))?;
+    assert!(full.add(2, vec![f32::INFINITY, 0.0], meta("inf")).is_err());
+    assert!(full.search(&[f32::NAN, 0.0], 1).is_err());
+    drop(full);
+    fs::remove_file(full_path)?;
+    drop(db);
+    fs::remove_file(path)?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use anyhow::Result;
+use common::{exact, meta, vectors};
+use std::fs;
+use vdb::{Id, Metric, Params, VectorDB};
+
+#[test]
+fn ground_truth_is_exact() -> Result<()> {
+    let path = "eval_truth.vdb";
+    let _ = fs::remove_file(path);
+    let data = vectors(500, 8, 1);
+    let queries = vectors(10, 8, 2);
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    for (i, v) in data.iter().enumerate() {
+        db.add(i, v.clone(), meta(""))?;
+    }
+    db.remove(0)?;
+    let truth = db.ground_truth(&queries, 5)?;
+    for (q, ids) in queries.iter().zip(&truth) {
+        let expected: Vec<Id> = exact(&data, q, 5, |i| i != 0).into_iter().map(Id::Int).collect();
+        assert_eq!(ids, &expected);
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn recall_and_latency_sweep() -> Result<()> {
+    let path = "eval_sweep.vdb";
+    let _ = fs::remove_file(path);
+    let params = Params {
+        ef_construction: 20,
+        ef_search: 10,
+    };
+    let mut db = VectorDB::<12, 24>::open_with_params(path, Metric::Euclidean, params)?;
+    for (i, v) in vectors(2000, 16, 3).into_iter().enumerate() {
+        db.add(i, v, meta(""))?;
+    }
+    let queries = vectors(30, 16, 4);
+    let truth = db.ground_truth(&queries, 10)?;
+    let sweep = db.evaluate(&queries, &truth, 10, &[10, 4000])?;
+    assert_eq!(sweep.len(), 2);
+    assert_eq!(sweep[0].ef_search, 10);
+    // a beam wider than the collection falls back to a full scan
+    assert_eq!(sweep[1].recall, 1.0);
+    assert!(sweep[0].recall <= sweep[1].recall);
+    for e in &sweep {
+        assert!(e.qps > 0.0);
+        assert!(e.p50 <= e.p99);
+    }
+
+    assert!(db.evaluate(&queries, &truth[1..], 10, &[10]).is_err());
+    assert!(db.ground_truth(&[vec![0.0; 3]], 10).is_err());
+    fs::remove_file(path)?;
+    Ok(())
+}
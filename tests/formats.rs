@@ -0,0 +1,395 @@
+mod common;
+
+use anyhow::Result;
+use common::vectors;
+use std::fs;
+use vdb::{read_ground_truth, Id, Metadata, Metric, VectorDB, VectorFile};
+
+/// Writes rows in the `.*vecs` layout, each component given as bytes.
+fn write_vecs<T>(path: &str, rows: &[Vec<T>], bytes: impl Fn(&T) -> Vec<u8>) -> Result<()> {
+    let mut out = Vec::new();
+    for row in rows {
+        out.extend((row.len() as i32).to_le_bytes());
+        for x in row {
+            out.extend(bytes(x));
+        }
+    }
+    fs::write(path, out)?;
+    Ok(())
+}
+
+#[test]
+fn vecs_files() -> Result<()> {
+    let data = vectors(200, 8, 1);
+    write_vecs("formats.fvecs", &data, |x| x.to_le_bytes().to_vec())?;
+    let file = VectorFile::open("formats.fvecs", "")?;
+    assert_eq!((file.len(), file.dim()), (200, 8));
+    let read: Vec<Vec<f32>> = file.collect::<Result<_>>()?;
+    assert_eq!(read, data);
+
+    let bytes: Vec<Vec<u8>> = vec![vec![0, 1, 255], vec![7, 8, 9]];
+    write_vecs("formats.bvecs", &bytes, |x| vec![*x])?;
+    let read: Vec<Vec<f32>> = VectorFile::open("formats.bvecs", "")?.collect::<Result<_>>()?;
+    assert_eq!(read, vec![vec![0.0, 1.0, 255.0], vec![7.0, 8.0, 9.0]]);
+
+    let neighbors: Vec<Vec<i32>> = vec![vec![3, 1], vec![0, 2]];
+    write_vecs("formats.ivecs", &neighbors, |x| x.to_le_bytes().to_vec())?;
+    let truth = read_ground_truth("formats.ivecs")?;
+    assert_eq!(truth, vec![vec![Id::Int(3), Id::Int(1)], vec![Id::Int(0), Id::Int(2)]]);
+
+    // a vector of another dimension, in a file of a plausible size
+    let mixed = vec![vec![1.0f32, 2.0], vec![3.0, 4.0, 5.0, 6.0, 7.0]];
+    write_vecs("formats_mixed.fvecs", &mixed, |x| x.to_le_bytes().to_vec())?;
+    let read: Result<Vec<Vec<f32>>> = VectorFile::open("formats_mixed.fvecs", "")?.collect();
+    assert!(read.is_err());
+    fs::write("formats_short.fvecs", [2, 0, 0, 0, 1, 2, 3])?;
+    assert!(VectorFile::open("formats_short.fvecs", "").is_err());
+    assert!(VectorFile::open("formats.txt", "").is_err());
+    for path in ["formats.fvecs", "formats.bvecs", "formats.ivecs"] {
+        fs::remove_file(path)?;
+    }
+    fs::remove_file("formats_mixed.fvecs")?;
+    fs::remove_file("formats_short.fvecs")?;
+    Ok(())
+}
+
+#[test]
+fn bulk_import() -> Result<()> {
+    let path = "formats_import.vdb";
+    let _ = fs::remove_file(path);
+    let data = vectors(500, 8, 2);
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    let batch = |range: std::ops::Range<usize>| {
+        range.map(|i| (Id::Int(i), data[i].clone(), Metadata::default())).collect::<Vec<_>>()
+    };
+    db.add_batch(batch(0..300))?;
+    db.add_batch(batch(300..500))?;
+    // a duplicate id rejects the whole batch
+    assert!(db.add_batch(vec![
+        (Id::Int(900), data[0].clone(), Metadata::default()),
+        (Id::Int(900), data[1].clone(), Metadata::default()),
+    ])
+    .is_err());
+    assert!(db.add_batch(vec![(Id::Int(901), vec![0.0; 3], Metadata::default())]).is_err());
+    assert_eq!(db.len(), 500);
+    drop(db);
+    let db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    assert_eq!(db.len(), 500);
+    assert_eq!(db.get(417).unwrap().0, data[417]);
+    assert_eq!(db.search(&data[123], 1)?[0].id, 123);
+    fs::remove_file(path)?;
+
+    // the first batch sets the dimension only if it is added
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    let bad = vec![
+        (Id::Int(0), vec![0.0; 4], Metadata::default()),
+        (Id::Int(1), vec![0.0; 5], Metadata::default()),
+    ];
+    assert!(db.add_batch(bad).is_err());
+    db.add_batch(vec![(Id::Int(0), vec![0.0; 5], Metadata::default())])?;
+    assert_eq!(db.dimension(), 5);
+    drop(db);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+const UNDEFINED: u64 = u64::MAX;
+
+/// Little-endian bytes of a sequence of fields.
+#[derive(Default)]
+struct Bytes(Vec<u8>);
+
+impl Bytes {
+    fn raw(mut self, b: &[u8]) -> Self {
+        self.0.extend_from_slice(b);
+        self
+    }
+
+    fn u8(self, x: u8) -> Self {
+        self.raw(&[x])
+    }
+
+    fn u16(self, x: u16) -> Self {
+        self.raw(&x.to_le_bytes())
+    }
+
+    fn u32(self, x: u32) -> Self {
+        self.raw(&x.to_le_bytes())
+    }
+
+    fn u64(self, x: u64) -> Self {
+        self.raw(&x.to_le_bytes())
+    }
+
+    fn pad(mut self, to: usize) -> Self {
+        while !self.0.len().is_multiple_of(to) {
+            self.0.push(0);
+        }
+        self
+    }
+}
+
+/// File image with structures placed at chosen addresses.
+struct Image(Vec<u8>);
+
+impl Image {
+    fn put(&mut self, at: u64, bytes: &[u8]) {
+        let at = at as usize;
+        if self.0.len() < at + bytes.len() {
+            self.0.resize(at + bytes.len(), 0);
+        }
+        self.0[at..at + bytes.len()].copy_from_slice(bytes);
+    }
+}
+
+fn dataspace(version: u8, rows: u64, cols: u64) -> Vec<u8> {
+    let head = Bytes::default().u8(version).u8(2).u8(0);
+    let head = if version == 1 { head.raw(&[0; 5]) } else { head.u8(1) };
+    head.u64(rows).u64(cols).0
+}
+
+fn float_type(size: u32) -> Vec<u8> {
+    let (exponent, mantissa, bias) = if size == 4 { (8, 23, 127) } else { (11, 52, 1023) };
+    let bits = 8 * size as u16;
+    Bytes::default()
+        .u8(0x11)
+        .raw(&[0x20, bits as u8 - 1, 0])
+        .u32(size)
+        .u16(0)
+        .u16(bits)
+        .u8(mantissa)
+        .u8(exponent)
+        .u8(0)
+        .u8(mantissa)
+        .u32(bias)
+        .0
+}
+
+fn int_type(size: u32) -> Vec<u8> {
+    Bytes::default().u8(0x10).raw(&[0x08, 0, 0]).u32(size).u16(0).u16(8 * size as u16).0
+}
+
+fn contiguous(address: u64, size: u64) -> Vec<u8> {
+    Bytes::default().u8(3).u8(1).u64(address).u64(size).0
+}
+
+fn compact(data: &[u8]) -> Vec<u8> {
+    Bytes::default().u8(3).u8(0).u16(data.len() as u16).raw(data).0
+}
+
+/// Version 1 object header.
+fn header_v1(messages: &[(u16, Vec<u8>)]) -> Vec<u8> {
+    let mut body = Bytes::default();
+    for (kind, data) in messages {
+        let data = Bytes::default().raw(data).pad(8).0;
+        body = body.u16(*kind).u16(data.len() as u16).u8(0).raw(&[0; 3]).raw(&data);
+    }
+    let head = Bytes::default().u8(1).u8(0).u16(messages.len() as u16).u32(1);
+    head.u32(body.0.len() as u32).raw(&[0; 4]).raw(&body.0).0
+}
+
+/// Version 2 messages, as found in a header chunk.
+fn messages_v2(messages: &[(u8, Vec<u8>)]) -> Vec<u8> {
+    let mut body = Bytes::default();
+    for (kind, data) in messages {
+        body = body.u8(*kind).u16(data.len() as u16).u8(0).raw(data);
+    }
+    body.0
+}
+
+/// Version 2 object header with a 4-byte chunk size and a dummy checksum.
+fn header_v2(messages: &[(u8, Vec<u8>)]) -> Vec<u8> {
+    let body = messages_v2(messages);
+    Bytes::default().raw(b"OHDR").u8(2).u8(0x02).u32(body.len() as u32).raw(&body).u32(0).0
+}
+
+fn floats(rows: &[Vec<f32>]) -> Vec<u8> {
+    rows.iter().flatten().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn ints(rows: &[Vec<i32>]) -> Vec<u8> {
+    rows.iter().flatten().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn f64s(rows: &[Vec<f32>]) -> Vec<u8> {
+    rows.iter().flatten().flat_map(|&x| (x as f64).to_le_bytes()).collect()
+}
+
+/// An ann-benchmarks file as h5py writes it by default: superblock 0 and a
+/// root group indexed by a symbol table.
+fn write_v0(path: &str, train: &[Vec<f32>], test: &[Vec<f32>], neighbors: &[Vec<i32>]) -> Result<()> {
+    const ROOT: u64 = 96;
+    const TREE: u64 = 200;
+    const NODE: u64 = 300;
+    const HEAP: u64 = 500;
+    const NAMES: u64 = 540;
+    const TRAIN: u64 = 600;
+    const TEST: u64 = 800;
+    const NEIGHBORS: u64 = 1000;
+    const DATA: u64 = 2048;
+    let mut image = Image(Vec::new());
+    let train_data = floats(train);
+    let test_data = f64s(test);
+    let neighbor_data = ints(neighbors);
+
+    let root_entry = Bytes::default().u64(0).u64(ROOT).u32(1).u32(0).u64(TREE).u64(HEAP);
+    let superblock = Bytes::default()
+        .raw(b"\x89HDF\r\n\x1a\n")
+        .raw(&[0, 0, 0, 0, 0])
+        .u8(8)
+        .u8(8)
+        .u8(0)
+        .u16(4)
+        .u16(16)
+        .u32(0)
+        .u64(0)
+        .u64(UNDEFINED)
+        .u64(4096)
+        .u64(UNDEFINED)
+        .raw(&root_entry.0);
+    assert_eq!(superblock.0.len(), 96);
+    image.put(0, &superblock.0);
+    let symbol_table = Bytes::default().u64(TREE).u64(HEAP).0;
+    image.put(ROOT, &header_v1(&[(0x0011, symbol_table)]));
+
+    let tree = Bytes::default().raw(b"TREE").u8(0).u8(0).u16(1).u64(UNDEFINED).u64(UNDEFINED);
+    image.put(TREE, &tree.u64(0).u64(NODE).u64(24).0);
+    let names = Bytes::default().raw(b"\0").pad(8).raw(b"train\0").pad(8);
+    let names = names.raw(b"test\0").pad(8).raw(b"neighbors\0").pad(8).0;
+    let heap = Bytes::default().raw(b"HEAP").u8(0).raw(&[0; 3]).u64(names.len() as u64);
+    image.put(HEAP, &heap.u64(UNDEFINED).u64(NAMES).0);
+    image.put(NAMES, &names);
+    let mut node = Bytes::default().raw(b"SNOD").u8(1).u8(0).u16(3);
+    for (name, header) in [(8, TRAIN), (16, TEST), (24, NEIGHBORS)] {
+        node = node.u64(name).u64(header).u32(0).u32(0).raw(&[0; 16]);
+    }
+    image.put(NODE, &node.0);
+
+    let cols = |rows: &[Vec<f32>]| rows[0].len() as u64;
+    image.put(
+        TRAIN,
+        &header_v1(&[
+            (0x0001, dataspace(1, train.len() as u64, cols(train))),
+            (0x0003, float_type(4)),
+            (0x0008, contiguous(DATA, train_data.len() as u64)),
+        ]),
+    );
+    image.put(
+        TEST,
+        &header_v1(&[
+            (0x0001, dataspace(1, test.len() as u64, cols(test))),
+            (0x0003, float_type(8)),
+            (0x0008, compact(&test_data)),
+        ]),
+    );
+    image.put(
+        NEIGHBORS,
+        &header_v1(&[
+            (0x0001, dataspace(1, neighbors.len() as u64, neighbors[0].len() as u64)),
+            (0x0003, int_type(4)),
+            (0x0008, contiguous(DATA + train_data.len() as u64, neighbor_data.len() as u64)),
+        ]),
+    );
+    image.put(DATA, &train_data);
+    image.put(DATA + train_data.len() as u64, &neighbor_data);
+    fs::write(path, image.0)?;
+    Ok(())
+}
+
+/// The same datasets in the layout of h5py with `libver="latest"`:
+/// superblock 2 and version 2 object headers whose links spill into a
+/// continuation chunk.
+fn write_v2(path: &str, train: &[Vec<f32>], neighbors: &[Vec<i32>]) -> Result<()> {
+    const ROOT: u64 = 48;
+    const MORE: u64 = 200;
+    const TRAIN: u64 = 300;
+    const NEIGHBORS: u64 = 500;
+    const DATA: u64 = 1024;
+    let mut image = Image(Vec::new());
+    let train_data = floats(train);
+    let neighbor_data = ints(neighbors);
+    let superblock = Bytes::default()
+        .raw(b"\x89HDF\r\n\x1a\n")
+        .u8(2)
+        .u8(8)
+        .u8(8)
+        .u8(0)
+        .u64(0)
+        .u64(UNDEFINED)
+        .u64(8192)
+        .u64(ROOT)
+        .u32(0);
+    image.put(0, &superblock.0);
+    let link = |name: &str, address: u64| {
+        Bytes::default().u8(1).u8(0).u8(name.len() as u8).raw(name.as_bytes()).u64(address).0
+    };
+    let more = messages_v2(&[(0x06, link("neighbors", NEIGHBORS))]);
+    let chunk = Bytes::default().raw(b"OCHK").raw(&more).u32(0).0;
+    let continuation = Bytes::default().u64(MORE).u64(chunk.len() as u64).0;
+    image.put(ROOT, &header_v2(&[(0x06, link("train", TRAIN)), (0x10, continuation)]));
+    image.put(MORE, &chunk);
+    image.put(
+        TRAIN,
+        &header_v2(&[
+            (0x01, dataspace(2, train.len() as u64, train[0].len() as u64)),
+            (0x03, float_type(4)),
+            (0x08, contiguous(DATA, train_data.len() as u64)),
+        ]),
+    );
+    image.put(
+        NEIGHBORS,
+        &header_v2(&[
+            (0x01, dataspace(2, neighbors.len() as u64, neighbors[0].len() as u64)),
+            (0x03, int_type(4)),
+            (0x08, compact(&neighbor_data)),
+        ]),
+    );
+    image.put(DATA, &train_data);
+    fs::write(path, image.0)?;
+    Ok(())
+}
+
+#[test]
+fn hdf5_files() -> Result<()> {
+    let train = vectors(20, 4, 3);
+    let test = vectors(3, 4, 4);
+    let neighbors = vec![vec![5, 2, 19], vec![0, 1, 2], vec![7, 7, 7]];
+    let expected: Vec<Vec<Id>> = neighbors
+        .iter()
+        .map(|row| row.iter().map(|&i| Id::Int(i as usize)).collect())
+        .collect();
+
+    write_v0("formats_v0.hdf5", &train, &test, &neighbors)?;
+    let file = VectorFile::open("formats_v0.hdf5", "train")?;
+    assert_eq!((file.len(), file.dim()), (20, 4));
+    assert_eq!(file.collect::<Result<Vec<_>>>()?, train);
+    // stored as f64 and narrowed
+    let read: Vec<Vec<f32>> = VectorFile::open("formats_v0.hdf5", "test")?.collect::<Result<_>>()?;
+    assert_eq!(read, test);
+    assert_eq!(read_ground_truth("formats_v0.hdf5")?, expected);
+    assert!(VectorFile::open("formats_v0.hdf5", "missing").is_err());
+
+    write_v2("formats_v2.h5", &train, &neighbors)?;
+    let read: Vec<Vec<f32>> = VectorFile::open("formats_v2.h5", "train")?.collect::<Result<_>>()?;
+    assert_eq!(read, train);
+    assert_eq!(read_ground_truth("formats_v2.h5")?, expected);
+
+    fs::write("formats_bad.h5", b"not an hdf5 file at all")?;
+    assert!(VectorFile::open("formats_bad.h5", "train").is_err());
+    // lengths read from a damaged file are checked against its size
+    let image = fs::read("formats_v2.h5")?;
+    for end in [250, 1100] {
+        fs::write("formats_bad.h5", &image[..end])?;
+        assert!(VectorFile::open("formats_bad.h5", "train").is_err());
+    }
+    let mut huge = image.clone();
+    let at = huge.windows(8).position(|w| w == 200u64.to_le_bytes()).unwrap();
+    huge[at + 8..at + 16].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+    fs::write("formats_bad.h5", huge)?;
+    let error = VectorFile::open("formats_bad.h5", "train").err().unwrap();
+    assert!(error.to_string().contains("truncated"));
+    fs::remove_file("formats_v0.hdf5")?;
+    fs::remove_file("formats_v2.h5")?;
+    fs::remove_file("formats_bad.h5")?;
+    Ok(())
+}
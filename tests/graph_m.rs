@@ -0,0 +1,56 @@
+mod common;
+
+use anyhow::Result;
+use common::meta;
+use std::fs;
+use vdb::{stored_m, CollectionOptions, Database, Metric, Params};
+
+fn with_m(m: usize) -> CollectionOptions {
+    CollectionOptions {
+        m: Some(m),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn collections_record_m() -> Result<()> {
+    let path = "graph_m.vdb";
+    let _ = fs::remove_file(path);
+    assert_eq!(stored_m(path)?, None);
+    let params = Params {
+        ef_construction: 80,
+        ef_search: 30,
+    };
+    {
+        let mut db = Database::<16, 32>::open(path)?;
+        assert_eq!(stored_m(path)?, None);
+        // the M of another build cannot be recorded
+        let other = db.create_collection_with_options("a", Metric::Euclidean, params, with_m(12));
+        assert!(other.is_err());
+        let c = db.create_collection_with_options("a", Metric::Euclidean, params, with_m(16))?;
+        c.add(1, vec![1.0, 0.0], meta("x"))?;
+        c.add(2, vec![0.0, 1.0], meta("y"))?;
+        db.create_collection("b", Metric::Cosine, Params::default())?;
+    }
+    assert_eq!(stored_m(path)?, Some(16));
+    assert!(Database::<12, 24>::open(path).is_err());
+    {
+        let db = Database::<16, 32>::open(path)?;
+        let c = db.collection("a")?;
+        assert_eq!(c.options(), &with_m(16));
+        assert_eq!((c.metric(), c.params().ef_construction), (Metric::Euclidean, 80));
+        assert_eq!(c.search(&[0.1, 0.9], 1)?[0].id, 2);
+        assert_eq!(db.collection("b")?.options().m, None);
+    }
+    fs::remove_file(path)?;
+
+    // collections without a recorded M open with any
+    {
+        let mut db = Database::<12, 24>::open(path)?;
+        db.create_collection("a", Metric::Euclidean, Params::default())?;
+    }
+    assert_eq!(stored_m(path)?, None);
+    assert!(Database::<8, 16>::open(path).is_ok());
+    fs::remove_file(path)?;
+    Ok(())
+}
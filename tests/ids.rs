@@ -0,0 +1,80 @@
+mod common;
+
+use anyhow::Result;
+use common::meta;
+use std::fs;
+use vdb::{CollectionOptions, Id, IdKind, Metric, Params, VectorDB};
+
+#[test]
+fn string_ids() -> Result<()> {
+    let path = "string_ids.vdb";
+    let _ = fs::remove_file(path);
+    let options = CollectionOptions {
+        id_kind: IdKind::Str,
+        ..Default::default()
+    };
+    {
+        let mut db = VectorDB::<12, 24>::open_with_options(
+            path,
+            Metric::Euclidean,
+            Params::default(),
+            options.clone(),
+        )?;
+        db.add("intro", vec![0.0, 0.0], meta("a"))?;
+        db.add("setup", vec![1.0, 1.0], meta("b"))?;
+        db.add("usage", vec![5.0, 5.0], meta("c"))?;
+        let err = db.add(7, vec![0.0, 0.0], meta("x")).unwrap_err();
+        assert!(err.to_string().contains("id kind"));
+        assert!(db.add("intro", vec![0.0, 0.0], meta("x")).is_err());
+
+        let results = db.search(&[0.9, 0.9], 1)?;
+        assert_eq!(results[0].id, "setup");
+        db.remove("setup")?;
+        assert!(db.remove("setup").is_err());
+        db.update("usage", vec![2.0, 2.0], meta("d"))?;
+    }
+    {
+        let db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+        assert_eq!(db.options(), &options);
+        assert!(!db.contains("setup"));
+        let (vector, metadata) = db.get("usage").unwrap();
+        assert_eq!(vector, vec![2.0, 2.0]);
+        assert_eq!(metadata.label, "d");
+        let results = db.search(&[0.9, 0.9], 2)?;
+        assert_eq!(results[0].id, "intro");
+        assert_eq!(results[1].id, "usage");
+    }
+    let reopened = VectorDB::<12, 24>::open_with_options(
+        path,
+        Metric::Euclidean,
+        Params::default(),
+        CollectionOptions::default(),
+    );
+    assert!(reopened.is_err());
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn uuid_ids() -> Result<()> {
+    let path = "uuid_ids.vdb";
+    let _ = fs::remove_file(path);
+    let options = CollectionOptions {
+        id_kind: IdKind::Uuid,
+        ..Default::default()
+    };
+    let id = Id::parse("67e55044-10b1-426f-9247-bb680e5fe0c8", IdKind::Uuid)?;
+    assert_eq!(id, Id::Uuid(0x67e5504410b1426f9247bb680e5fe0c8));
+    assert_eq!(id.to_string(), "67e55044-10b1-426f-9247-bb680e5fe0c8");
+    {
+        let mut db =
+            VectorDB::<12, 24>::open_with_options(path, Metric::Cosine, Params::default(), options)?;
+        db.add(&id, vec![1.0, 0.0], meta("a"))?;
+        db.add(Id::Uuid(1), vec![0.0, 1.0], meta("b"))?;
+    }
+    let db = VectorDB::<12, 24>::open(path, Metric::Cosine)?;
+    assert_eq!(db.search(&[1.0, 0.1], 1)?[0].id, id);
+    assert!(db.get(Id::Uuid(1)).is_some());
+    fs::remove_file(path)?;
+    Ok(())
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use anyhow::Result;
+use common::{meta, recall, vectors};
+use std::fs;
+use vdb::{
+    CollectionOptions, IndexKind, Metric, Params, Quantization, VectorDB,
+    TRAINING_POINTS_PER_LIST,
+};
+
+fn ivf(nlist: usize, nprobe: usize) -> CollectionOptions {
+    CollectionOptions {
+        index: IndexKind::Ivf { nlist, nprobe },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn trains_and_searches() -> Result<()> {
+    let path = "ivf_search.vdb";
+    let _ = fs::remove_file(path);
+    let nlist = 8;
+    let data = vectors(nlist * TRAINING_POINTS_PER_LIST + 200, 8, 1);
+    let queries = vectors(20, 8, 2);
+    let before = {
+        let mut db =
+            VectorDB::<12, 24>::open_with_options(path, Metric::Euclidean, Params::default(), ivf(nlist, 3))?;
+        for (i, v) in data.iter().enumerate() {
+            db.add(i, v.clone(), meta(""))?;
+        }
+        for q in &queries {
+            assert_eq!(db.search(q, 10)?.len(), 10);
+        }
+        assert!(recall(&db, &data, &queries, 10, |_| true)? >= 0.8);
+        db.search(&queries[0], 10)?.into_iter().map(|r| r.id).collect::<Vec<_>>()
+    };
+    // centroids and list assignments come back from the file
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    assert_eq!(db.options().index, IndexKind::Ivf { nlist, nprobe: 3 });
+    let after: Vec<_> = db.search(&queries[0], 10)?.into_iter().map(|r| r.id).collect();
+    assert_eq!(before, after);
+    assert_eq!(db.search(&data[17], 1)?[0].id, 17);
+
+    db.remove(17)?;
+    assert_ne!(db.search(&data[17], 1)?[0].id, 17);
+    db.train()?;
+    drop(db);
+    let db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    assert_eq!(db.len(), data.len() - 1);
+    assert_eq!(db.search(&data[18], 1)?[0].id, 18);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn searches_before_training() -> Result<()> {
+    let path = "ivf_untrained.vdb";
+    let _ = fs::remove_file(path);
+    let data = vectors(50, 4, 3);
+    let mut db = VectorDB::<12, 24>::open_with_options(path, Metric::Cosine, Params::default(), ivf(16, 2))?;
+    for (i, v) in data.iter().enumerate() {
+        db.add(i, v.clone(), meta(""))?;
+    }
+    // every vector is still in the single list, so the search is exact
+    assert_eq!(db.search(&data[9], 1)?[0].id, 9);
+    assert_eq!(db.search(&data[9], 50)?.len(), 50);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn rejected_options() -> Result<()> {
+    let path = "ivf_rejected.vdb";
+    let _ = fs::remove_file(path);
+    let params = Params::default();
+    assert!(VectorDB::<12, 24>::open_with_options(path, Metric::Hamming, params, ivf(4, 1)).is_err());
+    assert!(VectorDB::<12, 24>::open_with_options(path, Metric::Euclidean, params, ivf(0, 1)).is_err());
+    assert!(VectorDB::<12, 24>::open_with_options(path, Metric::Euclidean, params, ivf(4, 0)).is_err());
+    let quantized = CollectionOptions {
+        quantization: Quantization::Binary,
+        ..ivf(4, 1)
+    };
+    assert!(VectorDB::<12, 24>::open_with_options(path, Metric::Euclidean, params, quantized).is_err());
+    let _ = fs::remove_file(path);
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    assert!(db.train().is_err());
+    drop(db);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn parses_index_kind() {
+    assert_eq!("hnsw".parse::<IndexKind>().unwrap(), IndexKind::Hnsw);
+    assert_eq!(
+        "ivf:64:4".parse::<IndexKind>().unwrap(),
+        IndexKind::Ivf { nlist: 64, nprobe: 4 }
+    );
+    assert!("ivf:64".parse::<IndexKind>().is_err());
+    assert!("flat".parse::<IndexKind>().is_err());
+}
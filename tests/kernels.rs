@@ -0,0 +1,29 @@
+use vdb::Kernel;
+
+fn vector(len: usize, seed: f32) -> Vec<f32> {
+    (0..len).map(|i| ((i as f32 + seed) * 0.37).sin()).collect()
+}
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() <= 1e-4 * a.abs().max(b.abs()).max(1.0)
+}
+
+#[test]
+fn kernels_agree() {
+    let kernels = Kernel::available();
+    assert_eq!(kernels[0].name(), "scalar");
+    assert_eq!(Kernel::detect(), *kernels.last().unwrap());
+    let scalar = kernels[0];
+    // lengths around every register width, so that tails are covered
+    for len in [0, 1, 3, 4, 7, 8, 15, 16, 17, 33, 128, 1000] {
+        let (a, b) = (vector(len, 0.0), vector(len, 5.0));
+        let d = scalar.dot(&a, &b);
+        for kernel in &kernels {
+            assert!(close(kernel.dot(&a, &b), d), "{kernel} at {len}");
+            let l2 = scalar.squared_euclidean(&a, &b);
+            assert!(close(kernel.squared_euclidean(&a, &b), l2), "{kernel} at {len}");
+            let l1 = scalar.manhattan(&a, &b);
+            assert!(close(kernel.manhattan(&a, &b), l1), "{kernel} at {len}");
+        }
+    }
+}
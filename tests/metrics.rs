@@ -0,0 +1,55 @@
+mod common;
+
+use anyhow::Result;
+use common::meta;
+use std::fs;
+use vdb::{Database, Metric, Params};
+
+#[test]
+fn metric_names() -> Result<()> {
+    for metric in [Metric::Manhattan, Metric::Chebyshev, Metric::minkowski(3.0)] {
+        assert_eq!(metric.to_string().parse::<Metric>()?, metric);
+    }
+    assert_eq!("minkowski:1.5".parse::<Metric>()?, Metric::minkowski(1.5));
+    assert!("minkowski".parse::<Metric>().is_err());
+    assert!("minkowski:x".parse::<Metric>().is_err());
+    Ok(())
+}
+
+#[test]
+fn lp_metrics() -> Result<()> {
+    let path = "metrics.vdb";
+    let _ = fs::remove_file(path);
+    let metrics = [
+        ("l1", Metric::Manhattan),
+        ("linf", Metric::Chebyshev),
+        ("l3", Metric::minkowski(3.0)),
+    ];
+    {
+        let mut db = Database::<12, 24>::open(path)?;
+        for (name, metric) in metrics {
+            let c = db.create_collection(name, metric, Params::default())?;
+            c.add(1, vec![3.0, 0.0], meta("axis"))?;
+            c.add(2, vec![2.0, 2.0], meta("diagonal"))?;
+        }
+        let bad = db.create_collection("bad", Metric::minkowski(0.5), Params::default());
+        assert!(bad.is_err());
+    }
+    let db = Database::<12, 24>::open(path)?;
+    for (name, metric) in metrics {
+        assert_eq!(db.collection(name)?.metric(), metric);
+    }
+    // L1 prefers the axis point (3 < 4), L∞ the diagonal one (2 < 3)
+    let l1 = db.collection("l1")?.search(&[0.0, 0.0], 2)?;
+    assert!(l1.iter().map(|r| &r.id).eq(&[1usize, 2]));
+    assert_eq!(l1[0].distance, 3.0);
+    let linf = db.collection("linf")?.search(&[0.0, 0.0], 2)?;
+    assert!(linf.iter().map(|r| &r.id).eq(&[2usize, 1]));
+    assert_eq!(linf[0].distance, 2.0);
+    // 16^(1/3) ≈ 2.52 < 3
+    let l3 = db.collection("l3")?.search(&[0.0, 0.0], 2)?;
+    assert!(l3.iter().map(|r| &r.id).eq(&[2usize, 1]));
+    assert!((l3[0].distance - 16f32.cbrt()).abs() < 1e-5);
+    fs::remove_file(path)?;
+    Ok(())
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use anyhow::Result;
+use common::meta;
+use std::fs;
+use vdb::{Metric, VectorDB};
+
+#[test]
+fn mmr_diversifies() -> Result<()> {
+    let path = "mmr.vdb";
+    let _ = fs::remove_file(path);
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    // three near-duplicates next to the query and one distinct entry
+    db.add(1, vec![1.0, 0.0], meta("a"))?;
+    db.add(2, vec![1.01, 0.0], meta("a copy"))?;
+    db.add(3, vec![1.02, 0.0], meta("another copy"))?;
+    db.add(4, vec![0.0, 1.5], meta("b"))?;
+
+    let query = [0.9, 0.0];
+    let plain = db.search(&query, 2)?;
+    assert!(plain.iter().map(|r| &r.id).eq(&[1usize, 2]));
+
+    let diverse = db.search_mmr(&query, 2, 4, 0.5)?;
+    assert!(diverse.iter().map(|r| &r.id).eq(&[1usize, 4]));
+    assert_eq!(diverse[1].distance, db.search(&query, 4)?[3].distance);
+
+    // lambda 1 is plain top-k
+    let top = db.search_mmr(&query, 3, 4, 1.0)?;
+    assert!(top.iter().map(|r| &r.id).eq(&[1usize, 2, 3]));
+
+    // only the fetched candidates are considered
+    let narrow = db.search_mmr(&query, 2, 3, 0.5)?;
+    assert_eq!(narrow.len(), 2);
+    assert!(narrow.iter().all(|r| r.id != 4));
+
+    assert!(db.search_mmr(&query, 2, 4, 1.5).is_err());
+    fs::remove_file(path)?;
+    Ok(())
+}
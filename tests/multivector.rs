@@ -0,0 +1,50 @@
+mod common;
+
+use anyhow::Result;
+use common::meta;
+use std::fs;
+use vdb::{Metric, VectorDB};
+
+#[test]
+fn multi_vector_entries() -> Result<()> {
+    let path = "multivector.vdb";
+    let _ = fs::remove_file(path);
+    {
+        let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+        db.add_multi(1, vec![vec![0.0, 0.0], vec![10.0, 0.0]], meta("a"))?;
+        db.add_multi(2, vec![vec![5.0, 5.0], vec![9.0, 1.0], vec![1.0, 1.0]], meta("b"))?;
+        db.add(3, vec![3.0, 3.0], meta("c"))?;
+        assert_eq!(db.len(), 3);
+
+        assert!(db.add_multi(4, vec![], meta("empty")).is_err());
+        assert!(db.add_multi(4, vec![vec![0.0, 0.0], vec![1.0]], meta("ragged")).is_err());
+
+        // each entry comes back once, scored by its closest vector
+        let results = db.search(&[9.5, 0.0], 10)?;
+        assert!(results.iter().map(|r| &r.id).eq(&[1usize, 2, 3]));
+        assert_eq!(results[0].distance, 0.5);
+
+        assert_eq!(db.get(2).unwrap().0, vec![5.0, 5.0]);
+        assert_eq!(db.get_vectors(2).unwrap().len(), 3);
+        assert_eq!(db.get_vectors(3), Some(vec![vec![3.0, 3.0]]));
+    }
+    {
+        let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+        assert_eq!(db.get_vectors(1), Some(vec![vec![0.0, 0.0], vec![10.0, 0.0]]));
+
+        // 1 covers both query vectors exactly, 2 only comes close
+        let results = db.search_multi(&[vec![0.0, 0.0], vec![10.0, 0.0]], 3)?;
+        assert!(results.iter().map(|r| &r.id).eq(&[1usize, 2, 3]));
+        assert_eq!(results[0].distance, 0.0);
+        assert!(db.search_multi(&[vec![0.0]], 3).is_err());
+
+        assert!(db.has_multi_vector_entries());
+        db.remove(1)?;
+        let results = db.search(&[10.0, 0.0], 10)?;
+        assert!(results.iter().map(|r| &r.id).eq(&[2usize, 3]));
+        db.remove(2)?;
+        assert!(!db.has_multi_vector_entries());
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
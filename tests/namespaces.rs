@@ -0,0 +1,52 @@
+mod common;
+
+use anyhow::Result;
+use common::meta;
+use std::fs;
+use vdb::{Metric, VectorDB};
+
+#[test]
+fn namespace_isolation() -> Result<()> {
+    let path = "namespaces.vdb";
+    let _ = fs::remove_file(path);
+    {
+        let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+        for i in 0..200 {
+            let tenant = if i % 10 == 0 { "a" } else { "b" };
+            db.add_in(tenant, i, vec![i as f32, 0.0], meta(tenant))?;
+        }
+        db.add(1000, vec![0.0, 0.0], meta("none"))?;
+        assert_eq!(db.namespace_len("a"), 20);
+        assert_eq!(db.namespace_len("b"), 180);
+        assert!(db.add_in("a", 5, vec![0.0, 0.0], meta("a")).is_err());
+
+        let results = db.search_in(&["a"], &[55.0, 0.0], 5)?;
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().all(|r| r.metadata.label == "a"));
+        assert_eq!(results[0].id, 50);
+
+        let results = db.search_in(&["a", "b"], &[0.0, 0.0], 3)?;
+        assert!(results.iter().map(|r| &r.id).eq(&[0usize, 1, 2]));
+        assert!(db.search_in(&["missing"], &[0.0, 0.0], 3)?.is_empty());
+
+        db.update(50, vec![51.0, 0.0], meta("a"))?;
+        assert_eq!(db.namespace_len("a"), 20);
+    }
+    {
+        let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+        assert_eq!(db.namespaces(), vec![("a", 20), ("b", 180)]);
+        assert_eq!(db.remove_namespace("b")?, 180);
+        assert_eq!(db.namespaces(), vec![("a", 20)]);
+        assert_eq!(db.len(), 21);
+    }
+    {
+        let db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+        assert_eq!(db.namespace_len("b"), 0);
+        assert!(db.search_in(&["b"], &[1.0, 0.0], 3)?.is_empty());
+        let results = db.search(&[1.0, 0.0], 2)?;
+        assert_eq!(results[0].id, 0);
+        assert_eq!(results[1].id, 1000);
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
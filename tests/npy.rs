@@ -0,0 +1,173 @@
+mod common;
+
+use anyhow::Result;
+use common::vectors;
+use std::fs;
+use vdb::{read_ids, Id, IdKind, Metadata, Metric, NpyWriter, VectorDB, VectorFile};
+
+/// An `.npy` file of format `version` holding `data`.
+fn npy(descr: &str, shape: &str, data: &[u8], version: u8) -> Vec<u8> {
+    let header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}\n");
+    let mut out = b"\x93NUMPY".to_vec();
+    out.extend([version, 0]);
+    if version == 1 {
+        out.extend((header.len() as u16).to_le_bytes());
+    } else {
+        out.extend((header.len() as u32).to_le_bytes());
+    }
+    out.extend(header.as_bytes());
+    out.extend(data);
+    out
+}
+
+/// A zip archive storing each file without compression, as `np.savez`
+/// writes them. Checksums are left out; the reader does not look at them.
+fn zip(files: &[(&str, &[u8])], method: u16) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in files {
+        let offset = out.len() as u32;
+        let extra = [0u8; 8];
+        let mut common = Vec::new();
+        common.extend(20u16.to_le_bytes());
+        common.extend(0u16.to_le_bytes());
+        common.extend(method.to_le_bytes());
+        common.extend([0; 8]);
+        common.extend((data.len() as u32).to_le_bytes());
+        common.extend((data.len() as u32).to_le_bytes());
+        common.extend((name.len() as u16).to_le_bytes());
+        out.extend(0x0403_4b50u32.to_le_bytes());
+        out.extend(&common);
+        out.extend((extra.len() as u16).to_le_bytes());
+        out.extend(name.as_bytes());
+        out.extend(extra);
+        out.extend(*data);
+        directory.extend(0x0201_4b50u32.to_le_bytes());
+        directory.extend(20u16.to_le_bytes());
+        directory.extend(&common);
+        directory.extend([0; 12]);
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+    }
+    let start = out.len() as u32;
+    out.extend(&directory);
+    out.extend(0x0605_4b50u32.to_le_bytes());
+    out.extend([0; 4]);
+    out.extend((files.len() as u16).to_le_bytes());
+    out.extend((files.len() as u16).to_le_bytes());
+    out.extend((directory.len() as u32).to_le_bytes());
+    out.extend(start.to_le_bytes());
+    out.extend(0u16.to_le_bytes());
+    out
+}
+
+fn floats(data: &[Vec<f32>]) -> Vec<u8> {
+    data.iter().flatten().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+#[test]
+fn npy_files() -> Result<()> {
+    let data = vectors(100, 12, 1);
+    let mut writer = NpyWriter::create("npy_written.npy", 100, 12)?;
+    for v in &data {
+        writer.write(v)?;
+    }
+    assert!(writer.write(&[0.0; 3]).is_err());
+    writer.finish()?;
+    let bytes = fs::read("npy_written.npy")?;
+    // the data starts on a multiple of 64 bytes
+    assert_eq!((bytes.len() - 100 * 12 * 4) % 64, 0);
+    let file = VectorFile::open("npy_written.npy", "")?;
+    assert_eq!((file.len(), file.dim()), (100, 12));
+    assert_eq!(file.collect::<Result<Vec<_>>>()?, data);
+    fs::remove_file("npy_written.npy")?;
+    assert!(NpyWriter::create("npy_short.npy", 2, 3)?.finish().is_err());
+    fs::remove_file("npy_short.npy")?;
+
+    // float64 arrays, with a version 2 header
+    let doubles: Vec<u8> = [1.5f64, -2.0, 3.25, 4.0].iter().flat_map(|x| x.to_le_bytes()).collect();
+    fs::write("npy_doubles.npy", npy("<f8", "(2, 2)", &doubles, 2))?;
+    let read = VectorFile::open("npy_doubles.npy", "")?.collect::<Result<Vec<_>>>()?;
+    assert_eq!(read, vec![vec![1.5, -2.0], vec![3.25, 4.0]]);
+    fs::remove_file("npy_doubles.npy")?;
+
+    let ints: Vec<u8> = [7i64, 3, 12].iter().flat_map(|x| x.to_le_bytes()).collect();
+    fs::write("npy_int_ids.npy", npy("<i8", "(3,)", &ints, 1))?;
+    let ids = read_ids("npy_int_ids.npy", "", IdKind::Int)?;
+    assert_eq!(ids, vec![Id::Int(7), Id::Int(3), Id::Int(12)]);
+    fs::remove_file("npy_int_ids.npy")?;
+
+    let text: Vec<u8> = ["a", "bb", "ccc"]
+        .iter()
+        .flat_map(|s| format!("{s:\0<3}").chars().collect::<Vec<_>>())
+        .flat_map(|c| (c as u32).to_le_bytes())
+        .collect();
+    fs::write("npy_str_ids.npy", npy("<U3", "(3,)", &text, 1))?;
+    let ids = read_ids("npy_str_ids.npy", "", IdKind::Str)?;
+    assert_eq!(ids, vec![Id::Str("a".into()), Id::Str("bb".into()), Id::Str("ccc".into())]);
+    fs::write("npy_str_ids.npy", npy("|S2", "(2,)", b"9\x0042", 1))?;
+    assert_eq!(read_ids("npy_str_ids.npy", "", IdKind::Int)?, vec![Id::Int(9), Id::Int(42)]);
+    fs::remove_file("npy_str_ids.npy")?;
+
+    for (descr, shape) in [(">f4", "(1, 1)"), ("<c8", "(1, 1)"), ("<f4", "(1, 1, 1)")] {
+        fs::write("npy_bad.npy", npy(descr, shape, &[0; 8], 1))?;
+        assert!(VectorFile::open("npy_bad.npy", "").is_err());
+    }
+    let mut fortran = npy("<f4", "(1, 1)", &[0; 4], 1);
+    let at = fortran.windows(5).position(|w| w == b"False").unwrap();
+    fortran[at..at + 5].copy_from_slice(b"True ");
+    fs::write("npy_bad.npy", fortran)?;
+    assert!(VectorFile::open("npy_bad.npy", "").is_err());
+    // shapes and header lengths must fit in the file
+    for shape in ["(3, 4)", "(1000000000, 1000000000)", "(4611686018427387904, 8)"] {
+        fs::write("npy_bad.npy", npy("<f4", shape, &[0; 40], 1))?;
+        let error = VectorFile::open("npy_bad.npy", "").err().unwrap();
+        assert!(error.to_string().contains("truncated"));
+    }
+    let mut long = npy("<f4", "(1, 1)", &[0; 4], 2);
+    long[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    fs::write("npy_bad.npy", long)?;
+    assert!(VectorFile::open("npy_bad.npy", "").is_err());
+    fs::write("npy_bad.npy", npy("<f4", "(0, 1000000000000)", &[], 1))?;
+    assert_eq!(VectorFile::open("npy_bad.npy", "")?.len(), 0);
+    fs::remove_file("npy_bad.npy")?;
+    Ok(())
+}
+
+#[test]
+fn npz_archives() -> Result<()> {
+    let path = "npy_import.vdb";
+    let _ = fs::remove_file(path);
+    let data = vectors(50, 4, 2);
+    let embeddings = npy("<f4", "(50, 4)", &floats(&data), 1);
+    let ids: Vec<u8> = (0..50u32).flat_map(|i| (1000 + i).to_le_bytes()).collect();
+    let ids = npy("<u4", "(50,)", &ids, 1);
+    let archive = zip(&[("embeddings.npy", &embeddings), ("ids.npy", &ids)], 0);
+    fs::write("npy_archive.npz", archive)?;
+
+    let file = VectorFile::open("npy_archive.npz", "embeddings")?;
+    assert_eq!((file.len(), file.dim()), (50, 4));
+    let ids = read_ids("npy_archive.npz", "ids", IdKind::Int)?;
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    let entries = ids
+        .into_iter()
+        .zip(file)
+        .map(|(id, v)| Ok((id, v?, Metadata::default())))
+        .collect::<Result<Vec<_>>>()?;
+    db.add_batch(entries)?;
+    assert_eq!(db.get(1017).unwrap().0, data[17]);
+    assert_eq!(db.search(&data[30], 1)?[0].id, 1030);
+
+    let missing = VectorFile::open("npy_archive.npz", "train").err().unwrap();
+    assert!(missing.to_string().contains("embeddings, ids"));
+    fs::write("npy_archive.npz", zip(&[("embeddings.npy", &embeddings)], 8))?;
+    assert!(VectorFile::open("npy_archive.npz", "embeddings").is_err());
+    // an array may not read past its own entry into the next one
+    let longer = npy("<f4", "(60, 4)", &floats(&data), 1);
+    fs::write("npy_archive.npz", zip(&[("embeddings.npy", &longer), ("ids.npy", &[0; 400])], 0))?;
+    assert!(VectorFile::open("npy_archive.npz", "embeddings").is_err());
+    fs::remove_file("npy_archive.npz")?;
+    drop(db);
+    fs::remove_file(path)?;
+    Ok(())
+}
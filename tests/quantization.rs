@@ -0,0 +1,105 @@
+mod common;
+
+use anyhow::Result;
+use common::{meta, vectors};
+use std::fs;
+use vdb::{CollectionOptions, Database, Id, Metric, Params, Quantization, VectorDB};
+
+fn quantized() -> CollectionOptions {
+    CollectionOptions {
+        quantization: Quantization::Binary,
+        ..Default::default()
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let na: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let nb: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    1.0 - dot / (na * nb)
+}
+
+#[test]
+fn quantized_search_rescores() -> Result<()> {
+    let path = "quantization.vdb";
+    let _ = fs::remove_file(path);
+    let data = vectors(200, 32, 0x2545_f491_4f6c_dd1d);
+    {
+        let mut db = VectorDB::<12, 24>::open_with_options(
+            path,
+            Metric::Cosine,
+            Params::default(),
+            CollectionOptions {
+                keep_magnitude: true,
+                ..quantized()
+            },
+        )?;
+        for (i, v) in data.iter().enumerate() {
+            db.add(i, v.clone(), meta(&i.to_string()))?;
+        }
+        db.remove(7)?;
+    }
+    let db = VectorDB::<12, 24>::open(path, Metric::Cosine)?;
+    assert_eq!(db.options().quantization, Quantization::Binary);
+    assert_eq!(db.get(3).unwrap().0, data[3]);
+    assert!(!db.contains(7));
+    let entries = db.iter_entries().collect::<Result<Vec<_>>>()?;
+    assert_eq!(entries.len(), 199);
+    for ((id, vectors, _), i) in entries.into_iter().zip((0..200).filter(|&i| i != 7)) {
+        assert_eq!((id, vectors), (&Id::Int(i), vec![data[i].clone()]));
+    }
+
+    let query = &data[42];
+    let mut expected: Vec<(usize, f32)> = data
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != 7)
+        .map(|(i, v)| (i, cosine(query, v)))
+        .collect();
+    expected.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    // rescoring over every entry is exact
+    let results = db.search_with_oversampling(query, 5, 200.0)?;
+    assert!(results.iter().map(|r| &r.id).eq(expected[..5].iter().map(|e| &e.0)));
+    for (r, e) in results.iter().zip(&expected) {
+        assert!((r.distance - e.1).abs() < 1e-5);
+    }
+
+    // the default shortlist still finds the query itself, with its full distance
+    let results = db.search(query, 5)?;
+    assert_eq!(results.len(), 5);
+    assert_eq!(results[0].id, 42);
+    assert!(results[0].distance.abs() < 1e-5);
+    assert!(results.windows(2).all(|w| w[0].distance <= w[1].distance));
+
+    assert!(db.search_with_oversampling(query, 5, 0.5).is_err());
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn quantized_collection_features() -> Result<()> {
+    let path = "quantization_features.vdb";
+    let _ = fs::remove_file(path);
+    let mut db = Database::<12, 24>::open(path)?;
+    let bad = db.create_collection_with_options("bits", Metric::Hamming, Params::default(), quantized());
+    assert!(bad.is_err());
+
+    let c = db.create_collection_with_options("q", Metric::Euclidean, Params::default(), quantized())?;
+    c.add_multi(1, vec![vec![1.0, 1.0], vec![-5.0, -5.0]], meta("a"))?;
+    c.add_in("ns", 2, vec![2.0, 2.0], meta("b"))?;
+    c.add(3, vec![-1.0, 1.0], meta("c"))?;
+    c.update(3, vec![-1.0, 2.0], meta("c2"))?;
+
+    assert_eq!(c.get_vectors(1).unwrap(), vec![vec![1.0, 1.0], vec![-5.0, -5.0]]);
+    assert_eq!(c.get(3).unwrap().0, vec![-1.0, 2.0]);
+    let results = c.search(&[-5.0, -4.0], 3)?;
+    assert_eq!(results[0].id, 1);
+    assert_eq!(results[0].distance, 1.0);
+    let results = c.search_in(&["ns"], &[0.0, 0.0], 3)?;
+    assert!(results.iter().map(|r| &r.id).eq(&[2usize]));
+    let results = c.search_by_id(2, 1)?;
+    assert_eq!(results[0].id, 1);
+    fs::remove_file(path)?;
+    Ok(())
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use anyhow::Result;
+use common::meta;
+use std::fs;
+use vdb::{Id, Metric, Recommend, VectorDB};
+
+fn sample(path: &str) -> Result<VectorDB> {
+    let _ = fs::remove_file(path);
+    let mut db = VectorDB::open(path, Metric::Euclidean)?;
+    db.add(1, vec![0.0, 0.0], meta("origin"))?;
+    db.add(2, vec![1.0, 0.0], meta("east"))?;
+    db.add(3, vec![-1.0, 0.0], meta("west"))?;
+    db.add(4, vec![2.5, 0.0], meta("far east"))?;
+    db.add(5, vec![-2.0, 0.0], meta("far west"))?;
+    Ok(db)
+}
+
+#[test]
+fn search_by_id() -> Result<()> {
+    let path = "search_by_id.vdb";
+    let db = sample(path)?;
+    let results = db.search_by_id(2, 2)?;
+    assert!(results.iter().map(|r| &r.id).eq(&[1usize, 4]));
+    assert_eq!(db.search_by_id(1, 10)?.len(), 4);
+    assert!(db.search_by_id(9, 2).is_err());
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn recommend() -> Result<()> {
+    let path = "recommend.vdb";
+    let db = sample(path)?;
+    let positive = [Id::from(1)];
+    let negative = [Id::from(3)];
+
+    // the target moves from the origin away from the west
+    let results = db.recommend(&positive, &negative, 2, Recommend::AverageVector)?;
+    assert!(results.iter().map(|r| &r.id).eq(&[2usize, 4]));
+
+    // west side entries sit closer to the negative example and come last
+    let results = db.recommend(&positive, &negative, 3, Recommend::BestScore)?;
+    assert!(results.iter().map(|r| &r.id).eq(&[2usize, 4, 5]));
+
+    let results = db.recommend(&positive, &[], 10, Recommend::BestScore)?;
+    assert_eq!(results.len(), 4);
+    assert!(results.iter().all(|r| r.id != 1));
+
+    assert!(db.recommend(&[], &negative, 2, Recommend::default()).is_err());
+    assert!(db.recommend(&[Id::from(9)], &[], 2, Recommend::default()).is_err());
+    fs::remove_file(path)?;
+    Ok(())
+}
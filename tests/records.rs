@@ -0,0 +1,161 @@
+mod common;
+
+use anyhow::Result;
+use common::{metadata, vectors};
+use std::fs;
+use vdb::{CollectionOptions, Id, IdKind, Metric, Params, RecordReader, RecordWriter, VectorDB};
+
+/// Exports `db` to `file` and imports the file into a new collection at
+/// `copy`, which must hold the same entries.
+fn round_trip(db: &VectorDB, file: &str, copy: &str) -> Result<()> {
+    let mut writer = RecordWriter::create(file)?;
+    for id in db.ids() {
+        let vectors = db.get_vectors(id.clone()).unwrap();
+        writer.write(id, &vectors[0], db.metadata(id.clone()).unwrap())?;
+    }
+    writer.finish()?;
+
+    let _ = fs::remove_file(copy);
+    let options = db.options().clone();
+    let mut imported = VectorDB::<12, 24>::open_with_options(
+        copy,
+        db.metric(),
+        Params::default(),
+        options.clone(),
+    )?;
+    let reader = RecordReader::open(file, options.id_kind)?;
+    let mut rejected = 0;
+    let reject = |_: &_, _: &_| {
+        rejected += 1;
+        Ok(())
+    };
+    let added = imported.import(reader, 7, reject, |_| {})?;
+    assert_eq!((added, rejected), (db.len(), 0));
+    assert_eq!(imported.ids().collect::<Vec<_>>(), db.ids().collect::<Vec<_>>());
+    for id in db.ids() {
+        let (vector, metadata) = imported.get(id.clone()).unwrap();
+        let (expected, expected_metadata) = db.get(id.clone()).unwrap();
+        // cosine collections normalize the exported unit vectors again
+        assert!(vector.iter().zip(&expected).all(|(a, b)| (a - b).abs() < 1e-6));
+        assert_eq!(metadata, expected_metadata);
+    }
+    fs::remove_file(file)?;
+    fs::remove_file(copy)?;
+    Ok(())
+}
+
+#[test]
+fn jsonl_and_csv_round_trips() -> Result<()> {
+    let path = "records_round_trip.vdb";
+    let _ = fs::remove_file(path);
+    let data = vectors(50, 6, 1);
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    for (i, v) in data.iter().enumerate() {
+        db.add(i, v.clone(), metadata(i))?;
+    }
+    db.remove(17)?;
+    db.update(3, data[4].clone(), metadata(33))?;
+    round_trip(&db, "records_round_trip.jsonl", "records_round_trip_jsonl.vdb")?;
+    round_trip(&db, "records_round_trip.csv", "records_round_trip_csv.vdb")?;
+    fs::remove_file(path)?;
+
+    for kind in [IdKind::Str, IdKind::Uuid] {
+        let options = CollectionOptions {
+            id_kind: kind,
+            ..CollectionOptions::default()
+        };
+        let mut db =
+            VectorDB::<12, 24>::open_with_options(path, Metric::Cosine, Params::default(), options)?;
+        for (i, v) in data.iter().take(10).enumerate() {
+            let id = match kind {
+                IdKind::Str => Id::Str(format!("doc-{i}")),
+                _ => Id::Uuid(0x0123_4567_89ab_cdef_0000_0000_0000_0000 + i as u128),
+            };
+            db.add(id, v.clone(), metadata(i))?;
+        }
+        round_trip(&db, "records_ids.jsonl", "records_ids_jsonl.vdb")?;
+        round_trip(&db, "records_ids.csv", "records_ids_csv.vdb")?;
+        drop(db);
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn rejects() -> Result<()> {
+    let path = "records_rejects.vdb";
+    let _ = fs::remove_file(path);
+    let jsonl = "records_rejects.jsonl";
+    fs::write(
+        jsonl,
+        concat!(
+            "{\"id\": 1, \"vector\": [1, 0, 0], \"metadata\": {\"label\": \"a\"}}\n",
+            "not json\n",
+            "\n",
+            "{\"id\": 2, \"vector\": [0, 1]}\n",
+            "{\"id\": \"3\", \"vector\": [0, 0, 1]}\n",
+            "{\"id\": 1, \"vector\": [0, 0, 1]}\n",
+            "{\"id\": \"x\", \"vector\": [0, 0, 1]}\n",
+            "{\"id\": 4, \"vector\": [1, 1, 0]}\n",
+        ),
+    )?;
+    let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+    let mut rejected = Vec::new();
+    let mut progress = Vec::new();
+    let reader = RecordReader::open(jsonl, IdKind::Int)?;
+    let added = db.import(
+        reader,
+        2,
+        |line, _| {
+            rejected.push((line.number, line.text.clone()));
+            Ok(())
+        },
+        |read| progress.push(read),
+    )?;
+    assert_eq!(added, 3);
+    let numbers: Vec<usize> = rejected.iter().map(|(n, _)| *n).collect();
+    assert_eq!(numbers, vec![2, 4, 6, 7]);
+    assert_eq!(rejected[0].1, "not json");
+    assert_eq!(progress, vec![3, 5, 7]);
+    assert_eq!(db.ids().collect::<Vec<_>>(), vec![&Id::Int(1), &Id::Int(3), &Id::Int(4)]);
+    fs::remove_file(jsonl)?;
+
+    let csv = "records_rejects.csv";
+    fs::write(
+        csv,
+        concat!(
+            "label,id,vector\n",
+            "x,5,\"[1, 2, 3]\"\n",
+            "y,6,\"1,2,oops\"\n",
+            "z,7\n",
+            "\"multi\nline\",8,\"0,0,0\"\n",
+        ),
+    )?;
+    let reader = RecordReader::open(csv, IdKind::Int)?;
+    assert_eq!(reader.header(), Some("label,id,vector"));
+    rejected.clear();
+    let added = db.import(
+        reader,
+        100,
+        |line, _| {
+            rejected.push((line.number, line.text.clone()));
+            Ok(())
+        },
+        |_| {},
+    )?;
+    assert_eq!(added, 2);
+    assert_eq!(rejected, vec![(3, "y,6,\"1,2,oops\"".to_string()), (4, "z,7".to_string())]);
+    let (vector, metadata) = db.get(5).unwrap();
+    assert_eq!((vector, metadata.label), (vec![1.0, 2.0, 3.0], "x".to_string()));
+    assert_eq!(db.metadata(8).unwrap().label, "multi\nline");
+    fs::remove_file(csv)?;
+
+    assert!(RecordReader::open("records_missing.jsonl", IdKind::Int).is_err());
+    fs::write("records_no_vector.csv", "id,label\n1,a\n")?;
+    assert!(RecordReader::open("records_no_vector.csv", IdKind::Int).is_err());
+    fs::remove_file("records_no_vector.csv")?;
+    assert!(RecordWriter::create("records.txt").is_err());
+    drop(db);
+    fs::remove_file(path)?;
+    Ok(())
+}
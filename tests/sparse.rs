@@ -0,0 +1,70 @@
+mod common;
+
+use anyhow::Result;
+use common::meta;
+use std::fs;
+use vdb::{Fusion, Metric, SparseVector, VectorDB};
+
+fn sparse(pairs: &[(u32, f32)]) -> SparseVector {
+    let (indices, values) = pairs.iter().copied().unzip();
+    SparseVector::new(indices, values).unwrap()
+}
+
+#[test]
+fn sparse_vector() {
+    let a = SparseVector::new(vec![7, 2], vec![1.0, 3.0]).unwrap();
+    assert_eq!(a.indices(), &[2, 7]);
+    assert_eq!(a.values(), &[3.0, 1.0]);
+    assert_eq!(a.dot(&sparse(&[(2, 2.0), (5, 9.0), (7, 4.0)])), 10.0);
+    assert!(SparseVector::new(vec![1, 1], vec![1.0, 1.0]).is_err());
+    assert!(SparseVector::new(vec![1], vec![]).is_err());
+    for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+        assert!(SparseVector::new(vec![1, 2], vec![1.0, value]).is_err());
+    }
+}
+
+#[test]
+fn sparse_search() -> Result<()> {
+    let path = "sparse.vdb";
+    let _ = fs::remove_file(path);
+    {
+        let mut db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+        db.add_with_sparse(1, vec![0.0, 0.0], sparse(&[(10, 1.0), (20, 0.5)]), meta("a"))?;
+        db.add_with_sparse(2, vec![1.0, 0.0], sparse(&[(20, 2.0)]), meta("b"))?;
+        db.add_with_sparse(3, vec![9.0, 9.0], sparse(&[(30, 5.0)]), meta("c"))?;
+        db.add(4, vec![0.1, 0.0], meta("dense only"))?;
+
+        let results = db.search_sparse(&sparse(&[(20, 1.0)]), 10)?;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, 2);
+        assert_eq!(results[0].score, 2.0);
+        assert_eq!(results[1].id, 1);
+
+        db.update(2, vec![2.0, 0.0], meta("b2"))?;
+        assert_eq!(db.get_sparse(2), Some(&sparse(&[(20, 2.0)])));
+        db.remove(1)?;
+    }
+    {
+        let db = VectorDB::<12, 24>::open(path, Metric::Euclidean)?;
+        let results = db.search_sparse(&sparse(&[(20, 1.0), (30, 1.0)]), 10)?;
+        assert!(results.iter().map(|r| &r.id).eq(&[3usize, 2]));
+        assert!(db.get_sparse(4).is_none());
+
+        // dense alone prefers 4, sparse alone prefers 3
+        let query = sparse(&[(30, 1.0)]);
+        let dense_only = Fusion::Weighted { alpha: 1.0 };
+        let results = db.search_dense_sparse(&[0.1, 0.0], &query, 1, dense_only)?;
+        assert_eq!(results[0].id, 4);
+        let sparse_only = Fusion::Weighted { alpha: 0.0 };
+        let results = db.search_dense_sparse(&[0.1, 0.0], &query, 1, sparse_only)?;
+        assert_eq!(results[0].id, 3);
+        let results = db.search_dense_sparse(&[0.1, 0.0], &query, 3, Fusion::default())?;
+        assert_eq!(results.len(), 3);
+        let nan = Fusion::Weighted { alpha: f32::NAN };
+        assert!(db.search_dense_sparse(&[0.1, 0.0], &query, 3, nan).is_err());
+        let negative = Fusion::ReciprocalRank { k: -1.0 };
+        assert!(db.search_dense_sparse(&[0.1, 0.0], &query, 3, negative).is_err());
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
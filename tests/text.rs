@@ -0,0 +1,89 @@
+use anyhow::Result;
+use std::fs;
+use vdb::{CollectionOptions, Fusion, Metadata, Metric, Params, TextField, VectorDB};
+
+fn meta(label: &str, description: &str) -> Metadata {
+    Metadata {
+        label: label.into(),
+        description: Some(description.into()),
+    }
+}
+
+fn open(path: &str) -> Result<VectorDB<12, 24>> {
+    let options = CollectionOptions {
+        text_fields: vec![TextField::Label, TextField::Description],
+        ..Default::default()
+    };
+    VectorDB::open_with_options(path, Metric::Euclidean, Params::default(), options)
+}
+
+#[test]
+fn keyword_search() -> Result<()> {
+    let path = "text.vdb";
+    let _ = fs::remove_file(path);
+    {
+        let mut db = open(path)?;
+        db.add(1, vec![0.0, 0.0], meta("Rust book", "learn the Rust language"))?;
+        db.add(2, vec![1.0, 0.0], meta("Cooking", "pasta and rice recipes"))?;
+        db.add(3, vec![2.0, 0.0], meta("Rust belt", "history of industry"))?;
+        db.add(4, vec![3.0, 0.0], meta("Garden", "growing rice at home"))?;
+
+        let results = db.search_text("rust", 10)?;
+        assert_eq!(results.len(), 2);
+        // "rust" appears twice in entry 1
+        assert_eq!(results[0].id, 1);
+        assert_eq!(results[1].id, 3);
+        assert!(results[0].score > results[1].score);
+        assert!(db.search_text("nothing", 10)?.is_empty());
+
+        db.remove(1)?;
+        let results = db.search_text("RUST!", 10)?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, 3);
+    }
+    {
+        let db = open(path)?;
+        let results = db.search_text("rice", 10)?;
+        let ids: Vec<String> = results.iter().map(|r| r.id.to_string()).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&"2".to_string()) && ids.contains(&"4".to_string()));
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn hybrid_search() -> Result<()> {
+    let path = "hybrid.vdb";
+    let _ = fs::remove_file(path);
+    let mut db = open(path)?;
+    db.add(1, vec![0.0, 0.0], meta("apple", "red fruit"))?;
+    db.add(2, vec![0.1, 0.0], meta("car", "red vehicle"))?;
+    db.add(3, vec![5.0, 5.0], meta("apple pie", "dessert"))?;
+
+    // Vector similarity alone prefers 2, keyword alone prefers 3.
+    let results = db.search_hybrid(&[0.1, 0.0], "apple", 3, Fusion::default())?;
+    assert_eq!(results[0].id, 1);
+
+    let results = db.search_hybrid(&[0.1, 0.0], "apple", 3, Fusion::Weighted { alpha: 1.0 })?;
+    assert_eq!(results[0].id, 2);
+    let results = db.search_hybrid(&[0.1, 0.0], "apple", 1, Fusion::Weighted { alpha: 0.0 })?;
+    assert_eq!(results.len(), 1);
+    assert_ne!(results[0].id, 2);
+    for fusion in [
+        Fusion::Weighted { alpha: f32::NAN },
+        Fusion::Weighted { alpha: 1.5 },
+        Fusion::ReciprocalRank { k: -1.0 },
+        Fusion::ReciprocalRank { k: f32::INFINITY },
+    ] {
+        assert!(db.search_hybrid(&[0.1, 0.0], "apple", 3, fusion).is_err());
+    }
+    let results = db.search_hybrid(&[0.1, 0.0], "apple", 3, Fusion::ReciprocalRank { k: 0.0 })?;
+    assert_eq!(results.len(), 3);
+
+    let plain = VectorDB::<12, 24>::open("hybrid_plain.vdb", Metric::Cosine)?;
+    assert!(plain.search_text("apple", 1).is_err());
+    fs::remove_file("hybrid_plain.vdb")?;
+    fs::remove_file(path)?;
+    Ok(())
+}